@@ -4,9 +4,22 @@ use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc, Datelike};
 use std::sync::Arc;
 use tokio::sync::Mutex;
+use crate::services::ai_providers::CircuitStatus;
 use crate::services::ai_service::{AIConfig, APITestResult, ModelInfo, AIService};
-use crate::services::monitor_service::{MonitoringConfig, FocusState, MonitoringResult, MonitorService};
-use crate::services::storage_service::StorageService;
+use crate::services::classification_cache::ClassificationCache;
+use crate::services::corrections::{AppDisagreement, CorrectionStore};
+use crate::services::local_classifier::{ClassifierMetrics, LocalClassifier};
+use crate::services::monitor_service::{MonitoringConfig, FocusState, MonitoringResult, MonitorService, ScreenContent};
+use crate::services::focus_classification::{self, FocusClassification};
+use crate::services::focus_rules::{self, RuleEngine};
+use crate::services::ocr_service::{TesseractCliEngine, TesseractInfo};
+use crate::services::prompt_budget;
+use crate::services::prompt_templates::{self, PromptLanguage, PromptTemplate, PromptTemplateStore};
+use crate::services::redaction_service::{RedactionCounts, Redactor};
+use crate::services::usage_service::{BudgetFallback, BudgetMode, SpendReport, UsageService};
+use crate::services::window_info_service::ForegroundWindow;
+use crate::services::secret_service::{self, SecretStorageStatus};
+use crate::services::storage_service::{StorageService, AI_CONFIG_SECRETS, MONITORING_CONFIG_SECRETS};
 use crate::services::timer_service::TimerService;
 use crate::services::report_service::{ReportService, DailyReport, WeeklyReport};
 use crate::models::focus_session::SessionType;
@@ -99,6 +112,24 @@ pub struct TodayStats {
     pub interruption_count: u32,  // 中断次数
 }
 
+/// 保存配置的结果
+#[derive(Debug, Serialize)]
+pub struct SaveConfigResult {
+    pub message: String,
+    pub secrets_persisted: bool, // 为 false 时API密钥只在本次运行中有效，重启后丢失
+}
+
+impl SaveConfigResult {
+    fn new(saved: &str, secrets_persisted: bool) -> Self {
+        let message = if secrets_persisted {
+            saved.to_string()
+        } else {
+            format!("{}，但系统密钥服务不可用且本地密钥库未解锁，API密钥仅在本次运行中有效，请解锁或创建密钥库", saved)
+        };
+        Self { message, secrets_persisted }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TimerStatus {
     pub is_running: bool,
@@ -394,14 +425,14 @@ pub async fn get_focus_history(_days: Option<u32>) -> Result<String, String> {
 
 /// 保存AI配置
 #[command]
-pub async fn save_ai_config(config: AIConfig) -> Result<String, String> {
+pub async fn save_ai_config(config: AIConfig) -> Result<SaveConfigResult, String> {
     println!("保存AI配置: {:?}", config);
     
     let storage_service = get_storage_service().await?;
-    storage_service.save_ai_config(&config).await
+    let secrets_persisted = storage_service.save_ai_config(&config).await
         .map_err(|e| format!("保存AI配置失败: {}", e))?;
     
-    Ok("AI配置保存成功".to_string())
+    Ok(SaveConfigResult::new("AI配置保存成功", secrets_persisted))
 }
 
 /// 加载AI配置
@@ -427,6 +458,7 @@ pub async fn test_ai_api(config: AIConfig) -> Result<APITestResult, String> {
             message: format!("测试失败: {}", e),
             response_time_ms: 0,
             model_used: None,
+            answered_by: None,
         }),
     }
 }
@@ -453,18 +485,127 @@ pub async fn refresh_models(config: AIConfig) -> Result<Vec<ModelInfo>, String>
     get_available_models(config).await
 }
 
+/// 获取AI服务的可用状态（熔断器状态）
+#[command]
+pub async fn get_ai_service_status(config: AIConfig) -> Result<CircuitStatus, String> {
+    Ok(AIService::new(config).circuit_status())
+}
+
+/// 获取AI调用的用量和花费
+#[command]
+pub async fn get_ai_spend_report() -> Result<SpendReport, String> {
+    let storage_service = get_storage_service().await?;
+    let config = storage_service.load_ai_config().await
+        .map_err(|e| format!("加载AI配置失败: {}", e))?;
+    Ok(UsageService::default().spend_report(&config))
+}
+
+/// 获取API密钥的存储状态
+#[command]
+pub async fn get_secret_storage_status() -> Result<SecretStorageStatus, String> {
+    tokio::task::spawn_blocking(secret_service::status)
+        .await
+        .map_err(|e| format!("读取密钥存储状态失败: {}", e))
+}
+
+/// 解锁本地密钥库（系统密钥服务不可用时使用），首次解锁时创建
+#[command]
+pub async fn unlock_secret_vault(passphrase: String) -> Result<SecretStorageStatus, String> {
+    tokio::task::spawn_blocking(move || {
+        let migrated = secret_service::unlock_vault(&passphrase)
+            .map_err(|e| format!("解锁密钥库失败: {}", e))?;
+        println!("🔓 密钥库已解锁，写入 {} 个密钥", migrated);
+        Ok(secret_service::status())
+    })
+    .await
+    .map_err(|e| format!("解锁密钥库失败: {}", e))?
+}
+
 // ===== 监控配置相关命令 =====
 
 /// 保存监控配置
 #[command]
-pub async fn save_monitoring_config(config: MonitoringConfig) -> Result<String, String> {
+pub async fn save_monitoring_config(mut config: MonitoringConfig) -> Result<SaveConfigResult, String> {
     println!("保存监控配置: {:?}", config);
     
+    focus_rules::validate_rules(&mut config.rules)
+        .map_err(|e| format!("专注规则无效: {}", e))?;
+    
     let storage_service = get_storage_service().await?;
-    storage_service.save_monitoring_config(&config).await
+    // 白名单、黑名单或专注规则变化后，缓存的判断可能已经不再适用
+    if let Ok(previous) = storage_service.load_monitoring_config().await {
+        if previous.whitelist != config.whitelist || previous.blacklist != config.blacklist || previous.rules != config.rules {
+            match ClassificationCache::load_default().clear() {
+                Ok(removed) => println!("🧹 应用规则已变化，已清除 {} 条分类缓存", removed),
+                Err(e) => println!("⚠️ 清除分类缓存失败: {}", e),
+            }
+        }
+    }
+    let secrets_persisted = storage_service.save_monitoring_config(&config).await
         .map_err(|e| format!("保存监控配置失败: {}", e))?;
     
-    Ok("监控配置保存成功".to_string())
+    Ok(SaveConfigResult::new("监控配置保存成功", secrets_persisted))
+}
+
+/// 清除分类缓存，返回删除的记录数
+#[command]
+pub async fn clear_classification_cache() -> Result<usize, String> {
+    let removed = ClassificationCache::load_default().clear()
+        .map_err(|e| format!("清除分类缓存失败: {}", e))?;
+    println!("🧹 已清除 {} 条分类缓存", removed);
+    Ok(removed)
+}
+
+/// 标注一条监控结果的实际专注状态，记录为用户纠正
+#[command]
+pub async fn correct_monitoring_result(
+    timestamp: DateTime<Utc>,
+    focus_state: FocusState,
+    note: Option<String>,
+) -> Result<MonitoringResult, String> {
+    let storage_service = get_storage_service().await?;
+    let result = storage_service.label_monitoring_result(timestamp, focus_state.clone()).await
+        .map_err(|e| format!("保存标注失败: {}", e))?
+        .ok_or_else(|| "找不到该监控记录，可能已被清理".to_string())?;
+
+    let correction = CorrectionStore::load_default().record(&result, focus_state, note)
+        .map_err(|e| format!("保存纠正记录失败: {}", e))?;
+    if correction.disagrees() {
+        println!("✍️ 已记录纠正: {:?} → {:?}", correction.model_state, correction.user_state);
+        // 避免继续复用被纠正的缓存判断
+        match ClassificationCache::load_default().clear() {
+            Ok(removed) => println!("🧹 已清除 {} 条分类缓存", removed),
+            Err(e) => println!("⚠️ 清除分类缓存失败: {}", e),
+        }
+    } else {
+        println!("✍️ 用户确认了判断: {:?}", correction.user_state);
+    }
+    Ok(result)
+}
+
+/// 按应用统计用户标注与模型判断的差异
+#[command]
+pub async fn get_correction_stats() -> Result<Vec<AppDisagreement>, String> {
+    let storage_service = get_storage_service().await?;
+    let results = storage_service.load_monitoring_results().await
+        .map_err(|e| format!("加载监控数据失败: {}", e))?;
+    Ok(CorrectionStore::load_default().app_disagreements(&results))
+}
+
+/// 用已保存的监控记录和用户纠正重新训练本地分类器
+#[command]
+pub async fn train_local_classifier() -> Result<ClassifierMetrics, String> {
+    let storage_service = get_storage_service().await?;
+    let config = storage_service.load_monitoring_config().await.unwrap_or_default();
+    let model = LocalClassifier::train_from_storage(&config.local_classifier).await
+        .map_err(|e| format!("训练本地分类器失败: {}", e))?;
+    Ok(model.metrics)
+}
+
+/// 获取本地分类器的准确率，尚未训练时返回空
+#[command]
+pub async fn get_local_classifier_metrics() -> Result<Option<ClassifierMetrics>, String> {
+    Ok(LocalClassifier::load_default().map(|model| model.metrics))
 }
 
 /// 加载监控配置
@@ -477,6 +618,64 @@ pub async fn load_monitoring_config() -> Result<MonitoringConfig, String> {
         .map_err(|e| format!("加载监控配置失败: {}", e))
 }
 
+/// 获取所有提示词模板（内置模板和用户保存的版本）
+#[command]
+pub async fn list_prompt_templates() -> Result<Vec<PromptTemplate>, String> {
+    Ok(PromptTemplateStore::load_default().templates())
+}
+
+/// 获取某种语言当前使用的提示词模板
+#[command]
+pub async fn get_active_prompt_template(language: PromptLanguage) -> Result<PromptTemplate, String> {
+    Ok(PromptTemplateStore::load_default().active(language))
+}
+
+/// 保存提示词模板为新版本并启用
+#[command]
+pub async fn save_prompt_template(language: PromptLanguage, body: String) -> Result<PromptTemplate, String> {
+    let template = PromptTemplateStore::load_default().save_version(language, &body)
+        .map_err(|e| format!("保存提示词模板失败: {}", e))?;
+    println!("📄 已保存提示词模板: {}", template.label());
+    Ok(template)
+}
+
+/// 切换到已有的提示词模板版本（可切回内置模板）
+#[command]
+pub async fn activate_prompt_template(label: String) -> Result<PromptTemplate, String> {
+    let template = PromptTemplateStore::load_default().activate(&label)
+        .map_err(|e| format!("切换提示词模板失败: {}", e))?;
+    println!("📄 已切换提示词模板: {}", template.label());
+    Ok(template)
+}
+
+/// 检测Tesseract版本和已安装的语言
+///
+/// 未传入路径时使用已保存的监控配置。
+#[command]
+pub async fn get_tesseract_info(
+    tesseract_path: Option<String>,
+    tessdata_dir: Option<String>,
+) -> Result<TesseractInfo, String> {
+    println!("检测Tesseract环境");
+    
+    let storage_service = get_storage_service().await?;
+    let config = storage_service.load_monitoring_config().await.unwrap_or_default();
+    let mut settings = config.tesseract_settings();
+    if tesseract_path.is_some() {
+        settings.tesseract_path = tesseract_path;
+    }
+    if tessdata_dir.is_some() {
+        settings.tessdata_dir = tessdata_dir;
+    }
+    
+    tokio::task::spawn_blocking(move || {
+        TesseractCliEngine::locate(&settings).and_then(|engine| engine.info())
+    })
+    .await
+    .map_err(|e| format!("检测Tesseract失败: {}", e))?
+    .map_err(|e| format!("检测Tesseract失败: {}", e))
+}
+
 /// 获取当前活动状态
 #[command]
 pub async fn get_current_focus_state() -> Result<Option<MonitoringResult>, String> {
@@ -524,6 +723,7 @@ pub async fn trigger_monitoring_check(app_handle: tauri::AppHandle) -> Result<Mo
             
             // 发送状态变化事件给前端
             let focus_state_str = match result.focus_state {
+                _ if result.ai_unavailable => "ai_unavailable",
                 FocusState::Focused => "focused",
                 FocusState::Distracted => "distracted", 
                 FocusState::SeverelyDistracted => "severely_distracted",
@@ -536,7 +736,9 @@ pub async fn trigger_monitoring_check(app_handle: tauri::AppHandle) -> Result<Mo
                 "application_name": result.application_name,
                 "window_title": result.window_title,
                 "timestamp": result.timestamp,
-                "ai_analysis": result.ai_analysis
+                "ai_analysis": result.ai_analysis,
+                "private": result.private,
+                "ai_unavailable": result.ai_unavailable
             });
             
             // 发送专注状态变化事件
@@ -579,33 +781,112 @@ async fn perform_manual_monitoring_check(config: &MonitoringConfig) -> Result<Mo
     // 1. 获取当前应用信息
     println!("📱 步骤1: 获取当前应用信息");
     let app_start = Instant::now();
-    let (app_name, window_title) = get_current_application_info_sync().await
-        .map_err(|e| format!("获取应用信息失败: {}", e))?;
+    let redactor = Redactor::new(&config.redaction);
+    let mut redactions = RedactionCounts::default();
+    let raw_foreground = MonitorService::get_foreground_window().await;
+    let foreground = redactor.redact_window(&raw_foreground, &mut redactions);
+    let app_name = foreground.app_name.clone();
+    let window_title = foreground.window_title.clone();
     println!("⏱️ 应用信息获取耗时: {:?}", app_start.elapsed());
+    
+    // 隐私排除检查先于输出窗口标题，命中时标题不出现在日志中
+    if let Some(rule) = config.never_capture.matched_rule(&raw_foreground) {
+        println!("📋 应用: {:?}, 窗口: <private>", app_name);
+        println!("🔒 前台窗口命中隐私排除规则（{}），跳过截图、OCR和AI分析", rule);
+        return Ok(MonitoringResult::private(&foreground));
+    }
     println!("📋 应用: {:?}, 窗口: {:?}", app_name, window_title);
     
+    let rules = RuleEngine::for_config(config);
+    if let Some(rule) = rules.evaluate(&raw_foreground) {
+        println!("📏 命中专注规则 {}（{}），跳过截图和AI分析: {:?}", rule.rule_id, rule.description, rule.verdict);
+        let mut result = MonitoringResult::rule_matched(&foreground, &rule);
+        result.redactions = redactions;
+        return Ok(result);
+    }
+    
+    // 手动检查只在AI不可用时使用本地分类器
+    let predict_locally = |ocr_text: Option<&str>| {
+        LocalClassifier::predict_current(&config.local_classifier, app_name.as_deref(), window_title.as_deref(), ocr_text)
+    };
+    
+    let budget_config;
+    let (config, budget_mode) = match UsageService::default().detection_budget_mode(&config.ai_config) {
+        BudgetMode::Normal => (config, None),
+        BudgetMode::LocalOnly(ai_config) => {
+            println!("💰 已超出AI预算，改用本地模型: {}", ai_config.detection_model);
+            budget_config = MonitoringConfig { ai_config: *ai_config, ..config.clone() };
+            (&budget_config, Some(BudgetFallback::LocalModel))
+        }
+        BudgetMode::RuleOnly(reason) => {
+            println!("💰 {}，跳过截图和AI分析，仅按应用规则判断", reason);
+            let mut result = MonitoringResult::rule_only(config, &foreground, &reason);
+            if let (FocusState::Unknown, Some(prediction)) = (&result.focus_state, predict_locally(None)) {
+                result = prediction.apply(result, &reason);
+            }
+            result.redactions = redactions;
+            return Ok(result);
+        }
+    };
+    
+    if let Some(circuit) = AIService::new(config.ai_config.clone()).unavailable_status("detection") {
+        println!("🔌 AI服务暂不可用，跳过截图和AI分析");
+        let mut result = MonitoringResult::ai_unavailable(&foreground, circuit.unavailable_message());
+        if let Some(prediction) = predict_locally(None) {
+            result = prediction.apply(result, "AI服务暂不可用");
+        }
+        result.redactions = redactions;
+        return Ok(result);
+    }
+    
     // 2. 截取屏幕并进行OCR
     println!("📸 步骤2: 屏幕截图和OCR识别");
     let ocr_start = Instant::now();
-    let ocr_text = capture_screen_and_ocr_sync().await
+    let mut screen = capture_screen_and_ocr_sync(config, &foreground).await
         .map_err(|e| format!("屏幕截图或OCR失败: {}", e))?;
+    screen.ocr_text = redactor.redact_option(&screen.ocr_text, &mut redactions);
     println!("⏱️ 截图+OCR耗时: {:?}", ocr_start.elapsed());
-    if let Some(ref text) = ocr_text {
+    MonitorService::log_redactions(config, &screen, &redactions);
+    println!("🔤 OCR状态: {:?}", screen.ocr_status);
+    if let Some(ref text) = screen.ocr_text {
         println!("📝 OCR识别文本长度: {} 字符", text.len());
         println!("📄 OCR文本预览: {}", 
-            if text.len() > 100 { 
-                format!("{}...", &text[..100]) 
+            if text.chars().count() > 100 { 
+                format!("{}...", prompt_budget::truncate_chars(text, 100)) 
             } else { 
                 text.clone() 
             }
         );
     }
     
+    if let Some(rule) = screen.ocr_text.as_deref().and_then(|text| rules.evaluate_screen_text(text)) {
+        println!("📏 屏幕文字命中专注规则 {}（{}），跳过AI分析: {:?}", rule.rule_id, rule.description, rule.verdict);
+        return Ok(MonitoringResult {
+            ocr_text: screen.ocr_text.clone(),
+            ocr_status: screen.ocr_status,
+            image_features: screen.image_features.clone(),
+            captured_display: screen.captured_display,
+            frame_hash: screen.frame_hash.map(|hash| format!("{:016x}", hash)),
+            redactions,
+            ..MonitoringResult::rule_matched(&foreground, &rule)
+        });
+    }
+    
     // 3. AI分析
     println!("🤖 步骤3: AI专注状态分析");
     let ai_start = Instant::now();
-    let ai_result = analyze_focus_with_ai_sync(config, &app_name, &window_title, &ocr_text).await
+    let mut ai_result = analyze_focus_with_ai_sync(config, &app_name, &window_title, &screen).await
         .map_err(|e| format!("AI分析失败: {}", e))?;
+    if ai_result.ai_unavailable {
+        if let Some(prediction) = predict_locally(screen.ocr_text.as_deref()) {
+            println!("🧮 AI服务不可用，改用本地分类器: {:?}", prediction.state);
+            ai_result = prediction.apply(ai_result, "AI服务不可用");
+        }
+    }
+    ai_result.captured_display = screen.captured_display;
+    ai_result.frame_hash = screen.frame_hash.map(|hash| format!("{:016x}", hash));
+    ai_result.redactions = redactions;
+    ai_result.budget_mode = budget_mode;
     println!("⏱️ AI分析耗时: {:?}", ai_start.elapsed());
     
     println!("🎯 总检查耗时: {:?}", start_time.elapsed());
@@ -613,16 +894,13 @@ async fn perform_manual_monitoring_check(config: &MonitoringConfig) -> Result<Mo
     Ok(ai_result)
 }
 
-/// 同步版本的应用信息获取函数
-async fn get_current_application_info_sync() -> Result<(Option<String>, Option<String>), anyhow::Error> {
-    // 调用MonitorService的静态方法
-    crate::services::monitor_service::MonitorService::get_current_application_info().await
-}
-
 /// 同步版本的屏幕截图和OCR函数
-async fn capture_screen_and_ocr_sync() -> Result<Option<String>, anyhow::Error> {
-    // 调用MonitorService的静态方法
-    crate::services::monitor_service::MonitorService::capture_screen_and_ocr().await
+async fn capture_screen_and_ocr_sync(
+    config: &MonitoringConfig,
+    window: &ForegroundWindow,
+) -> Result<ScreenContent, anyhow::Error> {
+    // 调用MonitorService的静态方法（手动检查总是重新识别，不与上次画面比较）
+    crate::services::monitor_service::MonitorService::capture_screen_and_ocr(config, window, None).await
 }
 
 /// 同步版本的AI分析函数
@@ -630,111 +908,101 @@ async fn analyze_focus_with_ai_sync(
     config: &MonitoringConfig,
     app_name: &Option<String>,
     window_title: &Option<String>,
-    ocr_text: &Option<String>,
+    screen: &ScreenContent,
 ) -> Result<MonitoringResult, anyhow::Error> {
     use crate::services::ai_service::AIService;
     
     let ai_service = AIService::new(config.ai_config.clone());
     
-    // 构建AI分析提示
-    let prompt = build_analysis_prompt_sync(config, app_name, window_title, ocr_text);
+    // 构建AI分析提示（与定时检查使用同一模板）
+    let current_task = MonitorService::get_current_task_name().await.ok();
+    let template = PromptTemplateStore::load_default().active(config.prompt_language);
+    let examples = CorrectionStore::load_default().similar(
+        app_name.as_deref(),
+        window_title.as_deref(),
+        config.corrections.few_shot_examples,
+    );
+    let build_prompt = |screen: &ScreenContent| prompt_templates::build_detection_prompt(
+        config,
+        &template,
+        app_name.as_deref(),
+        window_title.as_deref(),
+        screen,
+        current_task.as_deref(),
+        &examples,
+    );
+    let prompt = build_prompt(screen);
     println!("💭 AI提示词长度: {} 字符", prompt.len());
     println!("📋 AI提示词内容:\n{}", prompt);
     
-    // 调用AI模型进行分析
-    let ai_response = call_ai_model_sync(&ai_service, &prompt).await?;
+    // 调用AI模型进行分析（视觉请求失败时回退到OCR文本）
+    let ((classification, ai_response), vision_used) = match screen.vision_image.as_deref() {
+        Some(image) => match focus_classification::request_classification(&ai_service, &prompt, Some(image), template.language).await {
+            Ok(response) => (response, true),
+            Err(e) => {
+                println!("⚠️ 视觉模型调用失败，回退到OCR文本: {}", e);
+                let text_prompt = build_prompt(&screen.without_vision());
+                (call_ai_model_sync(&ai_service, &text_prompt, template.language).await?, false)
+            }
+        },
+        None => (call_ai_model_sync(&ai_service, &prompt, template.language).await?, false),
+    };
     println!("🤖 AI原始响应:\n{}", ai_response);
     
-    // 解析AI响应
-    let (focus_state, confidence) = parse_ai_response_sync(&ai_response);
-    println!("🎯 解析结果: {:?} (置信度: {:.2})", focus_state, confidence);
+    println!("🎯 解析结果: {:?} (置信度: {:.2}, 类别: {:?})",
+        classification.state, classification.confidence, classification.activity_category);
 
     Ok(MonitoringResult {
         timestamp: chrono::Utc::now(),
-        focus_state,
+        focus_state: classification.state,
         application_name: app_name.clone(),
         window_title: window_title.clone(),
-        ocr_text: ocr_text.clone(),
-        ai_analysis: Some(ai_response),
-        confidence,
+        ocr_text: screen.ocr_text.clone(),
+        ai_analysis: Some(classification.reason),
+        confidence: classification.confidence,
+        captured_display: None,
+        frame_hash: None,
+        carried_over: false,
+        ocr_status: screen.ocr_status,
+        image_features: screen.image_features.clone(),
+        vision_used,
+        redactions: Default::default(),
+        private: false,
+        activity_category: classification.activity_category,
+        task_relevance: classification.task_relevance,
+        ai_unavailable: classification.ai_unavailable,
+        answered_by: classification.answered_by,
+        budget_mode: None,
+        cache_hit: false,
+        prompt_version: Some(template.label()),
+        user_label: None,
+        local_classifier: false,
+        matched_rule: None,
     })
 }
 
-/// 构建AI分析提示词
-fn build_analysis_prompt_sync(
-    config: &MonitoringConfig,
-    app_name: &Option<String>,
-    window_title: &Option<String>,
-    ocr_text: &Option<String>,
-) -> String {
-    let app_info = app_name.as_deref().unwrap_or("未知应用");
-    let title_info = window_title.as_deref().unwrap_or("无标题");
-    let text_info = ocr_text.as_deref().unwrap_or("无文本内容");
-    
-    let whitelist = config.whitelist.join(", ");
-    let blacklist = config.blacklist.join(", ");
-
-    format!(
-        r#"请分析当前用户的专注状态。
-
-**白名单应用（专注工具）**: {}
-**黑名单应用（分心来源）**: {}
-
-**当前活动信息**:
-- 应用程序: {}
-- 窗口标题: {}
-- 屏幕文本: {}
-
-请根据以上信息判断用户当前的专注状态，并按以下格式回答：
-
-状态: [专注/分心/严重分心]
-分析: [详细说明判断理由]
-
-判断标准：
-- 专注：使用白名单中的应用，或从事与工作学习相关的活动
-- 分心：使用黑名单中的应用，或从事娱乐相关活动
-- 严重分心：长时间使用娱乐应用，或明显的非工作内容"#,
-        whitelist,
-        blacklist,
-        app_info,
-        title_info,
-        text_info
-    )
-}
-
-/// 调用AI模型
-async fn call_ai_model_sync(ai_service: &AIService, prompt: &str) -> Result<String, anyhow::Error> {
+/// 调用AI模型并解析结构化结果
+async fn call_ai_model_sync(
+    ai_service: &AIService,
+    prompt: &str,
+    language: PromptLanguage,
+) -> Result<(FocusClassification, String), anyhow::Error> {
     // 使用配置的检测模型调用AI服务
-    match ai_service.analyze_content(prompt, "detection").await {
+    match focus_classification::request_classification(ai_service, prompt, None, language).await {
         Ok(response) => Ok(response),
         Err(e) => {
             println!("⚠️ AI模型调用失败: {}", e);
-            // 如果AI调用失败，返回基础分析
-            Ok("状态: 未知\n分析: AI服务暂不可用，无法进行专注状态分析。".to_string())
+            // 如果AI调用失败，返回未知状态
+            Ok((FocusClassification::unavailable(format!("AI服务不可用: {}", e)), String::new()))
         }
     }
 }
 
-/// 解析AI响应
-fn parse_ai_response_sync(response: &str) -> (FocusState, f32) {
-    let response_lower = response.to_lowercase();
-    
-    if response_lower.contains("专注") {
-        (FocusState::Focused, 0.8)
-    } else if response_lower.contains("严重分心") {
-        (FocusState::SeverelyDistracted, 0.9)
-    } else if response_lower.contains("分心") {
-        (FocusState::Distracted, 0.7)
-    } else {
-        (FocusState::Unknown, 0.5)
-    }
-}
-
 // ===== 报告生成相关命令 =====
 
 /// 生成日报告
 #[command]
-pub async fn generate_daily_report(date: String) -> Result<DailyReport, String> {
+pub async fn generate_daily_report(app_handle: tauri::AppHandle, date: String) -> Result<DailyReport, String> {
     println!("📊 开始生成日报告: {}", date);
     
     let storage_service = get_storage_service().await?;
@@ -742,7 +1010,7 @@ pub async fn generate_daily_report(date: String) -> Result<DailyReport, String>
         .map_err(|e| format!("加载AI配置失败: {}", e))?;
     
     let ai_service = AIService::new(ai_config);
-    let report_service = ReportService::new(storage_service);
+    let report_service = ReportService::new(storage_service).with_app_handle(app_handle);
     
     match report_service.generate_daily_report(&date, &ai_service).await {
         Ok(report) => {
@@ -758,7 +1026,7 @@ pub async fn generate_daily_report(date: String) -> Result<DailyReport, String>
 
 /// 生成周报告
 #[command]
-pub async fn generate_weekly_report(week_start: String) -> Result<WeeklyReport, String> {
+pub async fn generate_weekly_report(app_handle: tauri::AppHandle, week_start: String) -> Result<WeeklyReport, String> {
     println!("📊 开始生成周报告: {}", week_start);
     
     let storage_service = get_storage_service().await?;
@@ -766,7 +1034,7 @@ pub async fn generate_weekly_report(week_start: String) -> Result<WeeklyReport,
         .map_err(|e| format!("加载AI配置失败: {}", e))?;
     
     let ai_service = AIService::new(ai_config);
-    let report_service = ReportService::new(storage_service);
+    let report_service = ReportService::new(storage_service).with_app_handle(app_handle);
     
     match report_service.generate_weekly_report(&week_start, &ai_service).await {
         Ok(report) => {
@@ -1089,8 +1357,10 @@ pub async fn optimize_storage() -> Result<String, String> {
 }
 
 /// 备份数据
+///
+/// 默认不包含API密钥，`include_secrets` 为 true 时才写入备份。
 #[command]
-pub async fn backup_data(backup_path: Option<String>) -> Result<String, String> {
+pub async fn backup_data(backup_path: Option<String>, include_secrets: Option<bool>) -> Result<String, String> {
     println!("💾 开始数据备份");
     
     let storage_service = get_storage_service().await?;
@@ -1113,11 +1383,21 @@ pub async fn backup_data(backup_path: Option<String>) -> Result<String, String>
     let user_settings = storage_service.load_user_settings().await
         .unwrap_or_default();
     
-    let ai_config = storage_service.load_ai_config().await
+    let mut ai_config = storage_service.load_ai_config().await
         .ok();
     
-    let monitoring_config = storage_service.load_monitoring_config().await
+    let mut monitoring_config = storage_service.load_monitoring_config().await
         .ok();
+
+    if include_secrets.unwrap_or(false) {
+        println!("⚠️ 备份中包含API密钥，请妥善保管备份文件");
+    } else {
+        ai_config = ai_config.map(AIConfig::redacted);
+        if let Some(config) = monitoring_config.as_mut() {
+            config.ai_config = config.ai_config.clone().redacted();
+        }
+        println!("🔐 备份中不包含API密钥");
+    }
     
     // 创建备份数据结构
     let backup_data = BackupData {
@@ -1190,7 +1470,11 @@ pub async fn restore_data(backup_data: String) -> Result<String, String> {
         restored_items.push("用户设置".to_string());
     }
     
+    // 不含密钥的备份恢复时保留当前保存的密钥
     if let Some(ai_config) = backup.ai_config {
+        let ai_config = tokio::task::spawn_blocking(move || ai_config.with_secrets(AI_CONFIG_SECRETS))
+            .await
+            .map_err(|e| format!("读取密钥存储失败: {}", e))?;
         if let Err(e) = storage_service.save_ai_config(&ai_config).await {
             println!("⚠️ 恢复AI配置失败: {}", e);
         } else {
@@ -1198,7 +1482,11 @@ pub async fn restore_data(backup_data: String) -> Result<String, String> {
         }
     }
     
-    if let Some(monitoring_config) = backup.monitoring_config {
+    if let Some(mut monitoring_config) = backup.monitoring_config {
+        let ai_config = monitoring_config.ai_config.clone();
+        monitoring_config.ai_config = tokio::task::spawn_blocking(move || ai_config.with_secrets(MONITORING_CONFIG_SECRETS))
+            .await
+            .map_err(|e| format!("读取密钥存储失败: {}", e))?;
         if let Err(e) = storage_service.save_monitoring_config(&monitoring_config).await {
             println!("⚠️ 恢复监控配置失败: {}", e);
         } else {
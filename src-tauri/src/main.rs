@@ -46,13 +46,27 @@ async fn main() {
             test_ai_api,
             get_available_models,
             refresh_models,
+            get_ai_service_status,
+            get_ai_spend_report,
+            get_secret_storage_status,
+            unlock_secret_vault,
             
             // 监控配置管理
             save_monitoring_config,
             load_monitoring_config,
+            clear_classification_cache,
+            correct_monitoring_result,
+            get_correction_stats,
+            train_local_classifier,
+            get_local_classifier_metrics,
+            list_prompt_templates,
+            get_active_prompt_template,
+            save_prompt_template,
+            activate_prompt_template,
             get_current_focus_state,
             update_monitoring_interval,
             trigger_monitoring_check,
+            get_tesseract_info,
             
             // 报告生成管理
             generate_daily_report,
@@ -0,0 +1,225 @@
+use async_trait::async_trait;
+
+use super::openai::{chat_body, parse_chat_response, read_chat_stream};
+use super::{test_result, AiProvider, ChatRequest, ChatResponse, ChunkHandler, HttpClient, ProviderCapabilities, ProviderError};
+use crate::services::ai_service::{AIConfig, APITestResult, ModelInfo};
+
+/// 未配置 api_version 时使用的Azure OpenAI API版本
+const DEFAULT_API_VERSION: &str = "2024-06-01";
+
+/// 列出部署时使用的API版本（较新的数据面版本不再提供部署列表）
+const DEPLOYMENTS_API_VERSION: &str = "2022-12-01";
+
+/// Azure OpenAI
+///
+/// `api_url` 为资源地址（如 https://my-resource.openai.azure.com），模型名称填写部署名称。
+pub struct AzureOpenAiProvider {
+    http: HttpClient,
+    api_url: String,
+    api_key: String,
+    api_version: String,
+}
+
+impl AzureOpenAiProvider {
+    pub fn new(http: HttpClient, config: &AIConfig) -> Self {
+        let api_version = config.api_version.as_deref()
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .unwrap_or(DEFAULT_API_VERSION)
+            .to_string();
+        Self {
+            http,
+            api_url: config.api_url.trim_end_matches('/').trim_end_matches("/openai").to_string(),
+            api_key: config.api_key.clone(),
+            api_version,
+        }
+    }
+
+    fn chat_request(&self, deployment: &str, body: &serde_json::Value) -> reqwest::RequestBuilder {
+        self.http
+            .post(format!(
+                "{}/openai/deployments/{}/chat/completions?api-version={}",
+                self.api_url, deployment, self.api_version
+            ))
+            .header("api-key", &self.api_key)
+            .header("Content-Type", "application/json")
+            .json(body)
+    }
+
+    async fn fetch_deployments(&self) -> Result<Vec<ModelInfo>, String> {
+        let request = self.http
+            .get(format!("{}/openai/deployments?api-version={}", self.api_url, DEPLOYMENTS_API_VERSION))
+            .header("api-key", &self.api_key);
+        let json = self.http.send_json(self.name(), request).await?;
+        Self::parse_deployments(&json)
+    }
+
+    fn parse_deployments(json: &serde_json::Value) -> Result<Vec<ModelInfo>, String> {
+        let deployments = json.get("data")
+            .and_then(|d| d.as_array())
+            .ok_or_else(|| "Azure部署列表格式解析失败".to_string())?;
+
+        Ok(deployments
+            .iter()
+            .filter_map(|deployment| {
+                let id = deployment.get("id").and_then(|i| i.as_str())?;
+                let mut info = ModelInfo::new(id, "deployment");
+                info.created = deployment.get("created_at").and_then(|c| c.as_u64());
+                // 部署所用的底层模型
+                info.display_name = deployment.get("model").and_then(|m| m.as_str()).map(str::to_string);
+                Some(info)
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl AiProvider for AzureOpenAiProvider {
+    fn name(&self) -> &'static str {
+        "Azure OpenAI"
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            vision: true,
+            json_mode: true,
+            list_models: true,
+            requires_api_key: true,
+            streaming: true,
+        }
+    }
+
+    async fn chat(&self, request: &ChatRequest<'_>) -> Result<ChatResponse, ProviderError> {
+        println!("📞 调用Azure OpenAI API...");
+
+        // 部署名称在URL中，请求体不需要 model
+        let mut body = chat_body(request, None);
+        match self.http.send_json(self.name(), self.chat_request(request.model, &body)).await {
+            Ok(json) => parse_chat_response(self.name(), &json),
+            Err(e) if request.options.json_mode && e.message.contains("response_format") => {
+                println!("⚠️ 该部署不支持JSON模式，改用普通请求");
+                if let Some(body) = body.as_object_mut() {
+                    body.remove("response_format");
+                }
+                let json = self.http.send_json(self.name(), self.chat_request(request.model, &body)).await?;
+                parse_chat_response(self.name(), &json)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn chat_stream(
+        &self,
+        request: &ChatRequest<'_>,
+        on_chunk: &mut ChunkHandler<'_>,
+    ) -> Result<ChatResponse, ProviderError> {
+        println!("📞 调用Azure OpenAI API（流式）...");
+
+        let mut body = chat_body(request, None);
+        body["stream"] = serde_json::json!(true);
+        let reader = self.http.send_stream(self.name(), self.chat_request(request.model, &body)).await?;
+        read_chat_stream(self.name(), reader, on_chunk).await
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, String> {
+        self.fetch_deployments().await
+    }
+
+    async fn test_connection(&self) -> APITestResult {
+        println!("🔌 测试Azure OpenAI连接...");
+        let start_time = std::time::Instant::now();
+
+        match self.fetch_deployments().await {
+            Ok(deployments) => {
+                println!("✅ Azure OpenAI连接成功，找到 {} 个部署", deployments.len());
+                test_result(true, format!("连接成功！找到 {} 个部署", deployments.len()), start_time, None)
+            }
+            Err(e) => test_result(false, format!("Azure OpenAI连接失败: {}", e), start_time, None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::mock_server::MockServer;
+    use super::*;
+    use crate::services::ai_providers::RequestPolicy;
+    use crate::services::ai_service::ChatOptions;
+
+    fn provider(api_url: &str, api_version: Option<&str>) -> AzureOpenAiProvider {
+        let config = AIConfig {
+            api_type: "Azure OpenAI".to_string(),
+            api_url: api_url.to_string(),
+            api_key: "test-key".to_string(),
+            api_version: api_version.map(str::to_string),
+            ..AIConfig::default()
+        };
+        let policy = RequestPolicy { max_retries: 0, ..RequestPolicy::default() };
+        AzureOpenAiProvider::new(HttpClient::new(&policy, format!("azure-test|{}", api_url)), &config)
+    }
+
+    #[test]
+    fn normalizes_resource_url_and_api_version() {
+        let provider = provider("https://my-resource.openai.azure.com/openai/", Some("  "));
+        assert_eq!(provider.api_url, "https://my-resource.openai.azure.com");
+        assert_eq!(provider.api_version, DEFAULT_API_VERSION);
+    }
+
+    #[test]
+    fn parses_deployments() {
+        let json = serde_json::json!({
+            "data": [
+                { "id": "gpt4o-prod", "model": "gpt-4o", "created_at": 1_700_000_000u64 },
+                { "model": "missing-id" }
+            ]
+        });
+        let deployments = AzureOpenAiProvider::parse_deployments(&json).unwrap();
+        assert_eq!(deployments.len(), 1);
+        assert_eq!(deployments[0].id, "gpt4o-prod");
+        assert_eq!(deployments[0].object, "deployment");
+        assert_eq!(deployments[0].display_name.as_deref(), Some("gpt-4o"));
+        assert_eq!(deployments[0].created, Some(1_700_000_000));
+
+        assert!(AzureOpenAiProvider::parse_deployments(&serde_json::json!({ "value": [] })).is_err());
+    }
+
+    #[tokio::test]
+    async fn sends_chat_request_to_deployment() {
+        let server = MockServer::start(|_| serde_json::json!({
+            "choices": [{ "message": { "role": "assistant", "content": "{\"focused\": true}" } }],
+            "usage": { "prompt_tokens": 20, "completion_tokens": 6 }
+        })).await;
+
+        let options = ChatOptions { json_mode: true, max_tokens: Some(64), temperature: None };
+        let request = ChatRequest { model: "gpt4o-prod", content: "hi", image_base64: Some("aW1n"), options: &options };
+        let response = provider(&server.url, Some("2024-10-21")).chat(&request).await.unwrap();
+        assert_eq!(response.text, "{\"focused\": true}");
+        let usage = response.usage.unwrap();
+        assert_eq!((usage.prompt_tokens, usage.completion_tokens), (20, 6));
+
+        let requests = server.requests();
+        assert_eq!(requests[0].path, "/openai/deployments/gpt4o-prod/chat/completions?api-version=2024-10-21");
+        assert_eq!(requests[0].header("api-key"), Some("test-key"));
+        let body = requests[0].json();
+        // 部署名称在URL中，请求体不带 model
+        assert!(body.get("model").is_none());
+        assert_eq!(body.pointer("/max_tokens").and_then(|m| m.as_u64()), Some(64));
+        assert_eq!(body.pointer("/response_format/type").and_then(|t| t.as_str()), Some("json_object"));
+        assert_eq!(body.pointer("/messages/0/content/1/image_url/url").and_then(|u| u.as_str()), Some("data:image/jpeg;base64,aW1n"));
+    }
+
+    #[tokio::test]
+    async fn lists_deployments() {
+        let server = MockServer::start(|_| serde_json::json!({
+            "data": [{ "id": "gpt4o-prod", "model": "gpt-4o" }]
+        })).await;
+
+        let deployments = provider(&server.url, None).list_models().await.unwrap();
+        assert_eq!(deployments.len(), 1);
+
+        let requests = server.requests();
+        assert_eq!(requests[0].method, "GET");
+        assert_eq!(requests[0].path, format!("/openai/deployments?api-version={}", DEPLOYMENTS_API_VERSION));
+        assert_eq!(requests[0].header("api-key"), Some("test-key"));
+    }
+}
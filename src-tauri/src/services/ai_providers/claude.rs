@@ -0,0 +1,329 @@
+use async_trait::async_trait;
+
+use super::{test_result, AiProvider, ChatRequest, ChatResponse, ChunkHandler, HttpClient, ProviderCapabilities, ProviderError, ProviderErrorKind, TokenUsage};
+use crate::services::ai_service::{AIConfig, APITestResult, ModelInfo};
+use crate::services::redaction_service;
+
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// Anthropic Claude API
+pub struct ClaudeProvider {
+    http: HttpClient,
+    api_url: String,
+    api_key: String,
+}
+
+impl ClaudeProvider {
+    pub fn new(http: HttpClient, config: &AIConfig) -> Self {
+        Self {
+            http,
+            api_url: config.api_url.trim_end_matches('/').to_string(),
+            api_key: config.api_key.clone(),
+        }
+    }
+
+    fn model_info(model: &serde_json::Value) -> Option<ModelInfo> {
+        let id = model.get("id").and_then(|i| i.as_str())?;
+        let mut info = ModelInfo::new(id, "model");
+        info.owned_by = Some("anthropic".to_string());
+        info.display_name = model.get("display_name").and_then(|n| n.as_str()).map(str::to_string);
+        info.created = model.get("created_at")
+            .and_then(|t| t.as_str())
+            .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| t.timestamp().max(0) as u64);
+
+        // 接口返回上下文长度时直接使用；Claude 3 及之后的模型均为200K上下文并支持图像输入
+        let legacy = id.starts_with("claude-2") || id.starts_with("claude-instant");
+        info.context_length = model.get("max_input_tokens")
+            .and_then(|t| t.as_u64())
+            .or(if legacy { None } else { Some(200_000) });
+        info.supports_vision = Some(!legacy);
+        Some(info)
+    }
+
+    /// 构造 messages 请求体
+    fn messages_body(request: &ChatRequest<'_>) -> serde_json::Value {
+        // 附带图像时图像块放在文本之前
+        let message_content = match request.image_base64 {
+            Some(image) => serde_json::json!([
+                {
+                    "type": "image",
+                    "source": { "type": "base64", "media_type": "image/jpeg", "data": image }
+                },
+                { "type": "text", "text": request.content }
+            ]),
+            None => serde_json::json!(request.content),
+        };
+
+        let mut messages = vec![serde_json::json!({
+            "role": "user",
+            "content": message_content
+        })];
+        // Claude 没有JSON模式，预填 "{" 让回复直接从JSON对象开始
+        if request.options.json_mode {
+            messages.push(serde_json::json!({ "role": "assistant", "content": "{" }));
+        }
+
+        let mut body = serde_json::json!({
+            "model": request.model,
+            "max_tokens": request.options.max_tokens.unwrap_or(500),
+            "messages": messages
+        });
+        if let Some(temperature) = request.options.temperature {
+            body["temperature"] = serde_json::json!(temperature);
+        }
+        body
+    }
+
+    fn messages_request(&self, body: &serde_json::Value) -> reqwest::RequestBuilder {
+        self.http
+            .post(format!("{}/messages", self.api_url))
+            .header("x-api-key", &self.api_key)
+            .header("Content-Type", "application/json")
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .json(body)
+    }
+}
+
+#[async_trait]
+impl AiProvider for ClaudeProvider {
+    fn name(&self) -> &'static str {
+        "Claude"
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            vision: true,
+            json_mode: false,
+            list_models: true,
+            requires_api_key: true,
+            streaming: true,
+        }
+    }
+
+    async fn chat(&self, request: &ChatRequest<'_>) -> Result<ChatResponse, ProviderError> {
+        println!("📞 调用Claude API...");
+
+        let body = Self::messages_body(request);
+        let json = self.http.send_json(self.name(), self.messages_request(&body)).await?;
+
+        match json.pointer("/content/0/text").and_then(|t| t.as_str()) {
+            Some(text) => {
+                println!("✅ Claude API调用成功，响应长度: {} 字符", text.len());
+                Ok(ChatResponse {
+                    text: if request.options.json_mode { format!("{{{}", text) } else { text.to_string() },
+                    usage: TokenUsage::from_json(&json, "/usage/input_tokens", "/usage/output_tokens"),
+                })
+            }
+            None => {
+                println!("❌ Claude响应格式解析失败: {:?}", json);
+                Err(ProviderError::invalid_response("Claude响应格式解析失败"))
+            }
+        }
+    }
+
+    async fn chat_stream(
+        &self,
+        request: &ChatRequest<'_>,
+        on_chunk: &mut ChunkHandler<'_>,
+    ) -> Result<ChatResponse, ProviderError> {
+        println!("📞 调用Claude API（流式）...");
+
+        let mut body = Self::messages_body(request);
+        body["stream"] = serde_json::json!(true);
+        let mut reader = self.http.send_stream(self.name(), self.messages_request(&body)).await?;
+
+        let mut text = String::new();
+        let mut usage = TokenUsage::default();
+        if request.options.json_mode {
+            text.push('{');
+            on_chunk("{");
+        }
+        while let Some(data) = reader.next_sse_data().await? {
+            let event: serde_json::Value = serde_json::from_str(&data)
+                .map_err(|e| ProviderError::invalid_response(format!("Claude流式响应解析失败: {}", e)))?;
+            match event.get("type").and_then(|t| t.as_str()) {
+                Some("content_block_delta") => {
+                    if let Some(delta) = event.pointer("/delta/text").and_then(|t| t.as_str()) {
+                        text.push_str(delta);
+                        on_chunk(delta);
+                    }
+                }
+                // 输入token在开始时给出，输出token在结束前累计给出
+                Some("message_start") => {
+                    usage.prompt_tokens = event.pointer("/message/usage/input_tokens").and_then(|t| t.as_u64()).unwrap_or(0);
+                }
+                Some("message_delta") => {
+                    if let Some(output_tokens) = event.pointer("/usage/output_tokens").and_then(|t| t.as_u64()) {
+                        usage.completion_tokens = output_tokens;
+                    }
+                }
+                Some("message_stop") => break,
+                // 流中途的错误（如 overloaded_error）以事件形式返回
+                Some("error") => {
+                    let message = event.pointer("/error/message").and_then(|m| m.as_str()).unwrap_or("未知错误");
+                    println!("❌ Claude流式响应错误: {}", message);
+                    return Err(ProviderError::new(
+                        ProviderErrorKind::ServerError,
+                        format!("Claude流式响应错误: {}", message),
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        println!("✅ Claude 流式响应完成，长度: {} 字符", text.len());
+        Ok(ChatResponse { text, usage: Some(usage) })
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, String> {
+        let mut models = Vec::new();
+        let mut after_id: Option<String> = None;
+
+        // 模型列表分页返回，按 last_id 继续获取
+        loop {
+            let mut request = self.http
+                .get(format!("{}/models", self.api_url))
+                .header("x-api-key", &self.api_key)
+                .header("anthropic-version", ANTHROPIC_VERSION)
+                .query(&[("limit", "100")]);
+            if let Some(ref after_id) = after_id {
+                request = request.query(&[("after_id", after_id.as_str())]);
+            }
+            let json = self.http.send_json(self.name(), request).await?;
+
+            let page = json.get("data")
+                .and_then(|d| d.as_array())
+                .ok_or_else(|| "Claude模型列表格式解析失败".to_string())?;
+            models.extend(page.iter().filter_map(Self::model_info));
+
+            let has_more = json.get("has_more").and_then(|h| h.as_bool()).unwrap_or(false);
+            after_id = json.get("last_id").and_then(|l| l.as_str()).map(str::to_string);
+            if !has_more || after_id.is_none() {
+                break;
+            }
+        }
+
+        Ok(models)
+    }
+
+    async fn test_connection(&self) -> APITestResult {
+        println!("🔌 测试Claude API连接...");
+        let start_time = std::time::Instant::now();
+
+        // 优先获取模型列表，不消耗token
+        match self.list_models().await {
+            Ok(models) => {
+                println!("✅ Claude API连接成功，找到 {} 个模型", models.len());
+                return test_result(true, format!("Claude API连接成功！找到 {} 个可用模型", models.len()), start_time, None);
+            }
+            Err(e) => println!("⚠️ 获取Claude模型列表失败，改用测试消息: {}", e),
+        }
+
+        // 代理服务可能没有模型列表端点，发送一个简单的测试请求
+        let test_body = serde_json::json!({
+            "model": "claude-3-haiku-20240307",
+            "max_tokens": 10,
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hi"
+                }
+            ]
+        });
+
+        match self.messages_request(&test_body).send().await {
+            Ok(resp) => {
+                println!("📨 Claude API测试响应状态: {}", resp.status());
+                if resp.status().is_success() {
+                    println!("✅ Claude API连接成功");
+                    test_result(true, "Claude API连接成功！", start_time, Some("claude-3-haiku-20240307".to_string()))
+                } else {
+                    let status = resp.status();
+                    let error_text = redaction_service::scrub_secrets(&resp.text().await.unwrap_or_default());
+                    println!("❌ Claude API测试失败: {} - {}", status, error_text);
+
+                    let error_msg = if status == 401 {
+                        "Claude API认证失败 - 请检查API密钥是否正确".to_string()
+                    } else if status == 403 {
+                        "Claude API访问被拒绝 - 请检查API密钥权限".to_string()
+                    } else {
+                        format!("Claude API错误: {} - {}", status, error_text)
+                    };
+                    test_result(false, error_msg, start_time, None)
+                }
+            }
+            Err(e) => {
+                println!("❌ Claude API网络连接失败: {}", e);
+                test_result(false, format!("Claude连接失败: {}", e), start_time, None)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::mock_server::MockServer;
+    use super::*;
+    use crate::services::ai_providers::RequestPolicy;
+
+    fn provider(api_url: &str) -> ClaudeProvider {
+        let config = AIConfig {
+            api_type: "Claude".to_string(),
+            api_url: format!("{}/v1/", api_url),
+            api_key: "test-key".to_string(),
+            ..AIConfig::default()
+        };
+        let policy = RequestPolicy { max_retries: 0, ..RequestPolicy::default() };
+        ClaudeProvider::new(HttpClient::new(&policy, format!("claude-test|{}", api_url)), &config)
+    }
+
+    #[test]
+    fn builds_model_info() {
+        let model = serde_json::json!({
+            "id": "claude-3-5-sonnet-20241022",
+            "display_name": "Claude 3.5 Sonnet",
+            "created_at": "2024-10-22T00:00:00Z"
+        });
+        let info = ClaudeProvider::model_info(&model).unwrap();
+        assert_eq!(info.display_name.as_deref(), Some("Claude 3.5 Sonnet"));
+        assert_eq!(info.created, Some(1_729_555_200));
+        assert_eq!(info.context_length, Some(200_000));
+        assert_eq!(info.supports_vision, Some(true));
+
+        let legacy = ClaudeProvider::model_info(&serde_json::json!({ "id": "claude-2.1" })).unwrap();
+        assert_eq!((legacy.context_length, legacy.supports_vision), (None, Some(false)));
+        assert!(ClaudeProvider::model_info(&serde_json::json!({ "display_name": "no id" })).is_none());
+    }
+
+    #[tokio::test]
+    async fn lists_models_across_pages() {
+        let server = MockServer::start(|request| {
+            if request.path.contains("after_id=claude-3-opus") {
+                serde_json::json!({
+                    "data": [{ "id": "claude-3-haiku" }],
+                    "has_more": false,
+                    "last_id": "claude-3-haiku"
+                })
+            } else {
+                serde_json::json!({
+                    "data": [{ "id": "claude-3-5-sonnet" }, { "id": "claude-3-opus" }],
+                    "has_more": true,
+                    "last_id": "claude-3-opus"
+                })
+            }
+        })
+        .await;
+
+        let models = provider(&server.url).list_models().await.unwrap();
+        let ids: Vec<&str> = models.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, ["claude-3-5-sonnet", "claude-3-opus", "claude-3-haiku"]);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].path, "/v1/models?limit=100");
+        assert_eq!(requests[1].path, "/v1/models?limit=100&after_id=claude-3-opus");
+        assert_eq!(requests[0].header("x-api-key"), Some("test-key"));
+        assert_eq!(requests[0].header("anthropic-version"), Some(ANTHROPIC_VERSION));
+    }
+}
@@ -0,0 +1,294 @@
+use async_trait::async_trait;
+
+use super::{test_result, AiProvider, ChatRequest, ChatResponse, HttpClient, ProviderCapabilities, ProviderError, TokenUsage};
+use crate::services::ai_service::{AIConfig, APITestResult, ModelInfo};
+
+/// Google Gemini API (generativelanguage.googleapis.com/v1beta)
+pub struct GeminiProvider {
+    http: HttpClient,
+    api_url: String,
+    api_key: String,
+}
+
+impl GeminiProvider {
+    pub fn new(http: HttpClient, config: &AIConfig) -> Self {
+        Self {
+            http,
+            api_url: config.api_url.trim_end_matches('/').to_string(),
+            api_key: config.api_key.clone(),
+        }
+    }
+
+    fn models_request(&self, page_token: Option<&str>) -> reqwest::RequestBuilder {
+        let mut request = self.http
+            .get(format!("{}/models", self.api_url))
+            .header("x-goog-api-key", &self.api_key)
+            .query(&[("pageSize", "1000")]);
+        if let Some(page_token) = page_token {
+            request = request.query(&[("pageToken", page_token)]);
+        }
+        request
+    }
+
+    /// 列出支持 generateContent 的模型
+    async fn fetch_models(&self) -> Result<Vec<ModelInfo>, String> {
+        let mut models = Vec::new();
+        let mut page_token: Option<String> = None;
+
+        // 模型列表分页返回，按 nextPageToken 继续获取
+        loop {
+            let json = self.http.send_json(self.name(), self.models_request(page_token.as_deref())).await?;
+            let (page, next_page_token) = Self::parse_models_page(&json)?;
+            models.extend(page);
+
+            page_token = next_page_token;
+            if page_token.is_none() {
+                break;
+            }
+        }
+
+        Ok(models)
+    }
+
+    /// 解析一页模型列表，返回模型和下一页的令牌
+    fn parse_models_page(json: &serde_json::Value) -> Result<(Vec<ModelInfo>, Option<String>), String> {
+        let models = json.get("models")
+            .and_then(|m| m.as_array())
+            .ok_or_else(|| "Gemini模型列表格式解析失败".to_string())?;
+        let next_page_token = json.get("nextPageToken")
+            .and_then(|t| t.as_str())
+            .filter(|t| !t.is_empty())
+            .map(str::to_string);
+
+        let models: Vec<ModelInfo> = models
+            .iter()
+            .filter(|model| {
+                model.get("supportedGenerationMethods")
+                    .and_then(|m| m.as_array())
+                    .map(|methods| methods.iter().any(|m| m.as_str() == Some("generateContent")))
+                    .unwrap_or(true)
+            })
+            .filter_map(|model| {
+                let name = model.get("name").and_then(|n| n.as_str())?;
+                let mut info = ModelInfo::new(name.trim_start_matches("models/"), "model");
+                info.owned_by = Some("google".to_string());
+                info.display_name = model.get("displayName").and_then(|n| n.as_str()).map(str::to_string);
+                info.context_length = model.get("inputTokenLimit").and_then(|n| n.as_u64());
+                // Gemini 1.5 及之后的模型都支持图像输入
+                info.supports_vision = Some(!name.contains("embedding") && !name.contains("gemini-1.0"));
+                Some(info)
+            })
+            .collect();
+        Ok((models, next_page_token))
+    }
+
+    /// 构造 generateContent 请求体
+    fn generate_content_body(request: &ChatRequest<'_>) -> serde_json::Value {
+        let mut parts = vec![serde_json::json!({ "text": request.content })];
+        if let Some(image) = request.image_base64 {
+            parts.push(serde_json::json!({
+                "inline_data": { "mime_type": "image/jpeg", "data": image }
+            }));
+        }
+
+        let mut generation_config = serde_json::json!({
+            "maxOutputTokens": request.options.max_tokens.unwrap_or(500),
+            "temperature": request.options.temperature.unwrap_or(0.3)
+        });
+        if request.options.json_mode {
+            generation_config["responseMimeType"] = serde_json::json!("application/json");
+        }
+
+        serde_json::json!({
+            "contents": [{ "role": "user", "parts": parts }],
+            "generationConfig": generation_config
+        })
+    }
+
+    /// 从 generateContent 响应中取出回复文本，回复可能被拆成多段
+    fn parse_generate_content(json: &serde_json::Value) -> Result<ChatResponse, ProviderError> {
+        let text: String = json.pointer("/candidates/0/content/parts")
+            .and_then(|p| p.as_array())
+            .map(|parts| parts.iter().filter_map(|p| p.get("text").and_then(|t| t.as_str())).collect())
+            .unwrap_or_default();
+        if text.is_empty() {
+            println!("❌ Gemini响应格式解析失败: {:?}", json);
+            return Err(ProviderError::invalid_response("Gemini响应格式解析失败"));
+        }
+        Ok(ChatResponse {
+            text,
+            usage: TokenUsage::from_json(json, "/usageMetadata/promptTokenCount", "/usageMetadata/candidatesTokenCount"),
+        })
+    }
+}
+
+#[async_trait]
+impl AiProvider for GeminiProvider {
+    fn name(&self) -> &'static str {
+        "Gemini"
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            vision: true,
+            json_mode: true,
+            list_models: true,
+            requires_api_key: true,
+            streaming: false,
+        }
+    }
+
+    async fn chat(&self, request: &ChatRequest<'_>) -> Result<ChatResponse, ProviderError> {
+        println!("📞 调用Gemini API...");
+
+        let body = Self::generate_content_body(request);
+        let model = request.model.trim_start_matches("models/");
+        let response = self.http
+            .post(format!("{}/models/{}:generateContent", self.api_url, model))
+            .header("x-goog-api-key", &self.api_key)
+            .header("Content-Type", "application/json")
+            .json(&body);
+        let json = self.http.send_json(self.name(), response).await?;
+
+        let response = Self::parse_generate_content(&json)?;
+        println!("✅ Gemini API调用成功，响应长度: {} 字符", response.text.len());
+        Ok(response)
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, String> {
+        self.fetch_models().await
+    }
+
+    async fn test_connection(&self) -> APITestResult {
+        println!("🔌 测试Gemini API连接...");
+        let start_time = std::time::Instant::now();
+
+        match self.fetch_models().await {
+            Ok(models) => {
+                println!("✅ Gemini API连接成功，找到 {} 个模型", models.len());
+                test_result(true, format!("连接成功！找到 {} 个可用模型", models.len()), start_time, None)
+            }
+            Err(e) => test_result(false, format!("Gemini连接失败: {}", e), start_time, None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::mock_server::MockServer;
+    use super::*;
+    use crate::services::ai_service::ChatOptions;
+    use crate::services::ai_providers::{ProviderErrorKind, RequestPolicy};
+
+    fn provider(api_url: &str) -> GeminiProvider {
+        let config = AIConfig {
+            api_type: "Google Gemini".to_string(),
+            api_url: format!("{}/", api_url),
+            api_key: "test-key".to_string(),
+            ..AIConfig::default()
+        };
+        let policy = RequestPolicy { max_retries: 0, ..RequestPolicy::default() };
+        GeminiProvider::new(HttpClient::new(&policy, format!("gemini-test|{}", api_url)), &config)
+    }
+
+    #[test]
+    fn builds_generate_content_body() {
+        let options = ChatOptions { json_mode: true, max_tokens: None, temperature: Some(0.1) };
+        let request = ChatRequest { model: "gemini-1.5-flash", content: "你好", image_base64: Some("aW1n"), options: &options };
+        let body = GeminiProvider::generate_content_body(&request);
+
+        assert_eq!(body.pointer("/contents/0/role").and_then(|r| r.as_str()), Some("user"));
+        assert_eq!(body.pointer("/contents/0/parts/0/text").and_then(|t| t.as_str()), Some("你好"));
+        assert_eq!(body.pointer("/contents/0/parts/1/inline_data/data").and_then(|d| d.as_str()), Some("aW1n"));
+        assert_eq!(body.pointer("/generationConfig/maxOutputTokens").and_then(|m| m.as_u64()), Some(500));
+        assert_eq!(body.pointer("/generationConfig/responseMimeType").and_then(|m| m.as_str()), Some("application/json"));
+
+        let options = ChatOptions::default();
+        let request = ChatRequest { model: "gemini-1.5-flash", content: "hi", image_base64: None, options: &options };
+        let body = GeminiProvider::generate_content_body(&request);
+        assert_eq!(body.pointer("/contents/0/parts").and_then(|p| p.as_array()).map(|p| p.len()), Some(1));
+        assert!(body.pointer("/generationConfig/responseMimeType").is_none());
+    }
+
+    #[test]
+    fn parses_generate_content_response() {
+        let json = serde_json::json!({
+            "candidates": [{ "content": { "parts": [{ "text": "{\"focused\":" }, { "text": " true}" }] } }],
+            "usageMetadata": { "promptTokenCount": 12, "candidatesTokenCount": 5 }
+        });
+        let response = GeminiProvider::parse_generate_content(&json).unwrap();
+        assert_eq!(response.text, "{\"focused\": true}");
+        let usage = response.usage.unwrap();
+        assert_eq!((usage.prompt_tokens, usage.completion_tokens), (12, 5));
+
+        let blocked = serde_json::json!({ "promptFeedback": { "blockReason": "SAFETY" } });
+        let error = GeminiProvider::parse_generate_content(&blocked).unwrap_err();
+        assert_eq!(error.kind, ProviderErrorKind::InvalidResponse);
+    }
+
+    #[test]
+    fn parses_models_page() {
+        let json = serde_json::json!({
+            "models": [
+                {
+                    "name": "models/gemini-1.5-pro",
+                    "displayName": "Gemini 1.5 Pro",
+                    "inputTokenLimit": 2_000_000,
+                    "supportedGenerationMethods": ["generateContent", "countTokens"]
+                },
+                { "name": "models/text-embedding-004", "supportedGenerationMethods": ["embedContent"] }
+            ],
+            "nextPageToken": "page-2"
+        });
+        let (models, next_page_token) = GeminiProvider::parse_models_page(&json).unwrap();
+        assert_eq!(next_page_token.as_deref(), Some("page-2"));
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].id, "gemini-1.5-pro");
+        assert_eq!(models[0].context_length, Some(2_000_000));
+        assert_eq!(models[0].supports_vision, Some(true));
+
+        let (_, next_page_token) = GeminiProvider::parse_models_page(&serde_json::json!({ "models": [], "nextPageToken": "" })).unwrap();
+        assert_eq!(next_page_token, None);
+        assert!(GeminiProvider::parse_models_page(&serde_json::json!({ "error": {} })).is_err());
+    }
+
+    #[tokio::test]
+    async fn lists_models_across_pages() {
+        let server = MockServer::start(|request| {
+            if request.path.contains("pageToken=page-2") {
+                serde_json::json!({ "models": [{ "name": "models/gemini-1.5-flash" }] })
+            } else {
+                serde_json::json!({ "models": [{ "name": "models/gemini-1.5-pro" }], "nextPageToken": "page-2" })
+            }
+        }).await;
+
+        let models = provider(&server.url).list_models().await.unwrap();
+        let ids: Vec<&str> = models.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, vec!["gemini-1.5-pro", "gemini-1.5-flash"]);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].path.starts_with("/models?"));
+        assert!(!requests[0].path.contains("pageToken"));
+        assert_eq!(requests[1].header("x-goog-api-key"), Some("test-key"));
+    }
+
+    #[tokio::test]
+    async fn sends_generate_content_request() {
+        let server = MockServer::start(|_| serde_json::json!({
+            "candidates": [{ "content": { "parts": [{ "text": "ok" }] } }]
+        })).await;
+
+        let options = ChatOptions::default();
+        let request = ChatRequest { model: "models/gemini-1.5-flash", content: "hi", image_base64: None, options: &options };
+        let response = provider(&server.url).chat(&request).await.unwrap();
+        assert_eq!(response.text, "ok");
+        assert!(response.usage.is_none());
+
+        let requests = server.requests();
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].path, "/models/gemini-1.5-flash:generateContent");
+        assert_eq!(requests[0].header("x-goog-api-key"), Some("test-key"));
+        assert_eq!(requests[0].json().pointer("/contents/0/parts/0/text").and_then(|t| t.as_str()), Some("hi"));
+    }
+}
@@ -0,0 +1,670 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{ProviderError, ProviderErrorKind};
+use crate::services::redaction_service;
+
+/// AI请求的超时、重试和熔断设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestPolicy {
+    #[serde(default = "default_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+    #[serde(default = "default_request_timeout_secs")]
+    pub request_timeout_secs: u64, // 单次请求（含读取响应）的总超时
+    #[serde(default = "default_stream_timeout_secs")]
+    pub stream_timeout_secs: u64, // 流式请求的总超时
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32, // 429、5xx和网络错误的最大重试次数
+    #[serde(default = "default_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    #[serde(default = "default_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+    #[serde(default = "default_max_retry_after_secs")]
+    pub max_retry_after_secs: u64, // Retry-After 超过此值时不再等待，直接失败
+    #[serde(default = "default_breaker_failure_threshold")]
+    pub breaker_failure_threshold: u32, // 连续失败多少次后熔断
+    #[serde(default = "default_breaker_cooldown_secs")]
+    pub breaker_cooldown_secs: u64, // 熔断后多久再尝试
+}
+
+fn default_connect_timeout_secs() -> u64 {
+    10
+}
+
+fn default_request_timeout_secs() -> u64 {
+    60
+}
+
+fn default_stream_timeout_secs() -> u64 {
+    300
+}
+
+fn default_max_retries() -> u32 {
+    2
+}
+
+fn default_initial_backoff_ms() -> u64 {
+    500
+}
+
+fn default_max_backoff_ms() -> u64 {
+    8000
+}
+
+fn default_max_retry_after_secs() -> u64 {
+    30
+}
+
+fn default_breaker_failure_threshold() -> u32 {
+    3
+}
+
+fn default_breaker_cooldown_secs() -> u64 {
+    120
+}
+
+impl Default for RequestPolicy {
+    fn default() -> Self {
+        Self {
+            connect_timeout_secs: default_connect_timeout_secs(),
+            request_timeout_secs: default_request_timeout_secs(),
+            stream_timeout_secs: default_stream_timeout_secs(),
+            max_retries: default_max_retries(),
+            initial_backoff_ms: default_initial_backoff_ms(),
+            max_backoff_ms: default_max_backoff_ms(),
+            max_retry_after_secs: default_max_retry_after_secs(),
+            breaker_failure_threshold: default_breaker_failure_threshold(),
+            breaker_cooldown_secs: default_breaker_cooldown_secs(),
+        }
+    }
+}
+
+impl RequestPolicy {
+    /// 第 attempt 次重试前的等待时间：指数退避，在 [一半, 全部] 之间随机抖动
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self.initial_backoff_ms.saturating_mul(1u64 << attempt.min(16));
+        let capped = exponential.min(self.max_backoff_ms).max(1);
+        Duration::from_millis(rand::thread_rng().gen_range(capped / 2..=capped))
+    }
+}
+
+/// 熔断器状态
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum CircuitState {
+    Closed,   // 正常
+    Open,     // 熔断中，请求直接失败
+    HalfOpen, // 冷却结束，只允许一个试探请求
+}
+
+/// 某个AI服务端点的可用性
+#[derive(Debug, Clone, Serialize)]
+pub struct CircuitStatus {
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+    pub retry_at: Option<DateTime<Utc>>, // 熔断中时下次允许请求的时间
+    pub last_error: Option<String>,
+}
+
+impl CircuitStatus {
+    /// 熔断期间显示给用户的说明
+    pub fn unavailable_message(&self) -> String {
+        if self.state == CircuitState::HalfOpen {
+            return format!(
+                "AI服务暂不可用：正在试探服务是否恢复（{}）",
+                self.last_error.as_deref().unwrap_or("未知错误")
+            );
+        }
+        let wait = self.retry_at.map(|t| (t - Utc::now()).num_seconds().max(0)).unwrap_or(0);
+        format!(
+            "AI服务暂不可用：连续失败 {} 次，{} 秒后重试（{}）",
+            self.consecutive_failures,
+            wait,
+            self.last_error.as_deref().unwrap_or("未知错误")
+        )
+    }
+}
+
+#[derive(Default)]
+struct CircuitBreaker {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+    retry_at: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    probing: bool, // 半开状态下已有试探请求在进行
+}
+
+impl CircuitBreaker {
+    fn state(&self) -> CircuitState {
+        match self.open_until {
+            Some(until) if Instant::now() < until => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+            None => CircuitState::Closed,
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    // 按 API类型+地址 区分的熔断器，AIService 每次调用都会重新创建，状态需要全局保存
+    static ref BREAKERS: Mutex<HashMap<String, CircuitBreaker>> = Mutex::new(HashMap::new());
+}
+
+/// 查询端点的熔断状态
+pub fn circuit_status(key: &str) -> CircuitStatus {
+    let breakers = BREAKERS.lock().unwrap_or_else(|e| e.into_inner());
+    match breakers.get(key) {
+        Some(breaker) => CircuitStatus {
+            state: breaker.state(),
+            consecutive_failures: breaker.consecutive_failures,
+            retry_at: breaker.retry_at.filter(|_| breaker.state() == CircuitState::Open),
+            last_error: breaker.last_error.clone(),
+        },
+        None => CircuitStatus {
+            state: CircuitState::Closed,
+            consecutive_failures: 0,
+            retry_at: None,
+            last_error: None,
+        },
+    }
+}
+
+/// 半开状态下的试探请求，结束（包括被取消）时允许下一个试探
+struct ProbeGuard {
+    key: String,
+}
+
+impl Drop for ProbeGuard {
+    fn drop(&mut self) {
+        let mut breakers = BREAKERS.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(breaker) = breakers.get_mut(&self.key) {
+            breaker.probing = false;
+        }
+    }
+}
+
+/// 检查是否允许发出请求：熔断中拒绝；半开时只放行一个试探请求，其余请求在它结束前拒绝
+fn acquire(key: &str) -> Result<Option<ProbeGuard>, CircuitStatus> {
+    let mut breakers = BREAKERS.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(breaker) = breakers.get_mut(key) else {
+        return Ok(None);
+    };
+    match breaker.state() {
+        CircuitState::Closed => Ok(None),
+        CircuitState::HalfOpen if !breaker.probing => {
+            breaker.probing = true;
+            println!("🔍 AI服务冷却结束，发送试探请求: {}", key);
+            Ok(Some(ProbeGuard { key: key.to_string() }))
+        }
+        state => Err(CircuitStatus {
+            state,
+            consecutive_failures: breaker.consecutive_failures,
+            retry_at: breaker.retry_at.filter(|_| state == CircuitState::Open),
+            last_error: breaker.last_error.clone(),
+        }),
+    }
+}
+
+fn record_success(key: &str) {
+    let mut breakers = BREAKERS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(breaker) = breakers.remove(key) {
+        if breaker.open_until.is_some() {
+            println!("✅ AI服务已恢复: {}", key);
+        }
+    }
+}
+
+fn record_failure(key: &str, policy: &RequestPolicy, error: &str) {
+    let mut breakers = BREAKERS.lock().unwrap_or_else(|e| e.into_inner());
+    let breaker = breakers.entry(key.to_string()).or_default();
+    let half_open = breaker.state() == CircuitState::HalfOpen;
+    breaker.consecutive_failures += 1;
+    breaker.last_error = Some(error.to_string());
+
+    // 试探请求失败或连续失败达到阈值时熔断
+    if half_open || breaker.consecutive_failures >= policy.breaker_failure_threshold.max(1) {
+        let cooldown = Duration::from_secs(policy.breaker_cooldown_secs);
+        breaker.open_until = Some(Instant::now() + cooldown);
+        breaker.retry_at = Some(Utc::now() + chrono::Duration::seconds(policy.breaker_cooldown_secs as i64));
+        println!(
+            "🔌 AI服务连续失败 {} 次，暂停调用 {} 秒: {}",
+            breaker.consecutive_failures, policy.breaker_cooldown_secs, key
+        );
+    }
+}
+
+/// 失败状态码对应的错误类型
+fn status_error_kind(status: reqwest::StatusCode) -> ProviderErrorKind {
+    match status.as_u16() {
+        429 => ProviderErrorKind::RateLimited,
+        401 | 403 => ProviderErrorKind::Auth,
+        _ if status.is_server_error() => ProviderErrorKind::ServerError,
+        _ => ProviderErrorKind::BadRequest,
+    }
+}
+
+/// 解析 Retry-After（秒数或HTTP日期）
+fn parse_retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let value = headers.get(reqwest::header::RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    let seconds = (date.with_timezone(&Utc) - Utc::now()).num_milliseconds().max(0);
+    Some(Duration::from_millis(seconds as u64))
+}
+
+/// 带超时、重试和熔断的HTTP客户端
+#[derive(Clone)]
+pub struct HttpClient {
+    client: reqwest::Client,
+    policy: RequestPolicy,
+    breaker_key: String,
+}
+
+impl HttpClient {
+    pub fn new(policy: &RequestPolicy, breaker_key: String) -> Self {
+        let client = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(policy.connect_timeout_secs.max(1)))
+            .timeout(Duration::from_secs(policy.request_timeout_secs.max(1)))
+            .build()
+            .unwrap_or_else(|e| {
+                println!("⚠️ 创建HTTP客户端失败，使用默认设置: {}", e);
+                reqwest::Client::new()
+            });
+        Self {
+            client,
+            policy: policy.clone(),
+            breaker_key,
+        }
+    }
+
+    pub fn get(&self, url: String) -> reqwest::RequestBuilder {
+        self.client.get(url)
+    }
+
+    pub fn post(&self, url: String) -> reqwest::RequestBuilder {
+        self.client.post(url)
+    }
+
+    pub fn circuit_status(&self) -> CircuitStatus {
+        circuit_status(&self.breaker_key)
+    }
+
+    /// 发送请求并读取JSON响应
+    pub async fn send_json(
+        &self,
+        provider: &str,
+        request: reqwest::RequestBuilder,
+    ) -> Result<serde_json::Value, ProviderError> {
+        let resp = self.send(provider, request).await?;
+        resp.json::<serde_json::Value>().await.map_err(|e| {
+            println!("❌ {} JSON解析失败: {}", provider, e);
+            ProviderError::invalid_response(format!("{} JSON解析失败: {}", provider, e))
+        })
+    }
+
+    /// 只发送一次并读取JSON：不重试，也不计入熔断器
+    ///
+    /// 用于失败时可以忽略的附加信息（如模型详情），不影响服务的可用性判断。熔断中时不发出请求。
+    pub async fn send_json_once(
+        &self,
+        provider: &str,
+        request: reqwest::RequestBuilder,
+    ) -> Result<serde_json::Value, ProviderError> {
+        let status = self.circuit_status();
+        if status.state != CircuitState::Closed {
+            return Err(ProviderError::new(ProviderErrorKind::CircuitOpen, status.unavailable_message()));
+        }
+
+        let resp = request.send().await.map_err(|e| {
+            let detail = redaction_service::scrub_secrets(&e.to_string());
+            ProviderError::new(ProviderErrorKind::Network, format!("{}网络请求失败: {}", provider, detail))
+        })?;
+        let status = resp.status();
+        if !status.is_success() {
+            let error_text = redaction_service::scrub_secrets(&resp.text().await.unwrap_or_default());
+            return Err(ProviderError::new(
+                status_error_kind(status),
+                format!("{} API请求失败: {} - {}", provider, status, error_text),
+            ));
+        }
+        resp.json::<serde_json::Value>()
+            .await
+            .map_err(|e| ProviderError::invalid_response(format!("{} JSON解析失败: {}", provider, e)))
+    }
+
+    /// 发送流式请求，返回状态码成功的响应，由调用方逐块读取
+    ///
+    /// 流式回复可能持续较长时间，使用 `stream_timeout_secs` 代替单次请求超时。
+    pub async fn send_stream(
+        &self,
+        provider: &str,
+        request: reqwest::RequestBuilder,
+    ) -> Result<LineReader, ProviderError> {
+        let request = request.timeout(Duration::from_secs(self.policy.stream_timeout_secs.max(1)));
+        let resp = self.send(provider, request).await?;
+        Ok(LineReader::new(provider, resp))
+    }
+
+    /// 发送请求，返回状态码成功的响应
+    ///
+    /// 429、5xx和网络错误按指数退避重试（优先使用 Retry-After），重试用尽后计入熔断器；
+    /// 熔断期间直接返回错误，不发出请求；冷却结束后只有一个试探请求能发出。
+    async fn send(
+        &self,
+        provider: &str,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, ProviderError> {
+        let _probe = acquire(&self.breaker_key)
+            .map_err(|status| ProviderError::new(ProviderErrorKind::CircuitOpen, status.unavailable_message()))?;
+
+        let mut attempt = 0u32;
+        loop {
+            let current = request.try_clone()
+                .ok_or_else(|| ProviderError::new(ProviderErrorKind::BadRequest, "请求无法复制，不能重试"))?;
+
+            let (error, retry_after) = match current.send().await {
+                Ok(resp) => {
+                    let status = resp.status();
+                    println!("📨 {} API响应状态: {}", provider, status);
+                    if status.is_success() {
+                        record_success(&self.breaker_key);
+                        return Ok(resp);
+                    }
+
+                    let retry_after = parse_retry_after(resp.headers());
+                    let error_text = redaction_service::scrub_secrets(&resp.text().await.unwrap_or_default());
+                    println!("❌ {} API请求失败: {} - {}", provider, status, error_text);
+                    let message = format!("{} API请求失败: {} - {}", provider, status, error_text);
+                    let kind = status_error_kind(status);
+                    // 其他4xx是请求或配置问题，重试没有意义
+                    if !matches!(kind, ProviderErrorKind::RateLimited | ProviderErrorKind::ServerError) {
+                        return Err(ProviderError::new(kind, message));
+                    }
+                    (ProviderError::new(kind, message), retry_after)
+                }
+                Err(e) => {
+                    let kind = if e.is_timeout() { "请求超时" } else { "网络请求失败" };
+                    let detail = redaction_service::scrub_secrets(&e.to_string());
+                    println!("❌ {}{}: {}", provider, kind, detail);
+                    let message = format!("{}{}: {}", provider, kind, detail);
+                    if e.is_builder() {
+                        return Err(ProviderError::new(ProviderErrorKind::BadRequest, message));
+                    }
+                    (ProviderError::new(ProviderErrorKind::Network, message), None)
+                }
+            };
+
+            if attempt >= self.policy.max_retries {
+                record_failure(&self.breaker_key, &self.policy, &error.message);
+                return Err(error);
+            }
+
+            let delay = match retry_after {
+                Some(delay) if delay > Duration::from_secs(self.policy.max_retry_after_secs) => {
+                    println!("⏳ 服务要求 {} 秒后重试，超过上限，放弃本次请求", delay.as_secs());
+                    record_failure(&self.breaker_key, &self.policy, &error.message);
+                    return Err(error);
+                }
+                Some(delay) => delay,
+                None => self.policy.backoff(attempt),
+            };
+            attempt += 1;
+            println!("🔁 {} 毫秒后重试 ({}/{})", delay.as_millis(), attempt, self.policy.max_retries);
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// 按行读取流式响应（SSE 和 NDJSON 都以换行分隔）
+pub struct LineReader {
+    provider: String,
+    response: reqwest::Response,
+    buffer: Vec<u8>,
+    finished: bool,
+}
+
+impl LineReader {
+    fn new(provider: &str, response: reqwest::Response) -> Self {
+        Self {
+            provider: provider.to_string(),
+            response,
+            buffer: Vec::new(),
+            finished: false,
+        }
+    }
+
+    /// 读取下一行（不含换行符），响应结束时返回 None
+    pub async fn next_line(&mut self) -> Result<Option<String>, ProviderError> {
+        loop {
+            // 按字节切分，避免把多字节字符拆开
+            if let Some(pos) = self.buffer.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+                let line = String::from_utf8_lossy(&line);
+                return Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()));
+            }
+            if self.finished {
+                if self.buffer.is_empty() {
+                    return Ok(None);
+                }
+                let line = String::from_utf8_lossy(&self.buffer).trim_end().to_string();
+                self.buffer.clear();
+                return Ok(Some(line));
+            }
+
+            match self.response.chunk().await {
+                Ok(Some(chunk)) => self.buffer.extend_from_slice(&chunk),
+                Ok(None) => self.finished = true,
+                Err(e) => {
+                    println!("❌ {}流式响应中断: {}", self.provider, e);
+                    return Err(ProviderError::new(
+                        ProviderErrorKind::Network,
+                        format!("{}流式响应中断: {}", self.provider, e),
+                    ));
+                }
+            }
+        }
+    }
+
+    /// 读取下一条SSE数据（`data:` 行的内容），跳过事件名、注释和空行
+    pub async fn next_sse_data(&mut self) -> Result<Option<String>, ProviderError> {
+        while let Some(line) = self.next_line().await? {
+            if let Some(data) = line.strip_prefix("data:") {
+                return Ok(Some(data.trim_start().to_string()));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::super::mock_server::{MockResponse, MockServer};
+    use super::*;
+
+    fn policy(max_retries: u32, breaker_failure_threshold: u32) -> RequestPolicy {
+        RequestPolicy {
+            max_retries,
+            initial_backoff_ms: 1,
+            max_backoff_ms: 2,
+            breaker_failure_threshold,
+            ..RequestPolicy::default()
+        }
+    }
+
+    /// 每个测试使用独立的熔断器
+    fn client(server: &MockServer, policy: &RequestPolicy) -> HttpClient {
+        HttpClient::new(policy, format!("http-test|{}", server.url))
+    }
+
+    async fn call(client: &HttpClient, server: &MockServer) -> Result<serde_json::Value, ProviderError> {
+        client.send_json("测试", client.get(format!("{}/v1/test", server.url))).await
+    }
+
+    /// 前 `failures` 次请求返回 `failure`，之后返回 200
+    async fn failing_server(failures: usize, failure: impl Fn() -> MockResponse + Send + Sync + 'static) -> MockServer {
+        let count = Arc::new(AtomicUsize::new(0));
+        MockServer::start_with(move |_| {
+            if count.fetch_add(1, Ordering::SeqCst) < failures {
+                failure()
+            } else {
+                MockResponse::json(serde_json::json!({ "ok": true }))
+            }
+        })
+        .await
+    }
+
+    #[test]
+    fn backoff_grows_exponentially_with_jitter_and_cap() {
+        let policy = RequestPolicy { initial_backoff_ms: 100, max_backoff_ms: 1000, ..RequestPolicy::default() };
+        for _ in 0..20 {
+            let first = policy.backoff(0).as_millis();
+            assert!((50..=100).contains(&first), "{}", first);
+            let third = policy.backoff(2).as_millis();
+            assert!((200..=400).contains(&third), "{}", third);
+            let capped = policy.backoff(30).as_millis();
+            assert!((500..=1000).contains(&capped), "{}", capped);
+        }
+    }
+
+    #[test]
+    fn parses_retry_after_seconds_and_dates() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        assert_eq!(parse_retry_after(&headers), None);
+
+        headers.insert(reqwest::header::RETRY_AFTER, " 7 ".parse().unwrap());
+        assert_eq!(parse_retry_after(&headers), Some(Duration::from_secs(7)));
+
+        let date = (Utc::now() + chrono::Duration::seconds(60)).to_rfc2822();
+        headers.insert(reqwest::header::RETRY_AFTER, date.parse().unwrap());
+        let delay = parse_retry_after(&headers).unwrap();
+        assert!(delay > Duration::from_secs(55) && delay <= Duration::from_secs(60), "{:?}", delay);
+
+        // 已经过去的日期不再等待
+        headers.insert(reqwest::header::RETRY_AFTER, "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap());
+        assert_eq!(parse_retry_after(&headers), Some(Duration::ZERO));
+
+        headers.insert(reqwest::header::RETRY_AFTER, "soon".parse().unwrap());
+        assert_eq!(parse_retry_after(&headers), None);
+    }
+
+    #[tokio::test]
+    async fn retries_rate_limit_after_retry_after() {
+        let server = failing_server(1, || MockResponse::status(429).header("Retry-After", "0")).await;
+        let client = client(&server, &policy(2, 3));
+
+        assert_eq!(call(&client, &server).await.unwrap(), serde_json::json!({ "ok": true }));
+        assert_eq!(server.requests().len(), 2);
+        assert_eq!(client.circuit_status().consecutive_failures, 0);
+    }
+
+    #[tokio::test]
+    async fn gives_up_when_retry_after_exceeds_limit() {
+        let server = failing_server(1, || MockResponse::status(429).header("Retry-After", "3600")).await;
+        let client = client(&server, &policy(2, 3));
+
+        let error = call(&client, &server).await.unwrap_err();
+        assert_eq!(error.kind, ProviderErrorKind::RateLimited);
+        assert_eq!(server.requests().len(), 1);
+        assert_eq!(client.circuit_status().consecutive_failures, 1);
+    }
+
+    #[tokio::test]
+    async fn server_errors_open_breaker_after_threshold() {
+        let server = failing_server(usize::MAX, || MockResponse::status(503)).await;
+        let client = client(&server, &policy(1, 2));
+
+        let error = call(&client, &server).await.unwrap_err();
+        assert_eq!(error.kind, ProviderErrorKind::ServerError);
+        assert_eq!(server.requests().len(), 2); // 首次请求加一次重试
+        assert_eq!(client.circuit_status().state, CircuitState::Closed);
+
+        call(&client, &server).await.unwrap_err();
+        let status = client.circuit_status();
+        assert_eq!(status.state, CircuitState::Open);
+        assert_eq!(status.consecutive_failures, 2);
+        assert!(status.retry_at.is_some());
+
+        // 熔断期间不发出请求
+        let error = call(&client, &server).await.unwrap_err();
+        assert_eq!(error.kind, ProviderErrorKind::CircuitOpen);
+        assert_eq!(server.requests().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn successful_probe_closes_breaker() {
+        let server = failing_server(1, || MockResponse::status(500)).await;
+        // 冷却时间为 0，熔断后立即进入半开状态
+        let policy = RequestPolicy { breaker_cooldown_secs: 0, ..policy(0, 1) };
+        let client = client(&server, &policy);
+
+        call(&client, &server).await.unwrap_err();
+        assert_eq!(client.circuit_status().state, CircuitState::HalfOpen);
+
+        call(&client, &server).await.unwrap();
+        let status = client.circuit_status();
+        assert_eq!(status.state, CircuitState::Closed);
+        assert_eq!(status.consecutive_failures, 0);
+    }
+
+    #[test]
+    fn half_open_admits_a_single_probe() {
+        let key = "http-test|half-open";
+        let policy = RequestPolicy { breaker_cooldown_secs: 0, ..policy(0, 1) };
+        record_failure(key, &policy, "down");
+        assert_eq!(circuit_status(key).state, CircuitState::HalfOpen);
+
+        let probe = acquire(key).unwrap();
+        assert!(probe.is_some());
+        let rejected = acquire(key).err().unwrap();
+        assert_eq!(rejected.state, CircuitState::HalfOpen);
+        assert!(rejected.unavailable_message().contains("down"));
+
+        // 试探结束后（例如返回了不计入熔断的4xx）允许下一个试探
+        drop(probe);
+        assert!(acquire(key).unwrap().is_some());
+
+        // 试探失败后重新熔断
+        let probe = acquire(key).unwrap();
+        record_failure(key, &RequestPolicy { breaker_cooldown_secs: 60, ..policy }, "still down");
+        drop(probe);
+        assert_eq!(acquire(key).err().unwrap().state, CircuitState::Open);
+        record_success(key);
+        assert!(acquire(key).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn send_json_once_does_not_retry_or_count_failures() {
+        let server = failing_server(usize::MAX, || MockResponse::status(503)).await;
+        let client = client(&server, &policy(3, 1));
+
+        let request = client.get(format!("{}/v1/test", server.url));
+        let error = client.send_json_once("测试", request).await.unwrap_err();
+        assert_eq!(error.kind, ProviderErrorKind::ServerError);
+        assert_eq!(server.requests().len(), 1);
+        assert_eq!(client.circuit_status().consecutive_failures, 0);
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_not_retried() {
+        let server = failing_server(usize::MAX, || MockResponse::status(400)).await;
+        let client = client(&server, &policy(3, 1));
+
+        let error = call(&client, &server).await.unwrap_err();
+        assert_eq!(error.kind, ProviderErrorKind::BadRequest);
+        assert_eq!(server.requests().len(), 1);
+        assert_eq!(client.circuit_status().state, CircuitState::Closed);
+
+        let server = failing_server(usize::MAX, || MockResponse::status(401)).await;
+        let client = self::client(&server, &policy(3, 1));
+        assert_eq!(call(&client, &server).await.unwrap_err().kind, ProviderErrorKind::Auth);
+        assert_eq!(server.requests().len(), 1);
+    }
+}
@@ -0,0 +1,175 @@
+pub mod openai;
+pub mod ollama;
+pub mod claude;
+pub mod gemini;
+pub mod azure_openai;
+pub mod http;
+#[cfg(test)]
+mod mock_server;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::services::ai_service::{AIConfig, APITestResult, ChatOptions, ModelInfo};
+
+pub use azure_openai::AzureOpenAiProvider;
+pub use claude::ClaudeProvider;
+pub use gemini::GeminiProvider;
+pub use http::{CircuitState, CircuitStatus, HttpClient, LineReader, RequestPolicy};
+pub use ollama::OllamaProvider;
+pub use openai::OpenAiProvider;
+
+/// 提供者支持的功能
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ProviderCapabilities {
+    pub vision: bool,           // 可以在请求中附带图像
+    pub json_mode: bool,        // 有原生的JSON输出模式
+    pub list_models: bool,      // 可以获取模型列表
+    pub requires_api_key: bool, // 调用前必须填写API Key
+    pub streaming: bool,        // 支持流式输出
+}
+
+/// 一次对话请求
+pub struct ChatRequest<'a> {
+    pub model: &'a str,
+    pub content: &'a str,
+    pub image_base64: Option<&'a str>, // JPEG截图的base64编码
+    pub options: &'a ChatOptions,
+}
+
+/// 调用失败的类型，决定是否切换到备用服务
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum ProviderErrorKind {
+    RateLimited,     // 429
+    ServerError,     // 5xx
+    Network,         // 连接失败或超时
+    CircuitOpen,     // 熔断中，未发出请求
+    Auth,            // 401/403
+    BadRequest,      // 其他4xx
+    InvalidResponse, // 响应格式无法解析
+    Unsupported,     // 该服务不支持此次请求（API类型、图像输入等）
+}
+
+/// 提供者调用错误
+#[derive(Debug, Clone)]
+pub struct ProviderError {
+    pub kind: ProviderErrorKind,
+    pub message: String,
+}
+
+impl ProviderError {
+    pub fn new(kind: ProviderErrorKind, message: impl Into<String>) -> Self {
+        Self { kind, message: message.into() }
+    }
+
+    pub fn invalid_response(message: impl Into<String>) -> Self {
+        Self::new(ProviderErrorKind::InvalidResponse, message)
+    }
+
+    pub fn unsupported(message: impl Into<String>) -> Self {
+        Self::new(ProviderErrorKind::Unsupported, message)
+    }
+}
+
+impl std::fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<ProviderError> for String {
+    fn from(error: ProviderError) -> Self {
+        error.message
+    }
+}
+
+/// 模型返回的token用量
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+impl TokenUsage {
+    /// 从响应JSON中读取用量，两个字段都缺失时返回 None
+    pub(crate) fn from_json(json: &serde_json::Value, prompt_pointer: &str, completion_pointer: &str) -> Option<Self> {
+        let prompt_tokens = json.pointer(prompt_pointer).and_then(|t| t.as_u64());
+        let completion_tokens = json.pointer(completion_pointer).and_then(|t| t.as_u64());
+        if prompt_tokens.is_none() && completion_tokens.is_none() {
+            return None;
+        }
+        Some(Self {
+            prompt_tokens: prompt_tokens.unwrap_or(0),
+            completion_tokens: completion_tokens.unwrap_or(0),
+        })
+    }
+}
+
+/// 一次对话的回复
+#[derive(Debug, Clone)]
+pub struct ChatResponse {
+    pub text: String,
+    pub usage: Option<TokenUsage>, // 服务未返回用量时为空
+}
+
+/// 流式输出时接收每段文本的回调
+pub type ChunkHandler<'a> = dyn FnMut(&str) + Send + 'a;
+
+/// AI服务提供者
+///
+/// 每种API格式实现一次，`AIService` 只通过这个接口调用，新增提供者时在 `create_provider` 中注册即可。
+#[async_trait]
+pub trait AiProvider: Send + Sync {
+    /// 显示名称，用于日志
+    fn name(&self) -> &'static str;
+
+    fn capabilities(&self) -> ProviderCapabilities;
+
+    /// 发送单轮对话并返回模型回复的文本
+    async fn chat(&self, request: &ChatRequest<'_>) -> Result<ChatResponse, ProviderError>;
+
+    /// 流式对话：每收到一段文本调用一次 `on_chunk`，返回完整回复
+    ///
+    /// 不支持流式输出的提供者一次性返回全部文本。
+    async fn chat_stream(
+        &self,
+        request: &ChatRequest<'_>,
+        on_chunk: &mut ChunkHandler<'_>,
+    ) -> Result<ChatResponse, ProviderError> {
+        let response = self.chat(request).await?;
+        on_chunk(&response.text);
+        Ok(response)
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, String>;
+
+    async fn test_connection(&self) -> APITestResult;
+}
+
+/// 根据配置中的API类型创建提供者
+pub fn create_provider(config: &AIConfig, http: HttpClient) -> Result<Box<dyn AiProvider>, String> {
+    match config.api_type.as_str() {
+        "OpenAI Compatible" => Ok(Box::new(OpenAiProvider::new(http, config))),
+        "Ollama (本地)" => Ok(Box::new(OllamaProvider::new(http, config))),
+        "Claude API" => Ok(Box::new(ClaudeProvider::new(http, config))),
+        "Google Gemini" => Ok(Box::new(GeminiProvider::new(http, config))),
+        "Azure OpenAI" => Ok(Box::new(AzureOpenAiProvider::new(http, config))),
+        other => Err(format!("不支持的API类型: {}", other)),
+    }
+}
+
+/// 构造测试结果
+pub(crate) fn test_result(
+    success: bool,
+    message: impl Into<String>,
+    start_time: std::time::Instant,
+    model_used: Option<String>,
+) -> APITestResult {
+    APITestResult {
+        success,
+        message: message.into(),
+        response_time_ms: start_time.elapsed().as_millis() as u64,
+        model_used,
+        answered_by: None,
+    }
+}
@@ -0,0 +1,351 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use super::{test_result, AiProvider, ChatRequest, ChatResponse, ChunkHandler, HttpClient, ProviderCapabilities, ProviderError, ProviderErrorKind, TokenUsage};
+use crate::services::ai_service::{AIConfig, APITestResult, ModelInfo};
+use crate::services::redaction_service;
+
+/// 同时查询 /api/show 的最大数量
+const SHOW_CONCURRENCY: usize = 4;
+
+/// /api/show 返回的模型详情
+struct OllamaModelDetails {
+    context_length: Option<u64>,
+    supports_vision: Option<bool>,
+}
+
+/// Ollama本地模型
+pub struct OllamaProvider {
+    http: HttpClient,
+    base_url: String,
+}
+
+impl OllamaProvider {
+    pub fn new(http: HttpClient, config: &AIConfig) -> Self {
+        // 界面上的默认地址带 /v1（OpenAI兼容端点），原生API不需要
+        Self {
+            http,
+            base_url: config.api_url.replace("/v1", "").trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// 构造 /api/generate 请求体
+    fn generate_body(request: &ChatRequest<'_>, stream: bool) -> serde_json::Value {
+        let mut body = serde_json::json!({
+            "model": request.model,
+            "prompt": request.content,
+            "stream": stream
+        });
+        if let Some(image) = request.image_base64 {
+            body["images"] = serde_json::json!([image]);
+        }
+        if request.options.json_mode {
+            body["format"] = serde_json::json!("json");
+        }
+        // 未指定时使用模型自带的参数
+        let mut options = serde_json::Map::new();
+        if let Some(max_tokens) = request.options.max_tokens {
+            options.insert("num_predict".to_string(), serde_json::json!(max_tokens));
+        }
+        if let Some(temperature) = request.options.temperature {
+            options.insert("temperature".to_string(), serde_json::json!(temperature));
+        }
+        if !options.is_empty() {
+            body["options"] = serde_json::Value::Object(options);
+        }
+        body
+    }
+
+    fn generate_request(&self, body: &serde_json::Value) -> reqwest::RequestBuilder {
+        self.http
+            .post(format!("{}/api/generate", self.base_url))
+            .header("Content-Type", "application/json")
+            .json(body)
+    }
+
+    /// 通过 /api/show 获取模型详情
+    ///
+    /// 详情只是附加信息：只请求一次，失败不重试也不计入熔断器，避免列出模型时影响监控。
+    async fn show_model(http: &HttpClient, base_url: &str, name: &str) -> Result<OllamaModelDetails, ProviderError> {
+        let request = http
+            .post(format!("{}/api/show", base_url))
+            .json(&serde_json::json!({ "model": name }));
+        let json = http.send_json_once("Ollama", request).await?;
+
+        // model_info 中的键带架构前缀，如 "llama.context_length"
+        let context_length = json.get("model_info")
+            .and_then(|m| m.as_object())
+            .and_then(|info| {
+                info.iter()
+                    .find(|(key, _)| key.ends_with(".context_length"))
+                    .and_then(|(_, value)| value.as_u64())
+            });
+
+        // 新版本直接返回 capabilities，旧版本通过是否有视觉投影层判断
+        let supports_vision = match json.get("capabilities").and_then(|c| c.as_array()) {
+            Some(capabilities) => Some(capabilities.iter().any(|c| c.as_str() == Some("vision"))),
+            None => json.get("projector_info").map(|_| true),
+        };
+
+        Ok(OllamaModelDetails { context_length, supports_vision })
+    }
+}
+
+#[async_trait]
+impl AiProvider for OllamaProvider {
+    fn name(&self) -> &'static str {
+        "Ollama"
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            vision: true,
+            json_mode: true,
+            list_models: true,
+            requires_api_key: false,
+            streaming: true,
+        }
+    }
+
+    async fn chat(&self, request: &ChatRequest<'_>) -> Result<ChatResponse, ProviderError> {
+        println!("📞 调用Ollama本地API...");
+
+        let body = Self::generate_body(request, false);
+        let json = self.http.send_json(self.name(), self.generate_request(&body)).await?;
+
+        match json.get("response").and_then(|r| r.as_str()) {
+            Some(text) => {
+                println!("✅ Ollama API调用成功，响应长度: {} 字符", text.len());
+                Ok(ChatResponse {
+                    text: text.to_string(),
+                    usage: TokenUsage::from_json(&json, "/prompt_eval_count", "/eval_count"),
+                })
+            }
+            None => {
+                println!("❌ Ollama响应格式解析失败: {:?}", json);
+                Err(ProviderError::invalid_response("Ollama响应格式解析失败"))
+            }
+        }
+    }
+
+    async fn chat_stream(
+        &self,
+        request: &ChatRequest<'_>,
+        on_chunk: &mut ChunkHandler<'_>,
+    ) -> Result<ChatResponse, ProviderError> {
+        println!("📞 调用Ollama本地API（流式）...");
+
+        let body = Self::generate_body(request, true);
+        let mut reader = self.http.send_stream(self.name(), self.generate_request(&body)).await?;
+
+        // 每行一个JSON对象，最后一行 done 为 true
+        let mut text = String::new();
+        let mut usage = None;
+        while let Some(line) = reader.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let json: serde_json::Value = serde_json::from_str(&line)
+                .map_err(|e| ProviderError::invalid_response(format!("Ollama流式响应解析失败: {}", e)))?;
+            if let Some(error) = json.get("error").and_then(|e| e.as_str()) {
+                println!("❌ Ollama流式响应错误: {}", error);
+                return Err(ProviderError::new(ProviderErrorKind::ServerError, format!("Ollama流式响应错误: {}", error)));
+            }
+            if let Some(chunk) = json.get("response").and_then(|r| r.as_str()) {
+                if !chunk.is_empty() {
+                    text.push_str(chunk);
+                    on_chunk(chunk);
+                }
+            }
+            if json.get("done").and_then(|d| d.as_bool()).unwrap_or(false) {
+                usage = TokenUsage::from_json(&json, "/prompt_eval_count", "/eval_count");
+                break;
+            }
+        }
+
+        println!("✅ Ollama 流式响应完成，长度: {} 字符", text.len());
+        Ok(ChatResponse { text, usage })
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, String> {
+        let request = self.http.get(format!("{}/api/tags", self.base_url));
+        let json = self.http.send_json(self.name(), request).await?;
+        let models = json.get("models")
+            .and_then(|m| m.as_array())
+            .ok_or_else(|| "Ollama模型列表格式解析失败".to_string())?;
+
+        let mut result: Vec<ModelInfo> = Vec::with_capacity(models.len());
+        for model in models {
+            let Some(name) = model.get("name").and_then(|n| n.as_str()) else {
+                continue;
+            };
+            let mut info = ModelInfo::new(name, "model");
+            info.owned_by = Some("ollama".to_string());
+            info.created = model.get("modified_at")
+                .and_then(|t| t.as_str())
+                .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+                .map(|t| t.timestamp().max(0) as u64);
+            info.parameter_size = model.pointer("/details/parameter_size")
+                .and_then(|p| p.as_str())
+                .map(str::to_string);
+            // 多模态模型的 families 中带有视觉编码器
+            let families: Vec<&str> = model.pointer("/details/families")
+                .and_then(|f| f.as_array())
+                .map(|f| f.iter().filter_map(|f| f.as_str()).collect())
+                .unwrap_or_default();
+            if families.iter().any(|f| matches!(*f, "clip" | "mllama")) {
+                info.supports_vision = Some(true);
+            }
+            result.push(info);
+        }
+
+        // 上下文长度和能力只能通过 /api/show 获取，并发查询并限制同时进行的数量
+        let permits = Arc::new(Semaphore::new(SHOW_CONCURRENCY));
+        let mut tasks = JoinSet::new();
+        for (index, info) in result.iter().enumerate() {
+            let (http, base_url, name, permits) = (self.http.clone(), self.base_url.clone(), info.id.clone(), permits.clone());
+            tasks.spawn(async move {
+                let _permit = permits.acquire_owned().await;
+                (index, Self::show_model(&http, &base_url, &name).await)
+            });
+        }
+        while let Some(joined) = tasks.join_next().await {
+            let Ok((index, details)) = joined else {
+                continue;
+            };
+            let info = &mut result[index];
+            match details {
+                Ok(details) => {
+                    info.context_length = details.context_length;
+                    info.supports_vision = details.supports_vision.or(info.supports_vision);
+                }
+                Err(e) => println!("⚠️ 获取Ollama模型 {} 详情失败: {}", info.id, e),
+            }
+        }
+
+        println!("📋 Ollama本地模型: {} 个", result.len());
+        Ok(result)
+    }
+
+    async fn test_connection(&self) -> APITestResult {
+        println!("🔌 测试Ollama本地API连接...");
+        let start_time = std::time::Instant::now();
+
+        // Ollama的API端点通常不需要认证，直接测试模型列表
+        let response = self.http
+            .get(format!("{}/api/tags", self.base_url))
+            .header("Content-Type", "application/json")
+            .send()
+            .await;
+
+        match response {
+            Ok(resp) => {
+                println!("📨 Ollama API测试响应状态: {}", resp.status());
+                if resp.status().is_success() {
+                    match resp.json::<serde_json::Value>().await {
+                        Ok(json) => {
+                            let model_count = json.get("models")
+                                .and_then(|m| m.as_array())
+                                .map(|arr| arr.len())
+                                .unwrap_or(0);
+
+                            println!("✅ Ollama API连接成功，找到 {} 个模型", model_count);
+                            test_result(true, format!("Ollama连接成功！找到 {} 个本地模型", model_count), start_time, None)
+                        }
+                        Err(_) => {
+                            println!("⚠️ Ollama API连接成功，但响应格式异常");
+                            test_result(true, "Ollama连接成功，但无法解析模型列表", start_time, None)
+                        }
+                    }
+                } else {
+                    let status = resp.status();
+                    let error_text = redaction_service::scrub_secrets(&resp.text().await.unwrap_or_default());
+                    println!("❌ Ollama API测试失败: {} - {}", status, error_text);
+                    test_result(false, format!("Ollama API错误: {} - 请确认Ollama服务已启动", status), start_time, None)
+                }
+            }
+            Err(e) => {
+                println!("❌ Ollama API网络连接失败: {}", e);
+                test_result(
+                    false,
+                    format!("Ollama连接失败: {} - 请检查Ollama是否运行在 {}", e, self.base_url),
+                    start_time,
+                    None,
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::mock_server::{MockResponse, MockServer};
+    use super::*;
+    use crate::services::ai_providers::{CircuitState, RequestPolicy};
+
+    fn provider(api_url: &str) -> OllamaProvider {
+        let config = AIConfig {
+            api_type: "Ollama".to_string(),
+            api_url: format!("{}/v1", api_url),
+            ..AIConfig::default()
+        };
+        let policy = RequestPolicy { breaker_failure_threshold: 1, ..RequestPolicy::default() };
+        OllamaProvider::new(HttpClient::new(&policy, format!("ollama-test|{}", api_url)), &config)
+    }
+
+    #[tokio::test]
+    async fn lists_models_with_details() {
+        let server = MockServer::start_with(|request| match request.path.as_str() {
+            "/api/tags" => MockResponse::json(serde_json::json!({
+                "models": [
+                    {
+                        "name": "llava:7b",
+                        "modified_at": "2024-05-01T10:00:00Z",
+                        "details": { "parameter_size": "7B", "families": ["llama", "clip"] }
+                    },
+                    { "name": "qwen2:7b", "details": { "parameter_size": "7.6B", "families": ["qwen2"] } },
+                    { "name": "broken:latest", "details": { "families": ["clip"] } },
+                    { "size": 1 }
+                ]
+            })),
+            "/api/show" => match request.json()["model"].as_str() {
+                Some("llava:7b") => MockResponse::json(serde_json::json!({
+                    "model_info": { "llama.context_length": 4096 },
+                    "capabilities": ["completion", "vision"]
+                })),
+                Some("qwen2:7b") => MockResponse::json(serde_json::json!({
+                    "model_info": { "qwen2.context_length": 32768 },
+                    "capabilities": ["completion"]
+                })),
+                _ => MockResponse::status(500),
+            },
+            _ => MockResponse::status(404),
+        })
+        .await;
+        let provider = provider(&server.url);
+
+        let models = provider.list_models().await.unwrap();
+        let summary: Vec<(&str, Option<u64>, Option<bool>)> =
+            models.iter().map(|m| (m.id.as_str(), m.context_length, m.supports_vision)).collect();
+        assert_eq!(
+            summary,
+            [
+                ("llava:7b", Some(4096), Some(true)),
+                ("qwen2:7b", Some(32768), Some(false)),
+                // 详情获取失败时保留列表中的信息
+                ("broken:latest", None, Some(true)),
+            ]
+        );
+        assert_eq!(models[0].parameter_size.as_deref(), Some("7B"));
+        assert_eq!(models[0].created, Some(1_714_557_600));
+
+        // 每个模型只查询一次详情，失败不重试也不触发熔断
+        let requests = server.requests();
+        assert_eq!(requests.iter().filter(|r| r.path == "/api/tags").count(), 1);
+        assert_eq!(requests.iter().filter(|r| r.path == "/api/show" && r.method == "POST").count(), 3);
+        assert_eq!(provider.http.circuit_status().state, CircuitState::Closed);
+    }
+}
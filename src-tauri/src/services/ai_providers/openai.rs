@@ -0,0 +1,209 @@
+use async_trait::async_trait;
+
+use super::{test_result, AiProvider, ChatRequest, ChatResponse, ChunkHandler, HttpClient, LineReader, ProviderCapabilities, ProviderError, TokenUsage};
+use crate::services::ai_service::{AIConfig, APITestResult, ModelInfo, ModelsResponse};
+use crate::services::redaction_service;
+
+/// OpenAI兼容API（OpenAI、DeepSeek、LM Studio、vLLM等）
+pub struct OpenAiProvider {
+    http: HttpClient,
+    api_url: String,
+    api_key: String,
+}
+
+impl OpenAiProvider {
+    pub fn new(http: HttpClient, config: &AIConfig) -> Self {
+        Self {
+            http,
+            api_url: config.api_url.trim_end_matches('/').to_string(),
+            api_key: config.api_key.clone(),
+        }
+    }
+
+    fn chat_request(&self, body: &serde_json::Value) -> reqwest::RequestBuilder {
+        self.http
+            .post(format!("{}/chat/completions", self.api_url))
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Content-Type", "application/json")
+            .json(body)
+    }
+}
+
+/// 构造 chat/completions 请求体（Azure OpenAI 共用，Azure 不需要 model 字段）
+pub(super) fn chat_body(request: &ChatRequest<'_>, model: Option<&str>) -> serde_json::Value {
+    // 附带图像时使用多段内容：文本 + image_url (data URL)
+    let message_content = match request.image_base64 {
+        Some(image) => serde_json::json!([
+            { "type": "text", "text": request.content },
+            {
+                "type": "image_url",
+                "image_url": { "url": format!("data:image/jpeg;base64,{}", image) }
+            }
+        ]),
+        None => serde_json::json!(request.content),
+    };
+
+    let mut body = serde_json::json!({
+        "messages": [
+            {
+                "role": "user",
+                "content": message_content
+            }
+        ],
+        "max_tokens": request.options.max_tokens.unwrap_or(500),
+        "temperature": request.options.temperature.unwrap_or(0.3)
+    });
+    if let Some(model) = model {
+        body["model"] = serde_json::json!(model);
+    }
+    if request.options.json_mode {
+        body["response_format"] = serde_json::json!({ "type": "json_object" });
+    }
+    body
+}
+
+/// 从 chat/completions 响应中取出回复文本
+pub(super) fn parse_chat_response(provider: &str, json: &serde_json::Value) -> Result<ChatResponse, ProviderError> {
+    match json.pointer("/choices/0/message/content").and_then(|c| c.as_str()) {
+        Some(content) => {
+            println!("✅ {} API调用成功，响应长度: {} 字符", provider, content.len());
+            Ok(ChatResponse {
+                text: content.to_string(),
+                usage: TokenUsage::from_json(json, "/usage/prompt_tokens", "/usage/completion_tokens"),
+            })
+        }
+        None => {
+            println!("❌ {}响应格式解析失败: {:?}", provider, json);
+            Err(ProviderError::invalid_response("AI响应格式解析失败"))
+        }
+    }
+}
+
+/// 读取 chat/completions 的SSE流（Azure OpenAI 共用）
+pub(super) async fn read_chat_stream(
+    provider: &str,
+    mut reader: LineReader,
+    on_chunk: &mut ChunkHandler<'_>,
+) -> Result<ChatResponse, ProviderError> {
+    let mut text = String::new();
+    let mut usage = None;
+    while let Some(data) = reader.next_sse_data().await? {
+        if data == "[DONE]" {
+            break;
+        }
+        let json: serde_json::Value = serde_json::from_str(&data)
+            .map_err(|e| ProviderError::invalid_response(format!("{}流式响应解析失败: {}", provider, e)))?;
+        // 服务端开启用量统计时，最后一条数据带 usage
+        if let Some(chunk_usage) = TokenUsage::from_json(&json, "/usage/prompt_tokens", "/usage/completion_tokens") {
+            usage = Some(chunk_usage);
+        }
+        // Azure 的第一条数据只有内容过滤结果，没有 choices
+        if let Some(delta) = json.pointer("/choices/0/delta/content").and_then(|c| c.as_str()) {
+            if !delta.is_empty() {
+                text.push_str(delta);
+                on_chunk(delta);
+            }
+        }
+    }
+    println!("✅ {} 流式响应完成，长度: {} 字符", provider, text.len());
+    Ok(ChatResponse { text, usage })
+}
+
+#[async_trait]
+impl AiProvider for OpenAiProvider {
+    fn name(&self) -> &'static str {
+        "OpenAI"
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            vision: true,
+            json_mode: true,
+            list_models: true,
+            requires_api_key: true,
+            streaming: true,
+        }
+    }
+
+    async fn chat(&self, request: &ChatRequest<'_>) -> Result<ChatResponse, ProviderError> {
+        println!("📞 调用OpenAI兼容API...");
+
+        let mut body = chat_body(request, Some(request.model));
+        match self.http.send_json(self.name(), self.chat_request(&body)).await {
+            Ok(json) => parse_chat_response(self.name(), &json),
+            // 部分兼容服务不支持 response_format，去掉后重试一次，由提示词约束输出格式
+            Err(e) if request.options.json_mode && e.message.contains("response_format") => {
+                println!("⚠️ 服务不支持JSON模式，改用普通请求");
+                if let Some(body) = body.as_object_mut() {
+                    body.remove("response_format");
+                }
+                let json = self.http.send_json(self.name(), self.chat_request(&body)).await?;
+                parse_chat_response(self.name(), &json)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn chat_stream(
+        &self,
+        request: &ChatRequest<'_>,
+        on_chunk: &mut ChunkHandler<'_>,
+    ) -> Result<ChatResponse, ProviderError> {
+        println!("📞 调用OpenAI兼容API（流式）...");
+
+        let mut body = chat_body(request, Some(request.model));
+        body["stream"] = serde_json::json!(true);
+        let reader = self.http.send_stream(self.name(), self.chat_request(&body)).await?;
+        read_chat_stream(self.name(), reader, on_chunk).await
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, String> {
+        let response = self.http
+            .get(format!("{}/models", self.api_url))
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Content-Type", "application/json");
+        let json = self.http.send_json(self.name(), response).await?;
+        let models: ModelsResponse = serde_json::from_value(json)
+            .map_err(|e| format!("模型列表解析失败: {}", e))?;
+        Ok(models.data)
+    }
+
+    async fn test_connection(&self) -> APITestResult {
+        println!("🔌 测试OpenAI兼容API连接...");
+        let start_time = std::time::Instant::now();
+
+        let response = self.http
+            .get(format!("{}/models", self.api_url))
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Content-Type", "application/json")
+            .send()
+            .await;
+
+        match response {
+            Ok(resp) => {
+                println!("📨 OpenAI API测试响应状态: {}", resp.status());
+                if resp.status().is_success() {
+                    match resp.json::<ModelsResponse>().await {
+                        Ok(models) => {
+                            println!("✅ OpenAI API连接成功，找到 {} 个模型", models.data.len());
+                            test_result(true, format!("连接成功！找到 {} 个可用模型", models.data.len()), start_time, None)
+                        }
+                        Err(_) => {
+                            println!("⚠️ OpenAI API连接成功，但无法解析模型列表");
+                            test_result(true, "连接成功，但无法解析模型列表", start_time, None)
+                        }
+                    }
+                } else {
+                    let status = resp.status();
+                    let error_text = redaction_service::scrub_secrets(&resp.text().await.unwrap_or_default());
+                    println!("❌ OpenAI API测试失败: {} - {}", status, error_text);
+                    test_result(false, format!("API返回错误: {} - {}", status, error_text), start_time, None)
+                }
+            }
+            Err(e) => {
+                println!("❌ OpenAI API网络连接失败: {}", e);
+                test_result(false, format!("连接失败: {}", e), start_time, None)
+            }
+        }
+    }
+}
@@ -1,14 +1,370 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use crate::models::*;
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
+use crate::services::ai_providers::{
+    self, AiProvider, ChatRequest, ChatResponse, ChunkHandler, CircuitState, CircuitStatus, HttpClient, ProviderError,
+    ProviderErrorKind, RequestPolicy, TokenUsage,
+};
+use crate::services::prompt_budget;
+use crate::services::secret_service::{self, SecretBackend};
+use crate::services::usage_service::{self, ModelPrice, UsageBudget, UsageRecord, UsageService};
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct AIConfig {
     pub api_type: String,
     pub api_url: String,
     pub api_key: String,
     pub detection_model: String,
     pub report_model: String,
+    #[serde(default)]
+    pub vision_models: Vec<String>, // 支持图像输入的模型
+    #[serde(default)]
+    pub api_version: Option<String>, // Azure OpenAI 的 api-version，为空时使用默认版本
+    #[serde(default)]
+    pub request_policy: RequestPolicy, // 超时、重试和熔断设置
+    #[serde(default)]
+    pub detection_fallbacks: Vec<FallbackEndpoint>, // 检测模型的备用服务，按顺序尝试
+    #[serde(default)]
+    pub report_fallbacks: Vec<FallbackEndpoint>,    // 报告模型的备用服务，按顺序尝试
+    #[serde(default)]
+    pub failover: FailoverPolicy,
+    #[serde(default)]
+    pub detection_generation: GenerationSettings,
+    #[serde(default = "default_report_generation")]
+    pub report_generation: GenerationSettings,
+    #[serde(default = "usage_service::default_price_table")]
+    pub prices: Vec<ModelPrice>, // 用于计算费用的价格表
+    #[serde(default)]
+    pub budget: UsageBudget,     // 超出后专注检测只使用本地模型或应用规则
+}
+
+fn is_local_endpoint(api_type: &str, api_url: &str) -> bool {
+    let url = api_url.to_lowercase();
+    api_type == "Ollama (本地)"
+        || ["://localhost", "://127.0.0.1", "://[::1]"].iter().any(|host| url.contains(host))
+}
+
+/// 生成参数，为空时使用提供者的默认值
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct GenerationSettings {
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+}
+
+/// 报告需要较长的输出，默认不沿用检测时的500 token上限
+fn default_report_generation() -> GenerationSettings {
+    GenerationSettings {
+        max_tokens: Some(2000),
+        temperature: Some(0.7),
+    }
+}
+
+/// 备用AI服务（提供者 + 模型）
+#[derive(Serialize, Deserialize, Clone)]
+pub struct FallbackEndpoint {
+    #[serde(default)]
+    pub id: String, // 稳定的编号，API密钥按它保存；为空时自动生成
+    pub api_type: String,
+    pub api_url: String,
+    #[serde(default)]
+    pub api_key: String,
+    pub model: String,
+    #[serde(default)]
+    pub api_version: Option<String>,
+}
+
+/// 哪些错误会切换到下一个备用服务
+///
+/// 认证失败、请求参数错误通常是配置问题，默认不切换，以免掩盖错误；
+/// 服务不支持本次请求（如不支持图像输入）时总是切换。
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FailoverPolicy {
+    #[serde(default = "default_true")]
+    pub on_rate_limit: bool,
+    #[serde(default = "default_true")]
+    pub on_server_error: bool,
+    #[serde(default = "default_true")]
+    pub on_network_error: bool, // 连接失败和超时
+    #[serde(default = "default_true")]
+    pub on_circuit_open: bool,
+    #[serde(default)]
+    pub on_auth_error: bool,
+    #[serde(default)]
+    pub on_bad_request: bool,
+    #[serde(default)]
+    pub on_invalid_response: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for FailoverPolicy {
+    fn default() -> Self {
+        Self {
+            on_rate_limit: true,
+            on_server_error: true,
+            on_network_error: true,
+            on_circuit_open: true,
+            on_auth_error: false,
+            on_bad_request: false,
+            on_invalid_response: false,
+        }
+    }
+}
+
+impl FailoverPolicy {
+    pub fn should_fail_over(&self, kind: ProviderErrorKind) -> bool {
+        match kind {
+            ProviderErrorKind::RateLimited => self.on_rate_limit,
+            ProviderErrorKind::ServerError => self.on_server_error,
+            ProviderErrorKind::Network => self.on_network_error,
+            ProviderErrorKind::CircuitOpen => self.on_circuit_open,
+            ProviderErrorKind::Auth => self.on_auth_error,
+            ProviderErrorKind::BadRequest => self.on_bad_request,
+            ProviderErrorKind::InvalidResponse => self.on_invalid_response,
+            ProviderErrorKind::Unsupported => true,
+        }
+    }
+}
+
+/// 实际给出回复的AI服务
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AnsweredBy {
+    pub api_type: String,
+    pub model: String,
+    pub fallback: bool, // 由备用服务回复
+}
+
+/// 一次模型调用的回复
+#[derive(Debug, Clone)]
+pub struct AIReply {
+    pub text: String,
+    pub answered_by: AnsweredBy,
+}
+
+/// 日志中不输出API密钥
+fn redacted_key(api_key: &str) -> &'static str {
+    if api_key.is_empty() { "<未设置>" } else { "<已隐藏>" }
+}
+
+impl std::fmt::Debug for AIConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AIConfig")
+            .field("api_type", &self.api_type)
+            .field("api_url", &self.api_url)
+            .field("api_key", &redacted_key(&self.api_key))
+            .field("detection_model", &self.detection_model)
+            .field("report_model", &self.report_model)
+            .field("vision_models", &self.vision_models)
+            .field("api_version", &self.api_version)
+            .field("request_policy", &self.request_policy)
+            .field("detection_fallbacks", &self.detection_fallbacks)
+            .field("report_fallbacks", &self.report_fallbacks)
+            .field("failover", &self.failover)
+            .field("detection_generation", &self.detection_generation)
+            .field("report_generation", &self.report_generation)
+            .field("prices", &self.prices)
+            .field("budget", &self.budget)
+            .finish()
+    }
+}
+
+impl std::fmt::Debug for FallbackEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FallbackEndpoint")
+            .field("id", &self.id)
+            .field("api_type", &self.api_type)
+            .field("api_url", &self.api_url)
+            .field("api_key", &redacted_key(&self.api_key))
+            .field("model", &self.model)
+            .field("api_version", &self.api_version)
+            .finish()
+    }
+}
+
+/// 密钥存储中的名称
+///
+/// `scope` 区分保存配置的文件，`role` 区分主服务和各个备用服务（如 `fallback:<编号>`），
+/// 同一地址的不同账号各自保存，互不覆盖；备用服务调整顺序后仍能找到自己的密钥。
+fn secret_name(scope: &str, role: &str, api_type: &str, api_url: &str) -> String {
+    format!("{}:{}:{}|{}", scope, role, api_type, api_url.trim_end_matches('/'))
+}
+
+/// 旧版本按服务地址合并保存的名称，只用于读取已保存的密钥
+fn legacy_secret_name(scope: &str, api_type: &str, api_url: &str) -> String {
+    format!("{}:{}|{}", scope, api_type, api_url.trim_end_matches('/'))
+}
+
+/// 配置中的一个密钥位置
+struct SecretSlot<'a> {
+    name: String,
+    legacy_name: String,
+    key: &'a mut String,
+}
+
+impl AIConfig {
+    /// 为没有编号的备用服务生成编号（旧版本的配置），需要在保存密钥之前调用
+    pub fn ensure_endpoint_ids(&mut self) {
+        for endpoint in self.detection_fallbacks.iter_mut().chain(self.report_fallbacks.iter_mut()) {
+            if endpoint.id.trim().is_empty() {
+                endpoint.id = uuid::Uuid::new_v4().to_string();
+            }
+        }
+    }
+
+    /// 主服务和所有备用服务的密钥位置
+    fn secret_slots(&mut self, scope: &str) -> Vec<SecretSlot<'_>> {
+        self.ensure_endpoint_ids();
+        let mut slots = vec![SecretSlot {
+            name: secret_name(scope, "primary", &self.api_type, &self.api_url),
+            legacy_name: legacy_secret_name(scope, &self.api_type, &self.api_url),
+            key: &mut self.api_key,
+        }];
+        for endpoint in self.detection_fallbacks.iter_mut().chain(self.report_fallbacks.iter_mut()) {
+            slots.push(SecretSlot {
+                name: secret_name(scope, &format!("fallback:{}", endpoint.id), &endpoint.api_type, &endpoint.api_url),
+                legacy_name: legacy_secret_name(scope, &endpoint.api_type, &endpoint.api_url),
+                key: &mut endpoint.api_key,
+            });
+        }
+        slots
+    }
+
+    /// 配置中是否带有明文密钥
+    pub fn has_plaintext_secrets(&self) -> bool {
+        !self.api_key.is_empty()
+            || self.detection_fallbacks.iter().chain(self.report_fallbacks.iter()).any(|e| !e.api_key.is_empty())
+    }
+
+    /// 密钥存储中使用的名称
+    fn secret_names(&self, scope: &str) -> Vec<String> {
+        let mut config = self.clone();
+        let names = config.secret_slots(scope).into_iter().map(|slot| slot.name).collect();
+        names
+    }
+
+    /// 把API密钥移入密钥存储，返回可以写入磁盘的配置，以及密钥是否都已持久保存
+    ///
+    /// 每个服务的密钥单独保存，密钥为空时删除已保存的密钥；`previous` 是上次保存的配置，
+    /// 其中已删除或换了地址的服务的密钥一并删除。系统密钥服务不可用且密钥库未解锁时，
+    /// 密钥只保存在本次运行中，第二个返回值为 false。需要在阻塞线程中调用。
+    pub fn without_secrets(&self, scope: &str, previous: Option<&AIConfig>) -> (AIConfig, bool) {
+        let mut config = self.clone();
+        let mut names: Vec<String> = Vec::new();
+        let mut legacy_names: Vec<String> = Vec::new();
+        let mut session_only = false;
+        for slot in config.secret_slots(scope) {
+            names.push(slot.name.clone());
+            let key = std::mem::take(slot.key);
+            if key.is_empty() {
+                secret_service::delete(&slot.name);
+            } else if secret_service::store(&slot.name, &key) == SecretBackend::Session {
+                session_only = true;
+            }
+            if !legacy_names.contains(&slot.legacy_name) {
+                legacy_names.push(slot.legacy_name);
+            }
+        }
+        for name in previous.map(|previous| previous.secret_names(scope)).unwrap_or_default() {
+            if !names.contains(&name) {
+                secret_service::delete(&name);
+            }
+        }
+        if session_only {
+            println!("⚠️ 系统密钥服务不可用且密钥库未解锁，API密钥仅在本次运行中有效");
+        } else {
+            // 密钥已按新名称保存，旧版本共用的密钥不再需要
+            for legacy_name in legacy_names {
+                secret_service::delete(&legacy_name);
+            }
+        }
+        (config, !session_only)
+    }
+
+    /// 从密钥存储中补全为空的API密钥，兼容旧版本按服务地址保存的密钥。需要在阻塞线程中调用。
+    pub fn with_secrets(mut self, scope: &str) -> AIConfig {
+        for slot in self.secret_slots(scope) {
+            if slot.key.is_empty() {
+                if let Some(value) = secret_service::load(&slot.name).or_else(|| secret_service::load(&slot.legacy_name)) {
+                    *slot.key = value;
+                }
+            }
+        }
+        self
+    }
+
+    /// 去掉所有API密钥（用于导出备份）
+    pub fn redacted(mut self) -> AIConfig {
+        for slot in self.secret_slots("") {
+            slot.key.clear();
+        }
+        self
+    }
+
+    /// 模型是否被标记为支持图像输入
+    pub fn model_supports_vision(&self, model: &str) -> bool {
+        self.vision_models.iter().any(|m| m.eq_ignore_ascii_case(model))
+    }
+
+    fn model(&self, model_type: &str) -> Option<&str> {
+        match model_type {
+            "detection" => Some(&self.detection_model),
+            "report" => Some(&self.report_model),
+            _ => None,
+        }
+    }
+
+    /// 本地服务（Ollama 或本机地址）不计费
+    pub fn is_local(&self) -> bool {
+        is_local_endpoint(&self.api_type, &self.api_url)
+    }
+
+    /// 只使用本地服务的配置：主服务是本地服务时只保留本地备用服务，否则改用第一个本地备用服务
+    pub fn local_only(&self, model_type: &str) -> Option<AIConfig> {
+        if self.is_local() {
+            let mut config = self.clone();
+            config.detection_fallbacks.retain(|e| is_local_endpoint(&e.api_type, &e.api_url));
+            config.report_fallbacks.retain(|e| is_local_endpoint(&e.api_type, &e.api_url));
+            return Some(config);
+        }
+        self.fallbacks(model_type)
+            .iter()
+            .find(|e| is_local_endpoint(&e.api_type, &e.api_url))
+            .map(|endpoint| self.for_fallback(endpoint))
+    }
+
+    fn generation(&self, model_type: &str) -> GenerationSettings {
+        match model_type {
+            "report" => self.report_generation,
+            _ => self.detection_generation,
+        }
+    }
+
+    fn fallbacks(&self, model_type: &str) -> &[FallbackEndpoint] {
+        match model_type {
+            "detection" => &self.detection_fallbacks,
+            "report" => &self.report_fallbacks,
+            _ => &[],
+        }
+    }
+
+    /// 备用服务使用的配置：替换端点和模型，其余设置沿用主配置
+    fn for_fallback(&self, endpoint: &FallbackEndpoint) -> AIConfig {
+        AIConfig {
+            api_type: endpoint.api_type.clone(),
+            api_url: endpoint.api_url.clone(),
+            api_key: endpoint.api_key.clone(),
+            detection_model: endpoint.model.clone(),
+            report_model: endpoint.model.clone(),
+            api_version: endpoint.api_version.clone(),
+            detection_fallbacks: vec![],
+            report_fallbacks: vec![],
+            ..self.clone()
+        }
+    }
 }
 
 impl Default for AIConfig {
@@ -19,16 +375,36 @@ impl Default for AIConfig {
             api_key: "".to_string(),
             detection_model: "gpt-3.5-turbo".to_string(),
             report_model: "gpt-4-turbo-preview".to_string(),
+            vision_models: vec![],
+            api_version: None,
+            request_policy: RequestPolicy::default(),
+            detection_fallbacks: vec![],
+            report_fallbacks: vec![],
+            failover: FailoverPolicy::default(),
+            detection_generation: GenerationSettings::default(),
+            report_generation: default_report_generation(),
+            prices: usage_service::default_price_table(),
+            budget: UsageBudget::default(),
         }
     }
 }
 
+/// 单次模型调用的选项
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ChatOptions {
+    pub json_mode: bool, // 要求模型只输出JSON对象（OpenAI response_format、Ollama format，Claude 通过预填 "{" 实现）
+    pub max_tokens: Option<u32>,  // 为空时使用配置或提供者的默认值
+    pub temperature: Option<f32>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct APITestResult {
     pub success: bool,
     pub message: String,
     pub response_time_ms: u64,
     pub model_used: Option<String>,
+    #[serde(default)]
+    pub answered_by: Option<AnsweredBy>, // 主服务失败时，实际连接成功的备用服务
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -37,6 +413,30 @@ pub struct ModelInfo {
     pub object: String,
     pub created: Option<u64>,
     pub owned_by: Option<String>,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub context_length: Option<u64>,   // 上下文窗口（token）
+    #[serde(default)]
+    pub supports_vision: Option<bool>, // 是否支持图像输入，未知时为空
+    #[serde(default)]
+    pub parameter_size: Option<String>, // 参数规模，如 "8.0B"
+}
+
+impl ModelInfo {
+    /// 只有ID的模型信息，其余元数据留空
+    pub fn new(id: impl Into<String>, object: &str) -> Self {
+        Self {
+            id: id.into(),
+            object: object.to_string(),
+            created: None,
+            owned_by: None,
+            display_name: None,
+            context_length: None,
+            supports_vision: None,
+            parameter_size: None,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -45,272 +445,107 @@ pub struct ModelsResponse {
     pub data: Vec<ModelInfo>,
 }
 
+#[derive(Clone)]
 pub struct AIService {
     config: AIConfig,
-    client: reqwest::Client,
+    http: HttpClient,
 }
 
 impl AIService {
     pub fn new(config: AIConfig) -> Self {
-        Self {
-            config,
-            client: reqwest::Client::new(),
-        }
+        // 熔断状态按 API类型+地址 记录，切换配置后互不影响
+        let breaker_key = format!("{}|{}", config.api_type, config.api_url);
+        let http = HttpClient::new(&config.request_policy, breaker_key);
+        Self { config, http }
     }
 
-    /// 测试API连接
-    pub async fn test_api_connection(&self) -> Result<APITestResult> {
-        let start_time = std::time::Instant::now();
-        
-        if self.config.api_key.is_empty() {
-            return Ok(APITestResult {
-                success: false,
-                message: "API Key不能为空".to_string(),
-                response_time_ms: 0,
-                model_used: None,
-            });
-        }
+    /// 根据配置创建AI服务提供者
+    fn provider(&self) -> Result<Box<dyn AiProvider>, String> {
+        ai_providers::create_provider(&self.config, self.http.clone())
+    }
 
-        println!("🧪 开始测试API连接 - 类型: {}", self.config.api_type);
-        println!("📡 测试URL: {}", self.config.api_url);
-        
-        // 根据API类型选择不同的测试方式
-        match self.config.api_type.as_str() {
-            "OpenAI Compatible" => self.test_openai_connection(start_time).await,
-            "Ollama (本地)" => self.test_ollama_connection(start_time).await,
-            "Claude API" => self.test_claude_connection(start_time).await,
-            _ => Ok(APITestResult {
-                success: false,
-                message: format!("不支持的API类型: {}", self.config.api_type),
-                response_time_ms: start_time.elapsed().as_millis() as u64,
-                model_used: None,
-            }),
-        }
+    /// 当前API端点的熔断状态
+    pub fn circuit_status(&self) -> CircuitStatus {
+        self.http.circuit_status()
     }
 
-    /// 测试OpenAI兼容API连接
-    async fn test_openai_connection(&self, start_time: std::time::Instant) -> Result<APITestResult> {
-        println!("🔌 测试OpenAI兼容API连接...");
-        
-        let test_url = format!("{}/models", self.config.api_url);
-        
-        let response = self.client
-            .get(&test_url)
-            .header("Authorization", format!("Bearer {}", self.config.api_key))
-            .header("Content-Type", "application/json")
-            .send()
-            .await;
-
-        let elapsed = start_time.elapsed().as_millis() as u64;
-
-        match response {
-            Ok(resp) => {
-                println!("📨 OpenAI API测试响应状态: {}", resp.status());
-                if resp.status().is_success() {
-                    match resp.json::<ModelsResponse>().await {
-                        Ok(models) => {
-                            println!("✅ OpenAI API连接成功，找到 {} 个模型", models.data.len());
-                            Ok(APITestResult {
-                                success: true,
-                                message: format!("连接成功！找到 {} 个可用模型", models.data.len()),
-                                response_time_ms: elapsed,
-                                model_used: None,
-                            })
-                        },
-                        Err(_) => {
-                            println!("⚠️ OpenAI API连接成功，但无法解析模型列表");
-                            Ok(APITestResult {
-                                success: true,
-                                message: "连接成功，但无法解析模型列表".to_string(),
-                                response_time_ms: elapsed,
-                                model_used: None,
-                            })
-                        }
-                    }
-                } else {
-                    let status = resp.status();
-                    let error_text = resp.text().await.unwrap_or_default();
-                    println!("❌ OpenAI API测试失败: {} - {}", status, error_text);
-                    Ok(APITestResult {
-                        success: false,
-                        message: format!("API返回错误: {} - {}", status, error_text),
-                        response_time_ms: elapsed,
-                        model_used: None,
-                    })
-                }
-            }
-            Err(e) => {
-                println!("❌ OpenAI API网络连接失败: {}", e);
-                Ok(APITestResult {
-                    success: false,
-                    message: format!("连接失败: {}", e),
-                    response_time_ms: elapsed,
-                    model_used: None,
-                })
-            }
-        }
+    /// 主服务和备用服务，按尝试顺序排列
+    fn failover_chain(&self, model_type: &str) -> Vec<AIService> {
+        std::iter::once(self.clone())
+            .chain(self.config.fallbacks(model_type).iter().map(|endpoint| AIService::new(self.config.for_fallback(endpoint))))
+            .collect()
     }
 
-    /// 测试Ollama本地API连接
-    async fn test_ollama_connection(&self, start_time: std::time::Instant) -> Result<APITestResult> {
-        println!("🔌 测试Ollama本地API连接...");
-        
-        // Ollama的API端点通常不需要认证，直接测试模型列表
-        let test_url = format!("{}/api/tags", self.config.api_url.replace("/v1", ""));
-        
-        let response = self.client
-            .get(&test_url)
-            .header("Content-Type", "application/json")
-            .send()
-            .await;
-
-        let elapsed = start_time.elapsed().as_millis() as u64;
-
-        match response {
-            Ok(resp) => {
-                println!("📨 Ollama API测试响应状态: {}", resp.status());
-                if resp.status().is_success() {
-                    match resp.json::<serde_json::Value>().await {
-                        Ok(json) => {
-                            let model_count = json.get("models")
-                                .and_then(|m| m.as_array())
-                                .map(|arr| arr.len())
-                                .unwrap_or(0);
-                            
-                            println!("✅ Ollama API连接成功，找到 {} 个模型", model_count);
-                            Ok(APITestResult {
-                                success: true,
-                                message: format!("Ollama连接成功！找到 {} 个本地模型", model_count),
-                                response_time_ms: elapsed,
-                                model_used: None,
-                            })
-                        },
-                        Err(_) => {
-                            println!("⚠️ Ollama API连接成功，但响应格式异常");
-                            Ok(APITestResult {
-                                success: true,
-                                message: "Ollama连接成功，但无法解析模型列表".to_string(),
-                                response_time_ms: elapsed,
-                                model_used: None,
-                            })
-                        }
-                    }
-                } else {
-                    let status = resp.status();
-                    let error_text = resp.text().await.unwrap_or_default();
-                    println!("❌ Ollama API测试失败: {} - {}", status, error_text);
-                    Ok(APITestResult {
-                        success: false,
-                        message: format!("Ollama API错误: {} - 请确认Ollama服务已启动", status),
-                        response_time_ms: elapsed,
-                        model_used: None,
-                    })
-                }
+    /// 主服务和所有备用服务都在熔断中时返回主服务的熔断状态
+    pub fn unavailable_status(&self, model_type: &str) -> Option<CircuitStatus> {
+        let chain = self.failover_chain(model_type);
+        let all_open = chain.iter().all(|service| service.circuit_status().state == CircuitState::Open);
+        all_open.then(|| self.circuit_status())
+    }
+
+    /// 测试API连接，主服务失败时依次测试备用服务
+    pub async fn test_api_connection(&self) -> Result<APITestResult> {
+        let primary = self.test_endpoint_connection().await?;
+        if primary.success {
+            return Ok(primary);
+        }
+
+        let mut tested = vec![(self.config.api_type.clone(), self.config.api_url.clone())];
+        let endpoints = self.config.detection_fallbacks.iter().chain(&self.config.report_fallbacks);
+        for endpoint in endpoints {
+            let key = (endpoint.api_type.clone(), endpoint.api_url.clone());
+            if tested.contains(&key) {
+                continue;
             }
-            Err(e) => {
-                println!("❌ Ollama API网络连接失败: {}", e);
-                Ok(APITestResult {
-                    success: false,
-                    message: format!("Ollama连接失败: {} - 请检查Ollama是否运行在 {}", e, self.config.api_url),
-                    response_time_ms: elapsed,
-                    model_used: None,
-                })
+            tested.push(key);
+
+            println!("↪️ 主服务连接失败，测试备用服务: {} ({})", endpoint.api_type, endpoint.api_url);
+            let mut result = AIService::new(self.config.for_fallback(endpoint)).test_endpoint_connection().await?;
+            if result.success {
+                result.message = format!("主服务连接失败（{}），备用服务 {} 可用：{}", primary.message, endpoint.api_type, result.message);
+                result.answered_by = Some(AnsweredBy {
+                    api_type: endpoint.api_type.clone(),
+                    model: result.model_used.clone().unwrap_or_else(|| endpoint.model.clone()),
+                    fallback: true,
+                });
+                return Ok(result);
             }
         }
+
+        Ok(primary)
     }
 
-    /// 测试Claude API连接
-    async fn test_claude_connection(&self, start_time: std::time::Instant) -> Result<APITestResult> {
-        println!("🔌 测试Claude API连接...");
+    /// 测试当前配置的API端点
+    async fn test_endpoint_connection(&self) -> Result<APITestResult> {
+        let start_time = std::time::Instant::now();
+
+        let provider = match self.provider() {
+            Ok(provider) => provider,
+            Err(e) => return Ok(ai_providers::test_result(false, e, start_time, None)),
+        };
         
-        // Claude API没有直接的模型列表端点，我们发送一个简单的测试请求
-        let test_body = serde_json::json!({
-            "model": "claude-3-haiku-20240307",
-            "max_tokens": 10,
-            "messages": [
-                {
-                    "role": "user",
-                    "content": "Hi"
-                }
-            ]
-        });
-
-        let response = self.client
-            .post(&format!("{}/messages", self.config.api_url))
-            .header("x-api-key", &self.config.api_key)
-            .header("Content-Type", "application/json")
-            .header("anthropic-version", "2023-06-01")
-            .json(&test_body)
-            .send()
-            .await;
-
-        let elapsed = start_time.elapsed().as_millis() as u64;
-
-        match response {
-            Ok(resp) => {
-                println!("📨 Claude API测试响应状态: {}", resp.status());
-                if resp.status().is_success() {
-                    println!("✅ Claude API连接成功");
-                    Ok(APITestResult {
-                        success: true,
-                        message: "Claude API连接成功！".to_string(),
-                        response_time_ms: elapsed,
-                        model_used: Some("claude-3-haiku-20240307".to_string()),
-                    })
-                } else {
-                    let status = resp.status();
-                    let error_text = resp.text().await.unwrap_or_default();
-                    println!("❌ Claude API测试失败: {} - {}", status, error_text);
-                    
-                    let error_msg = if status == 401 {
-                        "Claude API认证失败 - 请检查API密钥是否正确".to_string()
-                    } else if status == 403 {
-                        "Claude API访问被拒绝 - 请检查API密钥权限".to_string()
-                    } else {
-                        format!("Claude API错误: {} - {}", status, error_text)
-                    };
-                    
-                    Ok(APITestResult {
-                        success: false,
-                        message: error_msg,
-                        response_time_ms: elapsed,
-                        model_used: None,
-                    })
-                }
-            }
-            Err(e) => {
-                println!("❌ Claude API网络连接失败: {}", e);
-                Ok(APITestResult {
-                    success: false,
-                    message: format!("Claude连接失败: {}", e),
-                    response_time_ms: elapsed,
-                    model_used: None,
-                })
-            }
+        if provider.capabilities().requires_api_key && self.config.api_key.is_empty() {
+            return Ok(ai_providers::test_result(false, "API Key不能为空", start_time, None));
         }
+
+        println!("🧪 开始测试API连接 - 类型: {}", self.config.api_type);
+        println!("📡 测试URL: {}", self.config.api_url);
+        
+        Ok(provider.test_connection().await)
     }
 
     /// 获取可用模型列表
     pub async fn get_available_models(&self) -> Result<Vec<ModelInfo>> {
-        if self.config.api_key.is_empty() {
+        let provider = self.provider().map_err(|e| anyhow::anyhow!(e))?;
+        if provider.capabilities().requires_api_key && self.config.api_key.is_empty() {
             return Ok(vec![]);
         }
-
-        let models_url = format!("{}/models", self.config.api_url);
-        
-        let response = self.client
-            .get(&models_url)
-            .header("Authorization", format!("Bearer {}", self.config.api_key))
-            .header("Content-Type", "application/json")
-            .send()
-            .await?;
-
-        if response.status().is_success() {
-            let models_response: ModelsResponse = response.json().await?;
-            Ok(models_response.data)
-        } else {
-            Err(anyhow::anyhow!("获取模型列表失败: {}", response.status()))
+        if !provider.capabilities().list_models {
+            return Err(anyhow::anyhow!("{} 暂不支持获取模型列表", provider.name()));
         }
+
+        provider.list_models().await
+            .map_err(|e| anyhow::anyhow!("获取模型列表失败: {}", e))
     }
 
     /// 使用检测模型分析应用使用模式
@@ -399,278 +634,309 @@ impl AIService {
 
     /// 分析内容（用于专注状态检测或报告生成）
     pub async fn analyze_content(&self, content: &str, model_type: &str) -> Result<String, String> {
-        let model = match model_type {
-            "detection" => &self.config.detection_model,
-            "report" => &self.config.report_model,
-            _ => return Err("不支持的模型类型".to_string()),
-        };
-
-        println!("🤖 准备调用AI API - 类型: {}", self.config.api_type);
-        println!("📡 API URL: {}", self.config.api_url);
-        println!("🎯 使用模型: {}", model);
-
-        let client = reqwest::Client::new();
-        
-        // 根据API类型选择不同的调用方式
-        match self.config.api_type.as_str() {
-            "OpenAI Compatible" => self.call_openai_api(&client, content, model).await,
-            "Ollama (本地)" => self.call_ollama_api(&client, content, model).await,
-            "Claude API" => self.call_claude_api(&client, content, model).await,
-            _ => Err(format!("不支持的API类型: {}", self.config.api_type)),
-        }
+        self.analyze_content_with_image(content, None, model_type).await
     }
 
-    /// 调用OpenAI兼容API
-    async fn call_openai_api(&self, client: &reqwest::Client, content: &str, model: &str) -> Result<String, String> {
-        println!("📞 调用OpenAI兼容API...");
-        
-        let request_body = serde_json::json!({
-            "model": model,
-            "messages": [
-                {
-                    "role": "user",
-                    "content": content
-                }
-            ],
-            "max_tokens": 500,
-            "temperature": 0.3
-        });
-
-        let response = client
-            .post(&format!("{}/chat/completions", self.config.api_url))
-            .header("Authorization", format!("Bearer {}", self.config.api_key))
-            .header("Content-Type", "application/json")
-            .json(&request_body)
-            .send()
-            .await;
-
-        self.parse_openai_response(response).await
+    /// 分析内容，可附带一张JPEG截图（视觉模式）
+    ///
+    /// 模型未标记为支持图像输入时返回错误，调用方应改用纯文本提示。
+    pub async fn analyze_content_with_image(
+        &self,
+        content: &str,
+        image_jpeg: Option<&[u8]>,
+        model_type: &str,
+    ) -> Result<String, String> {
+        self.analyze_content_with_options(content, image_jpeg, model_type, &ChatOptions::default()).await
     }
 
-    /// 调用Ollama本地API
-    async fn call_ollama_api(&self, client: &reqwest::Client, content: &str, model: &str) -> Result<String, String> {
-        println!("📞 调用Ollama本地API...");
-        
-        let request_body = serde_json::json!({
-            "model": model,
-            "prompt": content,
-            "stream": false
-        });
+    /// 按指定选项分析内容
+    pub async fn analyze_content_with_options(
+        &self,
+        content: &str,
+        image_jpeg: Option<&[u8]>,
+        model_type: &str,
+        options: &ChatOptions,
+    ) -> Result<String, String> {
+        self.chat(content, image_jpeg, model_type, options).await.map(|reply| reply.text)
+    }
 
-        let response = client
-            .post(&format!("{}/api/generate", self.config.api_url.replace("/v1", "")))
-            .header("Content-Type", "application/json")
-            .json(&request_body)
-            .send()
-            .await;
+    /// 调用模型并返回实际回复的服务
+    ///
+    /// 主服务失败且错误类型符合 `FailoverPolicy` 时，按顺序尝试备用服务。
+    pub async fn chat(
+        &self,
+        content: &str,
+        image_jpeg: Option<&[u8]>,
+        model_type: &str,
+        options: &ChatOptions,
+    ) -> Result<AIReply, String> {
+        self.chat_with_failover(content, image_jpeg, model_type, options, None).await
+    }
 
-        self.parse_ollama_response(response).await
+    /// 流式调用模型，每收到一段文本调用一次 `on_chunk`
+    ///
+    /// 已经输出部分文本后出错时不再切换备用服务，以免前端收到两段拼接的回复。
+    pub async fn chat_stream(
+        &self,
+        content: &str,
+        model_type: &str,
+        options: &ChatOptions,
+        on_chunk: &mut ChunkHandler<'_>,
+    ) -> Result<AIReply, String> {
+        self.chat_with_failover(content, None, model_type, options, Some(on_chunk)).await
     }
 
-    /// 调用Claude API
-    async fn call_claude_api(&self, client: &reqwest::Client, content: &str, model: &str) -> Result<String, String> {
-        println!("📞 调用Claude API...");
-        
-        let request_body = serde_json::json!({
-            "model": model,
-            "max_tokens": 500,
-            "messages": [
-                {
-                    "role": "user",
-                    "content": content
+    async fn chat_with_failover(
+        &self,
+        content: &str,
+        image_jpeg: Option<&[u8]>,
+        model_type: &str,
+        options: &ChatOptions,
+        mut on_chunk: Option<&mut ChunkHandler<'_>>,
+    ) -> Result<AIReply, String> {
+        let chain = self.failover_chain(model_type);
+        for (index, service) in chain.iter().enumerate() {
+            let mut streamed = false;
+            let call_start = std::time::Instant::now();
+            let result = match on_chunk.as_deref_mut() {
+                Some(on_chunk) => {
+                    let mut forward = |chunk: &str| {
+                        streamed = true;
+                        on_chunk(chunk);
+                    };
+                    service.chat_once(content, image_jpeg, model_type, options, Some(&mut forward)).await
                 }
-            ]
-        });
-
-        let response = client
-            .post(&format!("{}/messages", self.config.api_url))
-            .header("x-api-key", &self.config.api_key)
-            .header("Content-Type", "application/json")
-            .header("anthropic-version", "2023-06-01")
-            .json(&request_body)
-            .send()
-            .await;
-
-        self.parse_claude_response(response).await
-    }
-
-    /// 解析OpenAI格式响应
-    async fn parse_openai_response(&self, response: Result<reqwest::Response, reqwest::Error>) -> Result<String, String> {
-        match response {
-            Ok(resp) => {
-                println!("📨 OpenAI API响应状态: {}", resp.status());
-                if resp.status().is_success() {
-                    match resp.json::<serde_json::Value>().await {
-                        Ok(json) => {
-                            if let Some(choices) = json.get("choices").and_then(|c| c.as_array()) {
-                                if let Some(first_choice) = choices.first() {
-                                    if let Some(message) = first_choice.get("message") {
-                                        if let Some(content) = message.get("content").and_then(|c| c.as_str()) {
-                                            println!("✅ OpenAI API调用成功，响应长度: {} 字符", content.len());
-                                            return Ok(content.to_string());
-                                        }
-                                    }
-                                }
-                            }
-                            println!("❌ OpenAI响应格式解析失败: {:?}", json);
-                            Err("AI响应格式解析失败".to_string())
-                        }
-                        Err(e) => {
-                            println!("❌ OpenAI JSON解析失败: {}", e);
-                            Err(format!("JSON解析失败: {}", e))
-                        }
+                None => service.chat_once(content, image_jpeg, model_type, options, None).await,
+            };
+            let error = match result {
+                Ok(response) => {
+                    service.record_usage(model_type, content, &response, call_start.elapsed(), index > 0).await;
+                    let text = response.text;
+                    let answered_by = AnsweredBy {
+                        api_type: service.config.api_type.clone(),
+                        model: service.config.model(model_type).unwrap_or_default().to_string(),
+                        fallback: index > 0,
+                    };
+                    if answered_by.fallback {
+                        println!("✅ 由备用服务回复: {} / {}", answered_by.api_type, answered_by.model);
                     }
-                } else {
-                    let status = resp.status();
-                    let error_text = resp.text().await.unwrap_or_default();
-                    println!("❌ OpenAI API请求失败: {} - {}", status, error_text);
-                    Err(format!("API请求失败: {} - {}", status, error_text))
+                    return Ok(AIReply { text, answered_by });
                 }
+                Err(error) => error,
+            };
+
+            let Some(next) = chain.get(index + 1) else {
+                return Err(error.message);
+            };
+            if streamed {
+                println!("⚠️ 流式输出中途失败，不切换备用服务");
+                return Err(error.message);
             }
-            Err(e) => {
-                println!("❌ OpenAI网络请求失败: {}", e); 
-                Err(format!("网络请求失败: {}", e))
+            if !self.config.failover.should_fail_over(error.kind) {
+                println!("⚠️ 错误类型 {:?} 不切换备用服务", error.kind);
+                return Err(error.message);
             }
+            println!(
+                "↪️ {} 调用失败（{:?}），切换到备用服务: {} / {}",
+                service.config.api_type,
+                error.kind,
+                next.config.api_type,
+                next.config.model(model_type).unwrap_or_default()
+            );
         }
+        Err("没有可用的AI服务".to_string())
     }
 
-    /// 解析Ollama格式响应  
-    async fn parse_ollama_response(&self, response: Result<reqwest::Response, reqwest::Error>) -> Result<String, String> {
-        match response {
-            Ok(resp) => {
-                println!("📨 Ollama API响应状态: {}", resp.status());
-                if resp.status().is_success() {
-                    match resp.json::<serde_json::Value>().await {
-                        Ok(json) => {
-                            if let Some(response_text) = json.get("response").and_then(|r| r.as_str()) {
-                                println!("✅ Ollama API调用成功，响应长度: {} 字符", response_text.len());
-                                return Ok(response_text.to_string());
-                            }
-                            println!("❌ Ollama响应格式解析失败: {:?}", json);
-                            Err("Ollama响应格式解析失败".to_string())
-                        }
-                        Err(e) => {
-                            println!("❌ Ollama JSON解析失败: {}", e);
-                            Err(format!("Ollama JSON解析失败: {}", e))
-                        }
-                    }
-                } else {
-                    let status = resp.status();
-                    let error_text = resp.text().await.unwrap_or_default();
-                    println!("❌ Ollama API请求失败: {} - {}", status, error_text);
-                    Err(format!("Ollama API请求失败: {} - {}", status, error_text))
-                }
-            }
-            Err(e) => {
-                println!("❌ Ollama网络请求失败: {}", e);
-                Err(format!("Ollama网络请求失败: {}", e))
-            }
+    /// 记录一次调用的token用量和费用，服务未返回用量时按文本长度估算
+    async fn record_usage(&self, model_type: &str, prompt: &str, response: &ChatResponse, latency: std::time::Duration, fallback: bool) {
+        let model = self.config.model(model_type).unwrap_or_default();
+        let (usage, estimated) = match response.usage {
+            Some(usage) => (usage, false),
+            None => (
+                TokenUsage {
+                    prompt_tokens: prompt_budget::estimate_tokens(prompt) as u64,
+                    completion_tokens: prompt_budget::estimate_tokens(&response.text) as u64,
+                },
+                true,
+            ),
+        };
+        let (cost, priced) = usage_service::calculate_cost(
+            &self.config.prices,
+            self.config.is_local(),
+            model,
+            usage.prompt_tokens,
+            usage.completion_tokens,
+        );
+        println!(
+            "💰 token用量: 输入 {} / 输出 {}{}，费用 ${:.6}{}",
+            usage.prompt_tokens,
+            usage.completion_tokens,
+            if estimated { "（估算）" } else { "" },
+            cost,
+            if priced { "" } else { "（价格表中没有该模型）" }
+        );
+
+        let record = UsageRecord {
+            timestamp: chrono::Utc::now(),
+            api_type: self.config.api_type.clone(),
+            model: model.to_string(),
+            model_type: model_type.to_string(),
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            estimated,
+            latency_ms: latency.as_millis() as u64,
+            cost,
+            priced,
+            fallback,
+        };
+        if let Err(e) = UsageService::default().record(record).await {
+            println!("⚠️ 保存AI用量记录失败: {}", e);
         }
     }
 
-    /// 解析Claude格式响应
-    async fn parse_claude_response(&self, response: Result<reqwest::Response, reqwest::Error>) -> Result<String, String> {
-        match response {
-            Ok(resp) => {
-                println!("📨 Claude API响应状态: {}", resp.status());
-                if resp.status().is_success() {
-                    match resp.json::<serde_json::Value>().await {
-                        Ok(json) => {
-                            if let Some(content_array) = json.get("content").and_then(|c| c.as_array()) {
-                                if let Some(first_content) = content_array.first() {
-                                    if let Some(text) = first_content.get("text").and_then(|t| t.as_str()) {
-                                        println!("✅ Claude API调用成功，响应长度: {} 字符", text.len());
-                                        return Ok(text.to_string());
-                                    }
-                                }
-                            }
-                            println!("❌ Claude响应格式解析失败: {:?}", json);
-                            Err("Claude响应格式解析失败".to_string())
-                        }
-                        Err(e) => {
-                            println!("❌ Claude JSON解析失败: {}", e);
-                            Err(format!("Claude JSON解析失败: {}", e))
-                        }
-                    }
-                } else {
-                    let status = resp.status();
-                    let error_text = resp.text().await.unwrap_or_default();
-                    println!("❌ Claude API请求失败: {} - {}", status, error_text);
-                    Err(format!("Claude API请求失败: {} - {}", status, error_text))
+    /// 超出预算时返回说明
+    pub fn budget_exceeded(&self) -> Option<String> {
+        UsageService::default().budget_exceeded(&self.config)
+    }
+
+    /// 按预算选择服务：未超出预算时使用当前配置，超出时改用本地端点，
+    /// 没有本地端点时返回超出预算的说明
+    pub fn within_budget(&self, model_type: &str) -> Result<AIService, String> {
+        match self.budget_exceeded() {
+            None => Ok(self.clone()),
+            Some(reason) => match self.config.local_only(model_type) {
+                Some(local) => {
+                    println!("💰 {}，改用本地模型: {}", reason, local.model(model_type).unwrap_or_default());
+                    Ok(AIService::new(local))
                 }
-            }
-            Err(e) => {
-                println!("❌ Claude网络请求失败: {}", e);
-                Err(format!("Claude网络请求失败: {}", e))
-            }
+                None => Err(reason),
+            },
         }
     }
 
-    /// 生成专注状态分析的提示词
-    fn build_monitoring_prompt(
+    /// 只调用当前端点，不做故障切换；传入 `on_chunk` 时使用流式输出
+    async fn chat_once(
         &self,
-        app_name: &Option<String>,
-        window_title: &Option<String>,
-        ocr_text: &Option<String>,
-        whitelist: &[String],
-        blacklist: &[String],
-        _activities: &[ApplicationActivity]
-    ) -> String {
-        let mut prompt = String::new();
-        
-        // 添加基础指令
-        prompt.push_str("你是一个专注状态分析助手。基于以下信息，判断用户当前是专注(FOCUSED)还是分心(DISTRACTED)状态。\n\n");
-        
-        // 添加应用信息
-        prompt.push_str("**当前应用信息：**\n");
-        if let Some(app) = app_name {
-            prompt.push_str(&format!("- 应用程序：{}\n", app));
-        } else {
-            prompt.push_str("- 应用程序：未检测到\n");
-        }
-        
-        if let Some(title) = window_title {
-            prompt.push_str(&format!("- 窗口标题：{}\n", title));
-        } else {
-            prompt.push_str("- 窗口标题：未检测到\n");
-        }
-        
-        // 添加屏幕内容
-        prompt.push_str("\n**屏幕内容：**\n");
-        if let Some(text) = ocr_text {
-            if text.len() > 1000 {
-                prompt.push_str(&format!("{}...", &text[..1000]));
-            } else {
-                prompt.push_str(text);
+        content: &str,
+        image_jpeg: Option<&[u8]>,
+        model_type: &str,
+        options: &ChatOptions,
+        on_chunk: Option<&mut ChunkHandler<'_>>,
+    ) -> Result<ChatResponse, ProviderError> {
+        use base64::Engine;
+
+        let model = self.config.model(model_type)
+            .ok_or_else(|| ProviderError::unsupported("不支持的模型类型"))?;
+
+        println!("🤖 准备调用AI API - 类型: {}", self.config.api_type);
+        println!("📡 API URL: {}", self.config.api_url);
+        println!("🎯 使用模型: {}", model);
+
+        let provider = self.provider().map_err(ProviderError::unsupported)?;
+
+        let image_base64 = match image_jpeg {
+            Some(image) => {
+                if !provider.capabilities().vision {
+                    return Err(ProviderError::unsupported(format!("{} 不支持图像输入", provider.name())));
+                }
+                if !self.config.model_supports_vision(model) {
+                    return Err(ProviderError::unsupported(format!("模型 {} 未标记为支持图像输入", model)));
+                }
+                println!("🖼️ 附带截图: {} KB", image.len() / 1024);
+                Some(base64::engine::general_purpose::STANDARD.encode(image))
             }
-        } else {
-            prompt.push_str("无可识别文本内容");
+            None => None,
+        };
+        let image_base64 = image_base64.as_deref();
+
+        // 调用时未指定的生成参数使用配置中的设置
+        let generation = self.config.generation(model_type);
+        let options = ChatOptions {
+            max_tokens: options.max_tokens.or(generation.max_tokens),
+            temperature: options.temperature.or(generation.temperature),
+            ..*options
+        };
+        let request = ChatRequest {
+            model,
+            content,
+            image_base64,
+            options: &options,
+        };
+        match on_chunk {
+            Some(on_chunk) => provider.chat_stream(&request, on_chunk).await,
+            None => provider.chat(&request).await,
         }
-        
-        // 添加规则配置
-        prompt.push_str("\n\n**判断规则：**\n");
-        
-        if !whitelist.is_empty() {
-            prompt.push_str("专注应用白名单（以下应用视为专注状态）：\n");
-            for app in whitelist {
-                prompt.push_str(&format!("- {}\n", app));
-            }
+    }
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fallback(id: &str, api_url: &str, api_key: &str) -> FallbackEndpoint {
+        FallbackEndpoint {
+            id: id.to_string(),
+            api_type: "OpenAI Compatible".to_string(),
+            api_url: api_url.to_string(),
+            api_key: api_key.to_string(),
+            model: "gpt-4o-mini".to_string(),
+            api_version: None,
         }
-        
-        if !blacklist.is_empty() {
-            prompt.push_str("分心应用黑名单（以下应用视为分心状态）：\n");
-            for app in blacklist {
-                prompt.push_str(&format!("- {}\n", app));
-            }
+    }
+
+    #[test]
+    fn secret_slots_are_distinct_for_shared_urls() {
+        let mut config = AIConfig {
+            api_key: "sk-primary".to_string(),
+            detection_fallbacks: vec![fallback("a1", "https://api.openai.com/v1/", "sk-second")],
+            report_fallbacks: vec![fallback("b2", "https://api.openai.com/v1", "sk-third")],
+            ..AIConfig::default()
+        };
+
+        let slots = config.secret_slots("ai_config");
+        let names: Vec<&str> = slots.iter().map(|slot| slot.name.as_str()).collect();
+        assert_eq!(names, vec![
+            "ai_config:primary:OpenAI Compatible|https://api.openai.com/v1",
+            "ai_config:fallback:a1:OpenAI Compatible|https://api.openai.com/v1",
+            "ai_config:fallback:b2:OpenAI Compatible|https://api.openai.com/v1",
+        ]);
+        // 旧版本的名称相同，只用于读取
+        assert!(slots.iter().all(|slot| slot.legacy_name == "ai_config:OpenAI Compatible|https://api.openai.com/v1"));
+
+        let keys: Vec<&str> = slots.iter().map(|slot| slot.key.as_str()).collect();
+        assert_eq!(keys, vec!["sk-primary", "sk-second", "sk-third"]);
+    }
+
+    #[test]
+    fn fallback_secret_names_survive_reordering() {
+        let mut config = AIConfig {
+            detection_fallbacks: vec![
+                fallback("a1", "https://api.deepseek.com/v1", "sk-a"),
+                fallback("", "https://api.openai.com/v1", "sk-b"),
+            ],
+            ..AIConfig::default()
+        };
+        config.ensure_endpoint_ids();
+        let generated = config.detection_fallbacks[1].id.clone();
+        assert!(!generated.is_empty());
+        let names = config.secret_names("ai_config");
+
+        // 调整顺序后名称和密钥仍然一一对应，已有的编号不会改变
+        config.detection_fallbacks.reverse();
+        config.ensure_endpoint_ids();
+        assert_eq!(config.detection_fallbacks[0].id, generated);
+        let mut reordered = config.secret_names("ai_config");
+        reordered[1..].reverse();
+        assert_eq!(reordered, names);
+    }
+
+    #[test]
+    fn redacted_clears_every_key() {
+        let config = AIConfig {
+            api_key: "sk-primary".to_string(),
+            detection_fallbacks: vec![fallback("a1", "https://api.openai.com/v1", "sk-second")],
+            ..AIConfig::default()
         }
-        
-        // 添加输出格式要求
-        prompt.push_str("\n**输出要求：**\n");
-        prompt.push_str("请分析以上信息，并严格按照以下格式输出：\n");
-        prompt.push_str("状态：FOCUSED 或 DISTRACTED\n");
-        prompt.push_str("置信度：0.0-1.0之间的数值\n");
-        prompt.push_str("原因：简要说明判断理由\n");
-        
-        prompt
+        .redacted();
+        assert!(!config.has_plaintext_secrets());
     }
-} 
\ No newline at end of file
+}
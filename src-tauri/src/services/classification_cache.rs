@@ -0,0 +1,256 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::services::focus_classification::ActivityCategory;
+use crate::services::monitor_service::{FocusState, MonitoringResult, OcrStatus, PrivacyExclusions, ScreenContent};
+
+const CACHE_FILE: &str = "classification_cache.json";
+
+/// 分类缓存配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClassificationCacheConfig {
+    #[serde(default = "default_cache_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_cache_ttl_minutes")]
+    pub ttl_minutes: u32, // 缓存有效期（分钟）
+    #[serde(default = "default_cache_max_entries")]
+    pub max_entries: usize, // 超出时淘汰最久未命中的记录
+    #[serde(default = "default_cache_max_ocr_distance")]
+    pub max_ocr_distance: u32, // 屏幕文字指纹允许的差异位数 (0-64)
+}
+
+fn default_cache_enabled() -> bool {
+    true
+}
+
+fn default_cache_ttl_minutes() -> u32 {
+    720
+}
+
+fn default_cache_max_entries() -> usize {
+    1000
+}
+
+fn default_cache_max_ocr_distance() -> u32 {
+    6
+}
+
+impl Default for ClassificationCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_cache_enabled(),
+            ttl_minutes: default_cache_ttl_minutes(),
+            max_entries: default_cache_max_entries(),
+            max_ocr_distance: default_cache_max_ocr_distance(),
+        }
+    }
+}
+
+/// 缓存键：应用、窗口标题、当前任务和提示词模板版本的哈希，加上屏幕文字指纹
+#[derive(Debug, Clone)]
+pub struct CacheKey {
+    key: String,
+    ocr_fingerprint: u64,
+}
+
+impl CacheKey {
+    /// OCR没有运行时返回 None：只凭标题无法区分同一窗口中的不同内容
+    pub fn new(
+        app_name: Option<&str>,
+        window_title: Option<&str>,
+        task: Option<&str>,
+        prompt_version: &str,
+        screen: &ScreenContent,
+    ) -> Option<Self> {
+        let ocr_fingerprint = match screen.ocr_status {
+            OcrStatus::Recognized => ocr_fingerprint(screen.ocr_text.as_deref().unwrap_or("")),
+            OcrStatus::NoText => 0,
+            OcrStatus::Unavailable | OcrStatus::Disabled => return None,
+        };
+
+        let app = app_name.map(PrivacyExclusions::normalize_app_name).unwrap_or_default();
+        let title = window_title.map(normalize_title).unwrap_or_default();
+        let task = task.map(|task| task.trim().to_lowercase()).unwrap_or_default();
+        let key = format!(
+            "{:016x}",
+            fnv1a(format!("{}\u{1f}{}\u{1f}{}\u{1f}{}", app, title, task, prompt_version).as_bytes())
+        );
+        Some(Self { key, ocr_fingerprint })
+    }
+}
+
+/// 窗口标题：不区分大小写，合并空白，数字统一替换（未读数、时间等不影响命中）
+fn normalize_title(title: &str) -> String {
+    title
+        .split_whitespace()
+        .map(|word| {
+            word.chars()
+                .map(|c| if c.is_ascii_digit() { '#' } else { c })
+                .collect::<String>()
+                .to_lowercase()
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 屏幕文字的SimHash指纹，内容相近的文字指纹只差几位
+///
+/// 含数字的词（时钟、计数器）不参与计算；中日韩文字没有空格分词，按相邻两字切分。
+fn ocr_fingerprint(text: &str) -> u64 {
+    let mut weights = [0i32; 64];
+    let mut add = |feature: &str| {
+        let hash = fnv1a(feature.as_bytes());
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            if hash >> bit & 1 == 1 {
+                *weight += 1;
+            } else {
+                *weight -= 1;
+            }
+        }
+    };
+
+    let lowercase = text.to_lowercase();
+    for word in lowercase.split(|c: char| !c.is_alphanumeric()) {
+        if word.is_empty() || word.chars().any(|c| c.is_numeric()) {
+            continue;
+        }
+        if word.is_ascii() {
+            if word.len() >= 2 {
+                add(word);
+            }
+        } else {
+            let chars: Vec<char> = word.chars().collect();
+            if chars.len() == 1 {
+                add(word);
+            }
+            for pair in chars.windows(2) {
+                add(&pair.iter().collect::<String>());
+            }
+        }
+    }
+
+    weights
+        .iter()
+        .enumerate()
+        .filter(|(_, weight)| **weight > 0)
+        .fold(0u64, |fingerprint, (bit, _)| fingerprint | 1 << bit)
+}
+
+/// FNV-1a 64位哈希（结果跨版本稳定，可以写入磁盘）
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// 一条缓存的分类结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedClassification {
+    pub key: String,
+    pub ocr_fingerprint: u64,
+    pub focus_state: FocusState,
+    pub confidence: f32,
+    pub reason: String,
+    #[serde(default)]
+    pub activity_category: Option<ActivityCategory>,
+    #[serde(default)]
+    pub task_relevance: Option<f32>,
+    pub created_at: DateTime<Utc>,
+    pub last_hit: DateTime<Utc>,
+    #[serde(default)]
+    pub hits: u32,
+}
+
+/// 按窗口上下文缓存的专注分类结果，保存在数据目录中
+pub struct ClassificationCache {
+    path: PathBuf,
+    entries: Vec<CachedClassification>,
+}
+
+impl ClassificationCache {
+    /// 读取缓存文件，文件不存在或无法解析时为空
+    pub fn load(data_dir: PathBuf) -> Self {
+        let path = data_dir.join(CACHE_FILE);
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self { path, entries }
+    }
+
+    /// 与其他服务一致，使用应用本地目录
+    pub fn load_default() -> Self {
+        Self::load(PathBuf::from("data"))
+    }
+
+    /// 查找同一窗口上下文中屏幕文字最接近的未过期结果，并记录命中
+    pub fn lookup(&mut self, key: &CacheKey, config: &ClassificationCacheConfig) -> Option<CachedClassification> {
+        self.remove_expired(config);
+        let entry = self
+            .entries
+            .iter_mut()
+            .filter(|entry| entry.key == key.key)
+            .map(|entry| ((entry.ocr_fingerprint ^ key.ocr_fingerprint).count_ones(), entry))
+            .filter(|(distance, _)| *distance <= config.max_ocr_distance)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, entry)| entry)?;
+        entry.hits += 1;
+        entry.last_hit = Utc::now();
+        Some(entry.clone())
+    }
+
+    /// 写入一次AI分类结果，替换同一上下文中相近的旧记录
+    pub fn insert(&mut self, key: &CacheKey, result: &MonitoringResult, config: &ClassificationCacheConfig) {
+        self.remove_expired(config);
+        self.entries.retain(|entry| {
+            entry.key != key.key
+                || (entry.ocr_fingerprint ^ key.ocr_fingerprint).count_ones() > config.max_ocr_distance
+        });
+
+        let now = Utc::now();
+        self.entries.push(CachedClassification {
+            key: key.key.clone(),
+            ocr_fingerprint: key.ocr_fingerprint,
+            focus_state: result.focus_state.clone(),
+            confidence: result.confidence,
+            reason: result.ai_analysis.clone().unwrap_or_default(),
+            activity_category: result.activity_category,
+            task_relevance: result.task_relevance,
+            created_at: now,
+            last_hit: now,
+            hits: 0,
+        });
+
+        if self.entries.len() > config.max_entries {
+            self.entries.sort_by_key(|entry| std::cmp::Reverse(entry.last_hit));
+            self.entries.truncate(config.max_entries);
+        }
+    }
+
+    fn remove_expired(&mut self, config: &ClassificationCacheConfig) {
+        let cutoff = Utc::now() - Duration::minutes(i64::from(config.ttl_minutes));
+        self.entries.retain(|entry| entry.created_at > cutoff);
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, serde_json::to_string(&self.entries)?)?;
+        Ok(())
+    }
+
+    /// 清空缓存，返回删除的记录数
+    pub fn clear(&mut self) -> Result<usize> {
+        let removed = self.entries.len();
+        self.entries.clear();
+        if self.path.exists() {
+            fs::remove_file(&self.path)?;
+        }
+        Ok(removed)
+    }
+}
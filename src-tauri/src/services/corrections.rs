@@ -0,0 +1,310 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::services::monitor_service::{FocusState, MonitoringResult, PrivacyExclusions};
+use crate::services::prompt_budget;
+use crate::services::prompt_templates::PromptLanguage;
+
+const CORRECTIONS_FILE: &str = "corrections.json";
+
+/// 最多保留的纠正记录数，超出时删除最早的记录
+const MAX_CORRECTIONS: usize = 1000;
+
+/// 同一方向的纠正达到该次数后才给出白名单/黑名单建议
+const MIN_CORRECTIONS_FOR_SUGGESTION: u32 = 3;
+
+/// 用户纠正的使用设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CorrectionsConfig {
+    #[serde(default = "default_few_shot_examples")]
+    pub few_shot_examples: usize, // 加入检测提示词的相似纠正数量，0 表示不使用
+}
+
+fn default_few_shot_examples() -> usize {
+    3
+}
+
+impl Default for CorrectionsConfig {
+    fn default() -> Self {
+        Self {
+            few_shot_examples: default_few_shot_examples(),
+        }
+    }
+}
+
+/// 用户对一条监控结果给出的标注
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Correction {
+    pub result_timestamp: DateTime<Utc>, // 被标注的监控结果
+    pub corrected_at: DateTime<Utc>,
+    pub application_name: Option<String>,
+    pub window_title: Option<String>, // 与监控结果一样，已脱敏
+    pub model_state: FocusState,
+    pub user_state: FocusState,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+impl Correction {
+    /// 用户标注与模型判断不同
+    pub fn disagrees(&self) -> bool {
+        self.user_state != self.model_state
+    }
+}
+
+/// 单个应用的模型判断与用户标注对比
+#[derive(Debug, Clone, Serialize)]
+pub struct AppDisagreement {
+    pub application_name: String,
+    pub judged: u32,                 // 模型做出专注判断的次数
+    pub labeled: u32,                // 用户标注的次数
+    pub disagreements: u32,          // 标注与判断不同的次数
+    pub disagreement_rate: f32,      // disagreements / labeled
+    pub corrected_to_focused: u32,   // 模型判为分心，用户标为专注
+    pub corrected_to_distracted: u32, // 模型判为专注，用户标为分心
+    pub suggestion: Option<String>,  // 白名单或黑名单调整建议
+}
+
+/// 用户纠正记录，保存在数据目录中
+pub struct CorrectionStore {
+    path: PathBuf,
+    corrections: Vec<Correction>,
+}
+
+impl CorrectionStore {
+    /// 读取纠正记录，文件不存在或无法解析时为空
+    pub fn load(data_dir: PathBuf) -> Self {
+        let path = data_dir.join(CORRECTIONS_FILE);
+        let corrections = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self { path, corrections }
+    }
+
+    /// 与其他服务一致，使用应用本地目录
+    pub fn load_default() -> Self {
+        Self::load(PathBuf::from("data"))
+    }
+
+    pub fn corrections(&self) -> &[Correction] {
+        &self.corrections
+    }
+
+    /// 记录用户标注，同一条结果再次标注时替换之前的标注
+    pub fn record(&mut self, result: &MonitoringResult, user_state: FocusState, note: Option<String>) -> Result<Correction> {
+        let correction = Correction {
+            result_timestamp: result.timestamp,
+            corrected_at: Utc::now(),
+            application_name: result.application_name.clone(),
+            window_title: result.window_title.clone(),
+            model_state: result.focus_state.clone(),
+            user_state,
+            note: note.filter(|note| !note.trim().is_empty()),
+        };
+        self.corrections.retain(|existing| existing.result_timestamp != result.timestamp);
+        self.corrections.push(correction.clone());
+        if self.corrections.len() > MAX_CORRECTIONS {
+            let excess = self.corrections.len() - MAX_CORRECTIONS;
+            self.corrections.drain(..excess);
+        }
+        self.save()?;
+        Ok(correction)
+    }
+
+    /// 与当前窗口最相似的纠正（用户标注与模型判断不同的记录）
+    ///
+    /// 同一应用计1分，再加上窗口标题词语的重合度；都不相似的记录不会被选中。
+    pub fn similar(&self, app_name: Option<&str>, window_title: Option<&str>, limit: usize) -> Vec<Correction> {
+        if limit == 0 {
+            return Vec::new();
+        }
+        let app = app_name.map(PrivacyExclusions::normalize_app_name);
+        let words = text_words(window_title.unwrap_or(""));
+
+        let mut scored: Vec<(f32, &Correction)> = self
+            .corrections
+            .iter()
+            .filter(|correction| correction.disagrees())
+            .map(|correction| {
+                let same_app = app.is_some()
+                    && correction.application_name.as_deref().map(PrivacyExclusions::normalize_app_name) == app;
+                let overlap = jaccard(&words, &text_words(correction.window_title.as_deref().unwrap_or("")));
+                (if same_app { 1.0 } else { 0.0 } + overlap, correction)
+            })
+            .filter(|(score, _)| *score >= 0.3)
+            .collect();
+
+        // 相似度相同时优先使用较新的纠正
+        scored.sort_by(|(a_score, a), (b_score, b)| {
+            b_score.total_cmp(a_score).then(b.corrected_at.cmp(&a.corrected_at))
+        });
+        scored.into_iter().take(limit).map(|(_, correction)| correction.clone()).collect()
+    }
+
+    /// 按应用统计用户标注与模型判断的差异，差异最多的应用在前
+    pub fn app_disagreements(&self, results: &[MonitoringResult]) -> Vec<AppDisagreement> {
+        let mut stats: HashMap<String, AppDisagreement> = HashMap::new();
+
+        for result in results {
+            if result.private || result.ai_unavailable || result.focus_state == FocusState::Unknown {
+                continue;
+            }
+            if let Some(app) = result.application_name.as_deref() {
+                stats_entry(&mut stats, app).judged += 1;
+            }
+        }
+
+        for correction in &self.corrections {
+            let Some(app) = correction.application_name.as_deref() else { continue };
+            let stat = stats_entry(&mut stats, app);
+            stat.labeled += 1;
+            if correction.disagrees() {
+                stat.disagreements += 1;
+                match (&correction.model_state, &correction.user_state) {
+                    (FocusState::Distracted | FocusState::SeverelyDistracted, FocusState::Focused) => {
+                        stat.corrected_to_focused += 1
+                    }
+                    (FocusState::Focused, FocusState::Distracted | FocusState::SeverelyDistracted) => {
+                        stat.corrected_to_distracted += 1
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut stats: Vec<AppDisagreement> = stats
+            .into_values()
+            .filter(|stat| stat.labeled > 0)
+            .map(|mut stat| {
+                stat.disagreement_rate = stat.disagreements as f32 / stat.labeled as f32;
+                stat.suggestion = if stat.corrected_to_focused >= MIN_CORRECTIONS_FOR_SUGGESTION
+                    && stat.corrected_to_focused > stat.corrected_to_distracted
+                {
+                    Some("多次被纠正为专注，可考虑加入白名单".to_string())
+                } else if stat.corrected_to_distracted >= MIN_CORRECTIONS_FOR_SUGGESTION
+                    && stat.corrected_to_distracted > stat.corrected_to_focused
+                {
+                    Some("多次被纠正为分心，可考虑加入黑名单".to_string())
+                } else {
+                    None
+                };
+                stat
+            })
+            .collect();
+        stats.sort_by(|a, b| {
+            b.disagreements
+                .cmp(&a.disagreements)
+                .then(b.disagreement_rate.total_cmp(&a.disagreement_rate))
+        });
+        stats
+    }
+
+    fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&self.corrections)?)?;
+        Ok(())
+    }
+}
+
+/// 按规范化的应用名称取统计项，显示名称使用第一次出现的写法
+fn stats_entry<'a>(stats: &'a mut HashMap<String, AppDisagreement>, app: &str) -> &'a mut AppDisagreement {
+    stats
+        .entry(PrivacyExclusions::normalize_app_name(app))
+        .or_insert_with(|| AppDisagreement {
+            application_name: app.to_string(),
+            judged: 0,
+            labeled: 0,
+            disagreements: 0,
+            disagreement_rate: 0.0,
+            corrected_to_focused: 0,
+            corrected_to_distracted: 0,
+            suggestion: None,
+        })
+}
+
+/// 文本中的词语：英文按单词，中日韩文字按相邻两字，忽略含数字的词
+pub(crate) fn text_words(text: &str) -> HashSet<String> {
+    let mut words = HashSet::new();
+    for word in text.to_lowercase().split(|c: char| !c.is_alphanumeric()) {
+        if word.is_empty() || word.chars().any(|c| c.is_numeric()) {
+            continue;
+        }
+        if word.is_ascii() {
+            if word.len() >= 2 {
+                words.insert(word.to_string());
+            }
+        } else {
+            let chars: Vec<char> = word.chars().collect();
+            if chars.len() == 1 {
+                words.insert(word.to_string());
+            }
+            for pair in chars.windows(2) {
+                words.insert(pair.iter().collect());
+            }
+        }
+    }
+    words
+}
+
+fn jaccard(a: &HashSet<String>, b: &HashSet<String>) -> f32 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f32 / union as f32
+}
+
+fn state_label(state: &FocusState, language: PromptLanguage) -> &'static str {
+    match (state, language) {
+        (FocusState::Focused, PromptLanguage::Zh) => "专注 (focused)",
+        (FocusState::Distracted, PromptLanguage::Zh) => "分心 (distracted)",
+        (FocusState::SeverelyDistracted, PromptLanguage::Zh) => "严重分心 (severely_distracted)",
+        (FocusState::Unknown, PromptLanguage::Zh) => "无法判断",
+        (FocusState::Focused, PromptLanguage::En) => "focused",
+        (FocusState::Distracted, PromptLanguage::En) => "distracted",
+        (FocusState::SeverelyDistracted, PromptLanguage::En) => "severely_distracted",
+        (FocusState::Unknown, PromptLanguage::En) => "unknown",
+    }
+}
+
+/// 把纠正记录写成提示词中的示例，总长度不超过 `max_tokens`
+pub fn format_examples(examples: &[Correction], language: PromptLanguage, max_tokens: usize) -> String {
+    if examples.is_empty() {
+        return String::new();
+    }
+    let zh = language == PromptLanguage::Zh;
+    let per_example = max_tokens / examples.len();
+    let lines: Vec<String> = examples
+        .iter()
+        .map(|example| {
+            let app = example.application_name.as_deref().unwrap_or(if zh { "未知应用" } else { "Unknown application" });
+            let title = example.window_title.as_deref().unwrap_or(if zh { "无标题" } else { "Untitled" });
+            let line = if zh {
+                format!(
+                    "- 应用: {}，窗口标题: {} → 曾被判断为{}，用户纠正为{}",
+                    app, title, state_label(&example.model_state, language), state_label(&example.user_state, language)
+                )
+            } else {
+                format!(
+                    "- App: {}, window title: {} → judged {}, corrected by the user to {}",
+                    app, title, state_label(&example.model_state, language), state_label(&example.user_state, language)
+                )
+            };
+            let line = match example.note.as_deref() {
+                Some(note) if zh => format!("{}（用户说明: {}）", line, note),
+                Some(note) => format!("{} (user note: {})", line, note),
+                None => line,
+            };
+            prompt_budget::truncate_to_tokens(&line, per_example)
+        })
+        .collect();
+    lines.join("\n")
+}
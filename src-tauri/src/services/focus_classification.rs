@@ -0,0 +1,264 @@
+use serde::{Deserialize, Serialize};
+
+use crate::services::ai_service::{AIService, AnsweredBy, ChatOptions};
+use crate::services::monitor_service::FocusState;
+use crate::services::prompt_templates::PromptLanguage;
+
+/// 当前活动的类别
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityCategory {
+    Work,          // 编程、写作、设计等工作
+    Study,         // 学习、阅读资料
+    Communication, // 聊天、邮件、会议
+    Reference,     // 查文档、搜索
+    Entertainment, // 视频、游戏、音乐
+    SocialMedia,   // 社交媒体
+    Shopping,      // 购物
+    News,          // 新闻资讯
+    #[serde(other)]
+    Other,
+}
+
+/// 模型返回的结构化专注判断
+#[derive(Debug, Clone)]
+pub struct FocusClassification {
+    pub state: FocusState,
+    pub confidence: f32,
+    pub reason: String,
+    pub activity_category: Option<ActivityCategory>,
+    pub task_relevance: Option<f32>, // 与当前任务的相关度 (0-1)，没有设定任务时为空
+    pub ai_unavailable: bool,        // AI服务调用失败（超时、重试用尽或熔断中），没有得到判断
+    pub answered_by: Option<AnsweredBy>, // 实际回复的AI服务
+}
+
+/// 模型输出的原始JSON
+#[derive(Debug, Deserialize)]
+struct RawClassification {
+    state: String,
+    confidence: f32,
+    reason: String,
+    activity_category: ActivityCategory,
+    #[serde(default)]
+    task_relevance: Option<f32>,
+}
+
+/// 提示词中要求模型遵循的输出格式
+pub const RESPONSE_FORMAT_INSTRUCTIONS: &str = r#"请只输出一个JSON对象，不要输出其他文字，格式如下：
+{
+  "state": "focused" | "distracted" | "severely_distracted",
+  "confidence": 0到1之间的数字，表示判断的把握程度,
+  "reason": "简要说明判断理由",
+  "activity_category": "work" | "study" | "communication" | "reference" | "entertainment" | "social_media" | "shopping" | "news" | "other",
+  "task_relevance": 0到1之间的数字，表示当前活动与用户任务的相关度；没有设定任务时为 null
+}
+"#;
+
+/// 英文模板使用的输出格式要求（字段和取值与中文版相同）
+pub const RESPONSE_FORMAT_INSTRUCTIONS_EN: &str = r#"Reply with a single JSON object and nothing else, in this format:
+{
+  "state": "focused" | "distracted" | "severely_distracted",
+  "confidence": a number between 0 and 1 indicating how certain the judgment is,
+  "reason": "a brief explanation of the judgment",
+  "activity_category": "work" | "study" | "communication" | "reference" | "entertainment" | "social_media" | "shopping" | "news" | "other",
+  "task_relevance": a number between 0 and 1 indicating how related the activity is to the user's task; null when no task is set
+}
+"#;
+
+impl FocusClassification {
+    /// 解析并校验模型输出
+    pub fn parse(response: &str) -> Result<Self, String> {
+        let json = extract_json_object(response).ok_or_else(|| "回复中没有JSON对象".to_string())?;
+        let raw: RawClassification = serde_json::from_str(json).map_err(|e| format!("JSON格式不符合要求: {}", e))?;
+
+        let state = match raw.state.trim().to_lowercase().as_str() {
+            "focused" | "专注" => FocusState::Focused,
+            "distracted" | "分心" => FocusState::Distracted,
+            "severely_distracted" | "严重分心" => FocusState::SeverelyDistracted,
+            other => return Err(format!("未知的状态: {}", other)),
+        };
+        if !(0.0..=1.0).contains(&raw.confidence) {
+            return Err(format!("confidence 超出 0-1 范围: {}", raw.confidence));
+        }
+        if let Some(relevance) = raw.task_relevance {
+            if !(0.0..=1.0).contains(&relevance) {
+                return Err(format!("task_relevance 超出 0-1 范围: {}", relevance));
+            }
+        }
+        let reason = raw.reason.trim();
+        if reason.is_empty() {
+            return Err("reason 为空".to_string());
+        }
+
+        Ok(Self {
+            state,
+            confidence: raw.confidence,
+            reason: reason.to_string(),
+            activity_category: Some(raw.activity_category),
+            task_relevance: raw.task_relevance,
+            ai_unavailable: false,
+            answered_by: None,
+        })
+    }
+
+    /// 无法得到有效判断时的结果
+    pub fn unknown(reason: impl Into<String>) -> Self {
+        Self {
+            state: FocusState::Unknown,
+            confidence: 0.0,
+            reason: reason.into(),
+            activity_category: None,
+            task_relevance: None,
+            ai_unavailable: false,
+            answered_by: None,
+        }
+    }
+
+    fn answered(self, answered_by: AnsweredBy) -> Self {
+        Self {
+            answered_by: Some(answered_by),
+            ..self
+        }
+    }
+
+    /// AI服务不可用时的结果，和“判断不出来”区分开
+    pub fn unavailable(reason: impl Into<String>) -> Self {
+        Self {
+            ai_unavailable: true,
+            ..Self::unknown(reason)
+        }
+    }
+}
+
+/// 从回复中取出JSON对象（兼容 ```json 代码块和前后多余文字）
+fn extract_json_object(response: &str) -> Option<&str> {
+    let start = response.find('{')?;
+    let end = response.rfind('}')?;
+    (end > start).then(|| &response[start..=end])
+}
+
+/// 格式错误后重试时追加在提示词后的说明，使用与检测模板相同的语言
+fn retry_prompt(prompt: &str, error: &str, language: PromptLanguage) -> String {
+    match language {
+        PromptLanguage::Zh => format!(
+            "{}\n\n你上一次的回复无法解析：{}。请严格按照上面的格式只输出JSON对象。",
+            prompt, error
+        ),
+        PromptLanguage::En => format!(
+            "{}\n\nYour previous reply could not be parsed: {}. Reply with only the JSON object, strictly in the format above.",
+            prompt, error
+        ),
+    }
+}
+
+/// 以JSON模式请求专注判断，输出格式错误时附上错误原因重试一次
+///
+/// 返回解析结果和模型原始回复；API调用失败时返回错误，由调用方决定如何降级。
+pub async fn request_classification(
+    ai_service: &AIService,
+    prompt: &str,
+    image_jpeg: Option<&[u8]>,
+    language: PromptLanguage,
+) -> Result<(FocusClassification, String), String> {
+    let options = ChatOptions { json_mode: true, ..Default::default() };
+
+    let reply = ai_service.chat(prompt, image_jpeg, "detection", &options).await?;
+    let error = match FocusClassification::parse(&reply.text) {
+        Ok(classification) => return Ok((classification.answered(reply.answered_by), reply.text)),
+        Err(e) => e,
+    };
+    println!("⚠️ 模型输出无法解析（{}），重试一次", error);
+
+    let retry_prompt = retry_prompt(prompt, &error, language);
+    let reply = ai_service.chat(&retry_prompt, image_jpeg, "detection", &options).await?;
+    let classification = FocusClassification::parse(&reply.text).unwrap_or_else(|e| {
+        println!("❌ 重试后模型输出仍无法解析: {}", e);
+        FocusClassification::unknown(format!("模型输出无法解析: {}", e))
+    });
+    Ok((classification.answered(reply.answered_by), reply.text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALID: &str = r#"{"state": "distracted", "confidence": 0.85, "reason": "正在看视频", "activity_category": "entertainment", "task_relevance": 0.1}"#;
+
+    #[test]
+    fn parses_valid_json() {
+        let classification = FocusClassification::parse(VALID).unwrap();
+        assert_eq!(classification.state, FocusState::Distracted);
+        assert_eq!(classification.confidence, 0.85);
+        assert_eq!(classification.reason, "正在看视频");
+        assert_eq!(classification.activity_category, Some(ActivityCategory::Entertainment));
+        assert_eq!(classification.task_relevance, Some(0.1));
+        assert!(!classification.ai_unavailable);
+
+        // 没有任务时 task_relevance 可以为 null 或省略，未知类别归为 other
+        let json = r#"{"state": " Focused ", "confidence": 1, "reason": "writing code", "activity_category": "coding", "task_relevance": null}"#;
+        let classification = FocusClassification::parse(json).unwrap();
+        assert_eq!(classification.state, FocusState::Focused);
+        assert_eq!(classification.activity_category, Some(ActivityCategory::Other));
+        assert_eq!(classification.task_relevance, None);
+
+        let json = r#"{"state": "严重分心", "confidence": 0, "reason": "刷短视频", "activity_category": "social_media"}"#;
+        assert_eq!(FocusClassification::parse(json).unwrap().state, FocusState::SeverelyDistracted);
+    }
+
+    #[test]
+    fn parses_json_inside_code_fences_and_prose() {
+        let fenced = format!("```json\n{}\n```", VALID);
+        assert_eq!(FocusClassification::parse(&fenced).unwrap().state, FocusState::Distracted);
+
+        let prose = format!("好的，判断结果如下：\n{}\n以上是我的分析。", VALID);
+        assert_eq!(FocusClassification::parse(&prose).unwrap().state, FocusState::Distracted);
+
+        let english = format!("Sure! Here is the classification: {} Let me know if you need more.", VALID);
+        assert_eq!(FocusClassification::parse(&english).unwrap().reason, "正在看视频");
+    }
+
+    #[test]
+    fn rejects_out_of_range_numbers() {
+        let json = r#"{"state": "focused", "confidence": 1.5, "reason": "ok", "activity_category": "work"}"#;
+        assert!(FocusClassification::parse(json).unwrap_err().contains("confidence"));
+
+        let json = r#"{"state": "focused", "confidence": -0.1, "reason": "ok", "activity_category": "work"}"#;
+        assert!(FocusClassification::parse(json).unwrap_err().contains("confidence"));
+
+        let json = r#"{"state": "focused", "confidence": 0.9, "reason": "ok", "activity_category": "work", "task_relevance": 2}"#;
+        assert!(FocusClassification::parse(json).unwrap_err().contains("task_relevance"));
+    }
+
+    #[test]
+    fn rejects_unknown_states() {
+        // 旧的子串解析会把这些误判为分心
+        for state in ["不分心", "not distracted", "unknown", ""] {
+            let json = format!(
+                r#"{{"state": "{}", "confidence": 0.8, "reason": "ok", "activity_category": "work"}}"#,
+                state
+            );
+            assert!(FocusClassification::parse(&json).unwrap_err().contains("未知的状态"), "{}", state);
+        }
+    }
+
+    #[test]
+    fn rejects_non_json_and_incomplete_replies() {
+        assert!(FocusClassification::parse("The user seems distracted.").is_err());
+        assert!(FocusClassification::parse("用户不分心").is_err());
+        assert!(FocusClassification::parse("} 没有对象 {").is_err());
+        assert!(FocusClassification::parse(r#"{"state": "focused"}"#).is_err());
+        assert!(FocusClassification::parse(r#"{"state": "focused", "confidence": 0.9, "reason": "  ", "activity_category": "work"}"#).is_err());
+    }
+
+    #[test]
+    fn retry_prompt_follows_template_language() {
+        let zh = retry_prompt("提示词", "reason 为空", PromptLanguage::Zh);
+        assert!(zh.starts_with("提示词\n\n"));
+        assert!(zh.contains("无法解析：reason 为空"));
+
+        let en = retry_prompt("prompt", "reason 为空", PromptLanguage::En);
+        assert!(en.starts_with("prompt\n\n"));
+        assert!(en.contains("could not be parsed: reason 为空"));
+        assert!(!en.contains("请严格"));
+    }
+}
@@ -0,0 +1,420 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use lazy_static::lazy_static;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::services::monitor_service::{FocusState, MonitoringConfig, PrivacyExclusions};
+use crate::services::window_info_service::ForegroundWindow;
+
+lazy_static! {
+    // 文本中的域名，可带 http(s):// 前缀
+    static ref DOMAIN_PATTERN: Regex = Regex::new(
+        r"(?i)\b(?:https?://)?((?:[a-z0-9](?:[a-z0-9-]{0,61}[a-z0-9])?\.)+[a-z]{2,24})\b"
+    ).unwrap();
+}
+
+/// 规则匹配的字段
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleField {
+    App,    // 应用名称（不区分大小写，忽略 .exe）
+    Title,  // 窗口标题
+    Domain, // 从窗口标题或屏幕文字中提取的域名
+}
+
+/// 匹配方式
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchKind {
+    Exact, // 完全相同；域名规则同时匹配子域名
+    Glob,  // * 匹配任意字符，? 匹配单个字符，需要匹配整个值
+    Regex, // 正则表达式，匹配值的任意部分
+}
+
+/// 规则命中时的判断
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleVerdict {
+    Focused,
+    Distracted,
+    Ignore, // 不做专注判断，也不截图和调用AI
+}
+
+/// 一条专注判断规则
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FocusRule {
+    #[serde(default)]
+    pub id: String, // 为空时保存配置时自动生成
+    #[serde(default = "default_rule_enabled")]
+    pub enabled: bool,
+    pub field: RuleField,
+    #[serde(rename = "match")]
+    pub match_kind: MatchKind,
+    pub pattern: String,
+    pub verdict: RuleVerdict,
+    #[serde(default)]
+    pub priority: i32, // 数值大的先匹配，相同时按列表顺序
+}
+
+fn default_rule_enabled() -> bool {
+    true
+}
+
+/// 命中的规则
+#[derive(Debug, Clone)]
+pub struct RuleMatch {
+    pub rule_id: String,
+    pub verdict: RuleVerdict,
+    pub description: String, // 用于日志和判断理由，如 标题 匹配 *YouTube*
+}
+
+impl RuleMatch {
+    /// 对应的专注状态，忽略规则没有判断
+    pub fn focus_state(&self) -> FocusState {
+        match self.verdict {
+            RuleVerdict::Focused => FocusState::Focused,
+            RuleVerdict::Distracted => FocusState::Distracted,
+            RuleVerdict::Ignore => FocusState::Unknown,
+        }
+    }
+}
+
+enum Matcher {
+    Exact(String),
+    Pattern(Regex),
+}
+
+struct CompiledRule {
+    rule: FocusRule,
+    matcher: Matcher,
+}
+
+impl CompiledRule {
+    fn compile(rule: &FocusRule) -> Result<Self> {
+        let pattern = rule.pattern.trim();
+        if pattern.is_empty() {
+            return Err(anyhow::anyhow!("规则 {} 的匹配内容为空", rule.id));
+        }
+        let matcher = match rule.match_kind {
+            MatchKind::Exact => Matcher::Exact(normalize(rule.field, pattern)),
+            MatchKind::Glob => {
+                let escaped = regex::escape(&normalize(rule.field, pattern))
+                    .replace(r"\*", ".*")
+                    .replace(r"\?", ".");
+                Matcher::Pattern(RegexBuilder::new(&format!("^{}$", escaped)).case_insensitive(true).build()?)
+            }
+            MatchKind::Regex => Matcher::Pattern(
+                RegexBuilder::new(pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(|e| anyhow::anyhow!("规则 {} 的正则表达式无效: {}", rule.id, e))?,
+            ),
+        };
+        Ok(Self { rule: rule.clone(), matcher })
+    }
+
+    fn matches(&self, value: &str) -> bool {
+        let value = normalize(self.rule.field, value);
+        match &self.matcher {
+            Matcher::Exact(expected) if self.rule.field == RuleField::Domain => {
+                value == *expected || value.ends_with(&format!(".{}", expected))
+            }
+            Matcher::Exact(expected) => value == *expected,
+            Matcher::Pattern(regex) => regex.is_match(&value),
+        }
+    }
+
+    fn describe(&self) -> String {
+        let field = match self.rule.field {
+            RuleField::App => "应用",
+            RuleField::Title => "标题",
+            RuleField::Domain => "域名",
+        };
+        format!("{} 匹配 {}", field, self.rule.pattern.trim())
+    }
+}
+
+/// 应用名称去掉 .exe，域名去掉 www.，都不区分大小写
+fn normalize(field: RuleField, value: &str) -> String {
+    match field {
+        RuleField::App => PrivacyExclusions::normalize_app_name(value),
+        RuleField::Title => value.trim().to_lowercase(),
+        RuleField::Domain => {
+            let value = value.trim().to_lowercase();
+            value.strip_prefix("www.").map(str::to_string).unwrap_or(value)
+        }
+    }
+}
+
+/// 提取文本中的域名（浏览器标题或地址栏中的网址）
+pub fn extract_domains(text: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    DOMAIN_PATTERN
+        .captures_iter(text)
+        .map(|caps| caps[1].to_lowercase())
+        .filter(|domain| seen.insert(domain.clone()))
+        .collect()
+}
+
+/// 名单中的网站（如 weibo.com），应用名称如 Code.exe 不算
+fn is_domain_entry(entry: &str) -> bool {
+    let entry = entry.to_lowercase();
+    if entry.ends_with(".exe") || entry.ends_with(".app") {
+        return false;
+    }
+    extract_domains(&entry).first().is_some_and(|domain| *domain == entry)
+}
+
+/// 把白名单/黑名单转换为完全匹配的规则，网站按域名、其余按应用名称，编号为 whitelist-N / blacklist-N
+pub fn app_list_rules(whitelist: &[String], blacklist: &[String]) -> Vec<FocusRule> {
+    let convert = |list: &[String], prefix: &str, verdict: RuleVerdict| -> Vec<FocusRule> {
+        list.iter()
+            .map(|app| app.trim())
+            .filter(|app| !app.is_empty())
+            .enumerate()
+            .map(|(index, app)| FocusRule {
+                id: format!("{}-{}", prefix, index + 1),
+                enabled: true,
+                field: if is_domain_entry(app) { RuleField::Domain } else { RuleField::App },
+                match_kind: MatchKind::Exact,
+                pattern: app.to_string(),
+                verdict,
+                priority: 0,
+            })
+            .collect()
+    };
+    let mut rules = convert(whitelist, "whitelist", RuleVerdict::Focused);
+    rules.extend(convert(blacklist, "blacklist", RuleVerdict::Distracted));
+    rules
+}
+
+/// 检查规则：匹配内容不能为空，正则表达式必须有效，编号不能重复；为空的编号自动生成
+pub fn validate_rules(rules: &mut [FocusRule]) -> Result<()> {
+    let mut ids: HashSet<String> = rules.iter().map(|rule| rule.id.trim().to_string()).collect();
+    for (index, rule) in rules.iter_mut().enumerate() {
+        rule.id = rule.id.trim().to_string();
+        if rule.id.is_empty() {
+            let mut number = index + 1;
+            while ids.contains(&format!("rule-{}", number)) {
+                number += 1;
+            }
+            rule.id = format!("rule-{}", number);
+            ids.insert(rule.id.clone());
+        }
+    }
+
+    let mut seen = HashSet::new();
+    for rule in rules.iter() {
+        if !seen.insert(rule.id.as_str()) {
+            return Err(anyhow::anyhow!("规则编号重复: {}", rule.id));
+        }
+        CompiledRule::compile(rule)?;
+    }
+    Ok(())
+}
+
+/// 在调用AI之前按规则判断，命中时不再截图或调用AI
+pub struct RuleEngine {
+    rules: Vec<CompiledRule>, // 已按优先级排序
+}
+
+impl RuleEngine {
+    /// 编译启用的规则，无效的规则记录日志后跳过
+    pub fn new(rules: &[FocusRule]) -> Self {
+        let mut compiled: Vec<CompiledRule> = rules
+            .iter()
+            .filter(|rule| rule.enabled)
+            .filter_map(|rule| match CompiledRule::compile(rule) {
+                Ok(compiled) => Some(compiled),
+                Err(e) => {
+                    println!("⚠️ 忽略无效的专注规则: {}", e);
+                    None
+                }
+            })
+            .collect();
+        // 稳定排序，相同优先级保持列表顺序
+        compiled.sort_by_key(|compiled| std::cmp::Reverse(compiled.rule.priority));
+        Self { rules: compiled }
+    }
+
+    /// 配置中的规则加上白名单/黑名单应用；优先级相同时配置的规则先匹配
+    pub fn for_config(config: &MonitoringConfig) -> Self {
+        let mut rules = config.rules.clone();
+        rules.extend(app_list_rules(&config.whitelist, &config.blacklist));
+        Self::new(&rules)
+    }
+
+    /// 按应用名称、窗口标题和标题中的域名匹配，返回优先级最高的规则
+    pub fn evaluate(&self, window: &ForegroundWindow) -> Option<RuleMatch> {
+        let title_domains = window.window_title.as_deref().map(extract_domains).unwrap_or_default();
+        self.rules.iter().find_map(|compiled| {
+            let matched = match compiled.rule.field {
+                RuleField::App => window.app_name.as_deref().is_some_and(|app| compiled.matches(app)),
+                RuleField::Title => window.window_title.as_deref().is_some_and(|title| compiled.matches(title)),
+                RuleField::Domain => title_domains.iter().any(|domain| compiled.matches(domain)),
+            };
+            matched.then(|| Self::matched(compiled))
+        })
+    }
+
+    /// 只按域名规则匹配屏幕文字中的网址（标题中没有网址时，地址栏的文字通常有）
+    pub fn evaluate_screen_text(&self, ocr_text: &str) -> Option<RuleMatch> {
+        let domains = extract_domains(ocr_text);
+        if domains.is_empty() {
+            return None;
+        }
+        self.rules
+            .iter()
+            .filter(|compiled| compiled.rule.field == RuleField::Domain)
+            .find(|compiled| domains.iter().any(|domain| compiled.matches(domain)))
+            .map(Self::matched)
+    }
+
+    fn matched(compiled: &CompiledRule) -> RuleMatch {
+        RuleMatch {
+            rule_id: compiled.rule.id.clone(),
+            verdict: compiled.rule.verdict,
+            description: compiled.describe(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(id: &str, field: RuleField, match_kind: MatchKind, pattern: &str, verdict: RuleVerdict, priority: i32) -> FocusRule {
+        FocusRule {
+            id: id.to_string(),
+            enabled: true,
+            field,
+            match_kind,
+            pattern: pattern.to_string(),
+            verdict,
+            priority,
+        }
+    }
+
+    fn window(app_name: &str, window_title: &str) -> ForegroundWindow {
+        ForegroundWindow {
+            app_name: Some(app_name.to_string()),
+            window_title: Some(window_title.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn glob_escapes_regex_metacharacters() {
+        let compiled = CompiledRule::compile(&rule("g", RuleField::Title, MatchKind::Glob, "C++ (draft)*", RuleVerdict::Focused, 0)).unwrap();
+        assert!(compiled.matches("C++ (draft) - notes.txt"));
+        assert!(!compiled.matches("CC (draft) - notes.txt"));
+
+        let compiled = CompiledRule::compile(&rule("g", RuleField::Title, MatchKind::Glob, "a.b?", RuleVerdict::Focused, 0)).unwrap();
+        assert!(compiled.matches("A.bc"));
+        assert!(!compiled.matches("axbc"));
+        assert!(!compiled.matches("a.bcd"));
+    }
+
+    #[test]
+    fn exact_domain_matches_subdomains_only() {
+        let compiled = CompiledRule::compile(&rule("d", RuleField::Domain, MatchKind::Exact, "www.YouTube.com", RuleVerdict::Distracted, 0)).unwrap();
+        assert!(compiled.matches("youtube.com"));
+        assert!(compiled.matches("m.youtube.com"));
+        assert!(compiled.matches("www.youtube.com"));
+        assert!(!compiled.matches("notyoutube.com"));
+        assert!(!compiled.matches("youtube.com.evil.net"));
+
+        // 应用名称的完全匹配不包含后缀
+        let compiled = CompiledRule::compile(&rule("a", RuleField::App, MatchKind::Exact, "Code.exe", RuleVerdict::Focused, 0)).unwrap();
+        assert!(compiled.matches("code"));
+        assert!(!compiled.matches("vscode"));
+    }
+
+    #[test]
+    fn higher_priority_wins_and_ties_keep_list_order() {
+        let rules = vec![
+            rule("title", RuleField::Title, MatchKind::Glob, "*YouTube*", RuleVerdict::Distracted, 0),
+            rule("app", RuleField::App, MatchKind::Exact, "chrome", RuleVerdict::Focused, 0),
+            rule("course", RuleField::Title, MatchKind::Regex, "lecture", RuleVerdict::Focused, 10),
+        ];
+        let engine = RuleEngine::new(&rules);
+
+        let matched = engine.evaluate(&window("chrome.exe", "Lecture 3 - YouTube")).unwrap();
+        assert_eq!(matched.rule_id, "course");
+        let matched = engine.evaluate(&window("chrome.exe", "Music - YouTube")).unwrap();
+        assert_eq!(matched.rule_id, "title");
+        assert!(engine.evaluate(&window("firefox", "Docs")).is_none());
+    }
+
+    #[test]
+    fn disabled_rules_are_skipped() {
+        let mut disabled = rule("off", RuleField::App, MatchKind::Exact, "chrome", RuleVerdict::Distracted, 0);
+        disabled.enabled = false;
+        assert!(RuleEngine::new(&[disabled]).evaluate(&window("chrome", "")).is_none());
+    }
+
+    #[test]
+    fn validate_rules_fills_missing_ids_without_collisions() {
+        let mut rules = vec![
+            rule("", RuleField::App, MatchKind::Exact, "a", RuleVerdict::Focused, 0),
+            rule("rule-1", RuleField::App, MatchKind::Exact, "b", RuleVerdict::Focused, 0),
+            rule("  ", RuleField::App, MatchKind::Exact, "c", RuleVerdict::Focused, 0),
+            rule(" custom ", RuleField::App, MatchKind::Exact, "d", RuleVerdict::Focused, 0),
+        ];
+        validate_rules(&mut rules).unwrap();
+        let ids: Vec<&str> = rules.iter().map(|rule| rule.id.as_str()).collect();
+        assert_eq!(ids, ["rule-2", "rule-1", "rule-3", "custom"]);
+    }
+
+    #[test]
+    fn validate_rules_rejects_duplicates_and_invalid_patterns() {
+        let mut duplicated = vec![
+            rule("x", RuleField::App, MatchKind::Exact, "a", RuleVerdict::Focused, 0),
+            rule("x ", RuleField::App, MatchKind::Exact, "b", RuleVerdict::Focused, 0),
+        ];
+        assert!(validate_rules(&mut duplicated).is_err());
+
+        let mut invalid = vec![rule("r", RuleField::Title, MatchKind::Regex, "(", RuleVerdict::Focused, 0)];
+        assert!(validate_rules(&mut invalid).is_err());
+
+        let mut empty = vec![rule("e", RuleField::Title, MatchKind::Exact, "  ", RuleVerdict::Focused, 0)];
+        assert!(validate_rules(&mut empty).is_err());
+    }
+
+    #[test]
+    fn app_lists_become_exact_rules() {
+        let rules = app_list_rules(
+            &["Code.exe".to_string(), " ".to_string()],
+            &["WeChat.exe".to_string(), "weibo.com".to_string()],
+        );
+        let summary: Vec<(&str, RuleField, RuleVerdict)> =
+            rules.iter().map(|rule| (rule.id.as_str(), rule.field, rule.verdict)).collect();
+        assert_eq!(
+            summary,
+            [
+                ("whitelist-1", RuleField::App, RuleVerdict::Focused),
+                ("blacklist-1", RuleField::App, RuleVerdict::Distracted),
+                ("blacklist-2", RuleField::Domain, RuleVerdict::Distracted),
+            ]
+        );
+        assert!(rules.iter().all(|rule| rule.match_kind == MatchKind::Exact));
+    }
+
+    #[test]
+    fn config_rules_take_precedence_over_app_lists() {
+        let config = MonitoringConfig {
+            whitelist: vec!["chrome.exe".to_string()],
+            blacklist: vec!["youtube.com".to_string()],
+            rules: vec![rule("video", RuleField::Title, MatchKind::Glob, "* - YouTube", RuleVerdict::Distracted, 0)],
+            ..Default::default()
+        };
+        let engine = RuleEngine::for_config(&config);
+
+        assert_eq!(engine.evaluate(&window("Chrome", "Music - YouTube")).unwrap().rule_id, "video");
+        assert_eq!(engine.evaluate(&window("Chrome", "Docs")).unwrap().rule_id, "whitelist-1");
+        assert_eq!(engine.evaluate(&window("firefox", "https://m.youtube.com/watch")).unwrap().rule_id, "blacklist-1");
+        assert!(engine.evaluate(&window("chromium", "Docs")).is_none());
+    }
+}
@@ -0,0 +1,463 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+use crate::services::corrections::{self, Correction, CorrectionStore};
+use crate::services::monitor_service::{FocusState, MonitoringResult, PrivacyExclusions};
+use crate::services::prompt_budget;
+use crate::services::storage_service::StorageService;
+
+const MODEL_FILE: &str = "local_classifier.json";
+
+/// 特征提取方式变化时递增，旧版本的模型会重新训练
+const MODEL_VERSION: u32 = 1;
+
+/// 用户标注的样本权重（相对于模型判断）
+const CORRECTION_WEIGHT: f64 = 3.0;
+
+/// 参与特征提取的屏幕文字长度
+const MAX_OCR_CHARS: usize = 2000;
+
+/// 每隔几个样本取一个作为测试集
+const TEST_EVERY: usize = 5;
+
+/// 模型缺失或过期时，两次后台训练之间的最短间隔（样本不足时避免反复读取全部记录）
+const RETRAIN_RETRY_MINUTES: i64 = 60;
+
+/// 分类器区分的状态
+const CLASSES: [FocusState; 3] = [FocusState::Focused, FocusState::Distracted, FocusState::SeverelyDistracted];
+
+/// 本地分类器的使用方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LocalClassifierMode {
+    Off,
+    #[default]
+    Fallback,   // 只在AI不可用（熔断、调用失败、超出预算）时使用
+    FirstStage, // 先用本地分类器判断，置信度不足时才调用AI
+}
+
+/// 本地分类器配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalClassifierConfig {
+    #[serde(default)]
+    pub mode: LocalClassifierMode,
+    #[serde(default = "default_confidence_threshold")]
+    pub confidence_threshold: f32, // 首轮过滤时置信度达到该值才跳过AI
+    #[serde(default = "default_min_training_samples")]
+    pub min_training_samples: usize, // 样本不足时不训练
+    #[serde(default = "default_retrain_hours")]
+    pub retrain_hours: u32, // 模型超过该时间后用最新记录重新训练
+}
+
+fn default_confidence_threshold() -> f32 {
+    0.9
+}
+
+fn default_min_training_samples() -> usize {
+    30
+}
+
+fn default_retrain_hours() -> u32 {
+    24
+}
+
+impl Default for LocalClassifierConfig {
+    fn default() -> Self {
+        Self {
+            mode: LocalClassifierMode::default(),
+            confidence_threshold: default_confidence_threshold(),
+            min_training_samples: default_min_training_samples(),
+            retrain_hours: default_retrain_hours(),
+        }
+    }
+}
+
+/// 单个状态的评估结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClassMetrics {
+    pub state: FocusState,
+    pub support: usize,         // 测试集中该状态的样本数
+    pub precision: Option<f32>, // 没有被预测为该状态的样本时为空
+    pub recall: Option<f32>,
+}
+
+/// 在留出的测试集上评估的准确率
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClassifierMetrics {
+    pub trained_at: Option<DateTime<Utc>>,
+    pub training_samples: usize,
+    pub labeled_samples: usize, // 其中来自用户标注的样本
+    pub test_samples: usize,
+    pub accuracy: Option<f32>,
+    pub confidence_threshold: f32,
+    pub confident_share: Option<f32>,    // 置信度达到阈值的测试样本比例，即首轮过滤可省去的AI调用
+    pub confident_accuracy: Option<f32>, // 这些样本的准确率
+    pub classes: Vec<ClassMetrics>,
+}
+
+/// 本地分类器的判断
+#[derive(Debug, Clone)]
+pub struct LocalPrediction {
+    pub state: FocusState,
+    pub confidence: f32,
+}
+
+impl LocalPrediction {
+    /// 用本地判断替换结果中的专注状态
+    pub fn apply(&self, result: MonitoringResult, reason: &str) -> MonitoringResult {
+        MonitoringResult {
+            focus_state: self.state.clone(),
+            confidence: self.confidence,
+            ai_analysis: Some(format!("{}，由本地分类器判断（置信度 {:.0}%）", reason, self.confidence * 100.0)),
+            activity_category: None,
+            task_relevance: None,
+            ai_unavailable: false,
+            answered_by: None,
+            cache_hit: false,
+            prompt_version: None,
+            local_classifier: true,
+            ..result
+        }
+    }
+}
+
+/// 内存中共享的模型
+#[derive(Default)]
+struct SharedModel {
+    model: Option<Arc<LocalClassifier>>,
+    loaded: bool,                        // 已读取过模型文件
+    training: bool,                      // 后台训练进行中
+    last_attempt: Option<DateTime<Utc>>, // 上次开始后台训练的时间
+}
+
+lazy_static! {
+    // 模型只从磁盘读取一次，之后由训练更新，监控检查不再每次读取或训练
+    static ref SHARED_MODEL: Mutex<SharedModel> = Mutex::new(SharedModel::default());
+}
+
+fn shared_model() -> std::sync::MutexGuard<'static, SharedModel> {
+    SHARED_MODEL.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// 一个训练样本
+struct Sample {
+    features: HashSet<String>,
+    class: usize,
+    weight: f64,
+}
+
+/// 基于应用名称、窗口标题和屏幕文字词语的朴素贝叶斯分类器
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalClassifier {
+    version: u32,
+    class_weights: [f64; 3],                // 每个状态的样本权重之和
+    feature_totals: [f64; 3],               // 每个状态的特征权重之和
+    feature_weights: HashMap<String, [f64; 3]>,
+    pub metrics: ClassifierMetrics,
+}
+
+/// 应用名称、窗口标题和屏幕文字分别加前缀，同一个词出现在不同位置时含义不同
+fn extract_features(app_name: Option<&str>, window_title: Option<&str>, ocr_text: Option<&str>) -> HashSet<String> {
+    let mut features = HashSet::new();
+    if let Some(app) = app_name {
+        features.insert(format!("app:{}", PrivacyExclusions::normalize_app_name(app)));
+    }
+    for word in corrections::text_words(window_title.unwrap_or("")) {
+        features.insert(format!("title:{}", word));
+    }
+    let ocr = prompt_budget::truncate_chars(ocr_text.unwrap_or(""), MAX_OCR_CHARS);
+    for word in corrections::text_words(ocr) {
+        features.insert(format!("ocr:{}", word));
+    }
+    features
+}
+
+fn class_index(state: &FocusState) -> Option<usize> {
+    CLASSES.iter().position(|class| class == state)
+}
+
+/// 从监控记录和用户纠正中收集样本
+///
+/// 只使用AI实际给出的判断：缓存命中、沿用上次结果和本地分类器的结果会重复或自我强化，不参与训练。
+/// 用户标注优先于模型判断，并且权重更高。
+fn collect_samples(results: &[MonitoringResult], corrections: &[Correction]) -> Vec<Sample> {
+    let mut samples = Vec::new();
+
+    for result in results {
+        let label = match &result.user_label {
+            Some(label) => label,
+            None if result.prompt_version.is_none()
+                || result.private
+                || result.ai_unavailable
+                || result.cache_hit
+                || result.carried_over
+                || result.local_classifier => continue,
+            None => &result.focus_state,
+        };
+        let Some(class) = class_index(label) else { continue };
+        samples.push(Sample {
+            features: extract_features(
+                result.application_name.as_deref(),
+                result.window_title.as_deref(),
+                result.ocr_text.as_deref(),
+            ),
+            class,
+            weight: if result.user_label.is_some() { CORRECTION_WEIGHT } else { 1.0 },
+        });
+    }
+
+    // 对应的监控记录已被清理的纠正只有应用名称和窗口标题
+    let stored: HashSet<DateTime<Utc>> = results.iter().map(|result| result.timestamp).collect();
+    for correction in corrections {
+        if stored.contains(&correction.result_timestamp) {
+            continue;
+        }
+        let Some(class) = class_index(&correction.user_state) else { continue };
+        samples.push(Sample {
+            features: extract_features(correction.application_name.as_deref(), correction.window_title.as_deref(), None),
+            class,
+            weight: CORRECTION_WEIGHT,
+        });
+    }
+
+    samples
+}
+
+impl LocalClassifier {
+    fn empty() -> Self {
+        Self {
+            version: MODEL_VERSION,
+            class_weights: [0.0; 3],
+            feature_totals: [0.0; 3],
+            feature_weights: HashMap::new(),
+            metrics: ClassifierMetrics::default(),
+        }
+    }
+
+    fn fit<'a>(samples: impl Iterator<Item = &'a Sample>) -> Self {
+        let mut model = Self::empty();
+        for sample in samples {
+            model.class_weights[sample.class] += sample.weight;
+            for feature in &sample.features {
+                model.feature_weights.entry(feature.clone()).or_insert([0.0; 3])[sample.class] += sample.weight;
+                model.feature_totals[sample.class] += sample.weight;
+            }
+        }
+        model
+    }
+
+    /// 用监控记录和用户纠正训练，并在留出的测试集上评估
+    pub fn train(results: &[MonitoringResult], corrections: &[Correction], config: &LocalClassifierConfig) -> Result<Self> {
+        let samples = collect_samples(results, corrections);
+        if samples.len() < config.min_training_samples {
+            return Err(anyhow::anyhow!(
+                "训练样本不足: {} 条，至少需要 {} 条",
+                samples.len(),
+                config.min_training_samples
+            ));
+        }
+
+        // 每隔几个样本留出一个评估，再用全部样本训练最终模型
+        let held_out = Self::fit(samples.iter().enumerate().filter(|(i, _)| i % TEST_EVERY != 0).map(|(_, s)| s));
+        let test: Vec<&Sample> = samples.iter().step_by(TEST_EVERY).collect();
+        let mut model = Self::fit(samples.iter());
+        model.metrics = held_out.evaluate(&test, config.confidence_threshold);
+        model.metrics.trained_at = Some(Utc::now());
+        model.metrics.training_samples = samples.len();
+        model.metrics.labeled_samples = samples.iter().filter(|s| s.weight > 1.0).count();
+        Ok(model)
+    }
+
+    fn evaluate(&self, test: &[&Sample], threshold: f32) -> ClassifierMetrics {
+        let mut correct = 0;
+        let mut confident = 0;
+        let mut confident_correct = 0;
+        let mut predicted = [0usize; 3];
+        let mut true_positive = [0usize; 3];
+        let mut support = [0usize; 3];
+
+        for sample in test {
+            support[sample.class] += 1;
+            let Some((class, confidence)) = self.posterior(&sample.features) else { continue };
+            predicted[class] += 1;
+            if class == sample.class {
+                correct += 1;
+                true_positive[class] += 1;
+            }
+            if confidence >= threshold {
+                confident += 1;
+                if class == sample.class {
+                    confident_correct += 1;
+                }
+            }
+        }
+
+        let ratio = |part: usize, total: usize| if total == 0 { None } else { Some(part as f32 / total as f32) };
+        ClassifierMetrics {
+            test_samples: test.len(),
+            accuracy: ratio(correct, test.len()),
+            confidence_threshold: threshold,
+            confident_share: ratio(confident, test.len()),
+            confident_accuracy: ratio(confident_correct, confident),
+            classes: CLASSES
+                .iter()
+                .enumerate()
+                .map(|(i, state)| ClassMetrics {
+                    state: state.clone(),
+                    support: support[i],
+                    precision: ratio(true_positive[i], predicted[i]),
+                    recall: ratio(true_positive[i], support[i]),
+                })
+                .collect(),
+            ..ClassifierMetrics::default()
+        }
+    }
+
+    /// 最可能的状态及其后验概率；没有任何已知特征时返回 None
+    fn posterior(&self, features: &HashSet<String>) -> Option<(usize, f32)> {
+        let known: Vec<&[f64; 3]> = features.iter().filter_map(|f| self.feature_weights.get(f)).collect();
+        if known.is_empty() {
+            return None;
+        }
+
+        // 拉普拉斯平滑的多项式朴素贝叶斯，只计算出现过的特征
+        let total_weight: f64 = self.class_weights.iter().sum();
+        let vocabulary = self.feature_weights.len() as f64;
+        let scores: Vec<Option<f64>> = (0..CLASSES.len())
+            .map(|class| {
+                if self.class_weights[class] == 0.0 {
+                    return None;
+                }
+                let prior = (self.class_weights[class] / total_weight).ln();
+                let denominator = self.feature_totals[class] + vocabulary;
+                Some(prior + known.iter().map(|weights| ((weights[class] + 1.0) / denominator).ln()).sum::<f64>())
+            })
+            .collect();
+
+        let max = scores.iter().flatten().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let exp: Vec<f64> = scores.iter().map(|score| score.map_or(0.0, |s| (s - max).exp())).collect();
+        let sum: f64 = exp.iter().sum();
+        let (class, best) = exp
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))?;
+        Some((class, (best / sum) as f32))
+    }
+
+    /// 判断当前窗口的专注状态
+    pub fn predict(&self, app_name: Option<&str>, window_title: Option<&str>, ocr_text: Option<&str>) -> Option<LocalPrediction> {
+        let (class, confidence) = self.posterior(&extract_features(app_name, window_title, ocr_text))?;
+        Some(LocalPrediction {
+            state: CLASSES[class].clone(),
+            confidence,
+        })
+    }
+
+    fn is_stale(&self, config: &LocalClassifierConfig) -> bool {
+        self.version != MODEL_VERSION
+            || self
+                .metrics
+                .trained_at
+                .is_none_or(|trained_at| Utc::now() - trained_at > Duration::hours(i64::from(config.retrain_hours)))
+    }
+
+    /// 读取数据目录中的模型
+    pub fn load(data_dir: &Path) -> Option<Self> {
+        let content = fs::read_to_string(data_dir.join(MODEL_FILE)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// 与其他服务一致，使用应用本地目录
+    pub fn load_default() -> Option<Self> {
+        Self::load(&PathBuf::from("data"))
+    }
+
+    pub fn save(&self, data_dir: &Path) -> Result<()> {
+        fs::create_dir_all(data_dir)?;
+        fs::write(data_dir.join(MODEL_FILE), serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// 用已保存的监控记录和用户纠正训练并保存模型
+    pub async fn train_from_storage(config: &LocalClassifierConfig) -> Result<Self> {
+        let data_dir = PathBuf::from("data");
+        let results = StorageService::new(data_dir.clone()).load_monitoring_results().await?;
+        let corrections = CorrectionStore::load(data_dir.clone());
+        let model = Self::train(&results, corrections.corrections(), config)?;
+        model.save(&data_dir)?;
+        {
+            let mut shared = shared_model();
+            shared.model = Some(Arc::new(model.clone()));
+            shared.loaded = true;
+        }
+        println!(
+            "🧮 本地分类器已训练: {} 条样本（{} 条用户标注），测试准确率 {}",
+            model.metrics.training_samples,
+            model.metrics.labeled_samples,
+            model.metrics.accuracy.map_or("无".to_string(), |a| format!("{:.1}%", a * 100.0))
+        );
+        Ok(model)
+    }
+
+    /// 按配置取得内存中的模型；关闭或没有可用模型时返回 None
+    ///
+    /// 模型缺失或过期时在后台重新训练，本次调用仍返回当前的模型。需要在 tokio 运行时中调用。
+    pub fn shared(config: &LocalClassifierConfig) -> Option<Arc<Self>> {
+        if config.mode == LocalClassifierMode::Off {
+            return None;
+        }
+        Self::schedule_retrain(config);
+        shared_model().model.clone()
+    }
+
+    /// 模型缺失或过期时启动后台训练，最多每 `RETRAIN_RETRY_MINUTES` 分钟一次
+    ///
+    /// 监控循环每次检查后调用，只比较内存中的时间，不读取记录。需要在 tokio 运行时中调用。
+    pub fn schedule_retrain(config: &LocalClassifierConfig) {
+        if config.mode == LocalClassifierMode::Off {
+            return;
+        }
+
+        let mut shared = shared_model();
+        if !shared.loaded {
+            shared.loaded = true;
+            // 旧版本的特征与当前不兼容，不能继续使用
+            shared.model = Self::load_default()
+                .filter(|model| model.version == MODEL_VERSION)
+                .map(Arc::new);
+        }
+
+        let needs_training = shared.model.as_ref().is_none_or(|model| model.is_stale(config));
+        let retry_due = shared
+            .last_attempt
+            .is_none_or(|attempt| Utc::now() - attempt > Duration::minutes(RETRAIN_RETRY_MINUTES));
+        if needs_training && retry_due && !shared.training {
+            shared.training = true;
+            shared.last_attempt = Some(Utc::now());
+            let config = config.clone();
+            tokio::spawn(async move {
+                if let Err(e) = Self::train_from_storage(&config).await {
+                    println!("⚠️ 本地分类器未更新: {}", e);
+                }
+                shared_model().training = false;
+            });
+        }
+    }
+
+    /// 用内存中的模型判断当前窗口；关闭或没有可用模型时返回 None
+    pub fn predict_current(
+        config: &LocalClassifierConfig,
+        app_name: Option<&str>,
+        window_title: Option<&str>,
+        ocr_text: Option<&str>,
+    ) -> Option<LocalPrediction> {
+        Self::shared(config)?.predict(app_name, window_title, ocr_text)
+    }
+}
@@ -3,10 +3,25 @@ pub mod monitor_service;
 pub mod timer_service;
 pub mod ai_service;
 pub mod report_service;
+pub mod window_info_service;
+pub mod ocr_service;
+pub mod prompt_budget;
+pub mod prompt_templates;
+pub mod redaction_service;
+pub mod focus_classification;
+pub mod ai_providers;
+pub mod usage_service;
+pub mod classification_cache;
+pub mod secret_service;
+pub mod corrections;
+pub mod local_classifier;
+pub mod focus_rules;
 
 // 重新导出服务
 pub use storage_service::*;
 pub use monitor_service::*;
 pub use timer_service::*;
 pub use ai_service::*;
-pub use report_service::*; 
\ No newline at end of file
+pub use report_service::*;
+pub use window_info_service::*;
+pub use ocr_service::*; 
\ No newline at end of file
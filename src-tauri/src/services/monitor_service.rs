@@ -6,7 +6,27 @@ use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use tauri::{AppHandle, Manager};
 
-use crate::services::ai_service::{AIService, AIConfig};
+use crate::services::ai_service::{AIService, AIConfig, AnsweredBy};
+use crate::services::classification_cache::{CacheKey, ClassificationCache, ClassificationCacheConfig};
+use crate::services::corrections::{CorrectionStore, CorrectionsConfig};
+use crate::services::local_classifier::{LocalClassifier, LocalClassifierConfig, LocalClassifierMode};
+use crate::services::focus_rules::{FocusRule, RuleEngine, RuleMatch};
+use crate::services::focus_classification::{self, ActivityCategory, FocusClassification};
+use crate::services::ocr_service::{self, OcrEngineKind, OcrOptions, TesseractSettings};
+use crate::services::prompt_budget::{self, PromptBudgetConfig};
+use crate::services::prompt_templates::{self, PromptLanguage, PromptTemplateStore};
+use crate::services::usage_service::{BudgetFallback, BudgetMode, UsageService};
+use crate::services::redaction_service::{RedactionConfig, RedactionCounts, Redactor};
+use crate::services::window_info_service::{self, ForegroundWindow, WindowBounds};
+
+/// 截图模式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum CaptureMode {
+    #[default]
+    PrimaryOnly,      // 仅主屏幕
+    AllScreens,       // 所有屏幕拼接
+    ForegroundScreen, // 前台窗口所在屏幕
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MonitoringConfig {
@@ -15,6 +35,130 @@ pub struct MonitoringConfig {
     pub whitelist: Vec<String>,
     pub blacklist: Vec<String>,
     pub ai_config: AIConfig,
+    #[serde(default)]
+    pub capture_mode: CaptureMode,
+    #[serde(default = "default_skip_unchanged_frames")]
+    pub skip_unchanged_frames: bool, // 画面未变化时沿用上次判断
+    #[serde(default = "default_frame_change_threshold")]
+    pub frame_change_threshold: u32, // 感知哈希允许的差异位数 (0-64)
+    #[serde(default = "default_crop_to_active_window")]
+    pub crop_to_active_window: bool, // OCR仅识别前台窗口区域，关闭则识别整个截图
+    #[serde(default)]
+    pub ocr_engine: OcrEngineKind,
+    #[serde(default = "default_ocr_languages")]
+    pub ocr_languages: String, // Tesseract语言代码，如 chi_sim+eng、chi_tra+eng、jpn+eng
+    #[serde(default = "default_ocr_psm")]
+    pub ocr_psm: u8, // 页面分割模式，6 = 单个统一文本块
+    #[serde(default = "default_ocr_oem")]
+    pub ocr_oem: u8, // OCR引擎模式，3 = 默认
+    #[serde(default = "default_degraded_image_features")]
+    pub degraded_image_features: bool, // OCR不可用时附带图像统计特征
+    #[serde(default)]
+    pub vision_mode: bool, // 检测模型支持图像输入时，把截图直接发送给模型
+    #[serde(default = "default_vision_max_dimension")]
+    pub vision_max_dimension: u32, // 发送给模型的截图最长边（像素）
+    #[serde(default)]
+    pub prompt_budget: PromptBudgetConfig,
+    #[serde(default)]
+    pub tesseract_path: Option<String>, // 为空时自动查找
+    #[serde(default)]
+    pub tessdata_dir: Option<String>, // 为空时使用 TESSDATA_PREFIX 或自动查找
+    #[serde(default)]
+    pub redaction: RedactionConfig, // 发送给AI和写入磁盘前的敏感信息脱敏
+    #[serde(default)]
+    pub never_capture: PrivacyExclusions, // 命中时不截图、不OCR、不调用AI
+    #[serde(default)]
+    pub classification_cache: ClassificationCacheConfig, // 相同窗口上下文复用AI判断
+    #[serde(default)]
+    pub prompt_language: PromptLanguage, // 专注检测提示词使用的语言
+    #[serde(default)]
+    pub corrections: CorrectionsConfig, // 用户纠正作为提示词示例
+    #[serde(default)]
+    pub local_classifier: LocalClassifierConfig, // AI不可用时或调用AI之前使用的本地分类器
+    #[serde(default)]
+    pub rules: Vec<FocusRule>, // 调用AI之前按应用、标题和域名判断的规则
+}
+
+/// 永不截图的隐私应用（密码管理器、网银、人事系统等）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PrivacyExclusions {
+    #[serde(default)]
+    pub app_names: Vec<String>, // 应用名称，不区分大小写，可省略 .exe
+    #[serde(default)]
+    pub title_patterns: Vec<String>, // 窗口标题关键字，不区分大小写，支持 * 通配符
+}
+
+impl PrivacyExclusions {
+    /// 返回前台窗口命中的排除规则
+    pub fn matched_rule(&self, window: &ForegroundWindow) -> Option<String> {
+        if let Some(app_name) = window.app_name.as_deref() {
+            let app_name = Self::normalize_app_name(app_name);
+            if let Some(rule) = self.app_names.iter().find(|rule| {
+                !rule.trim().is_empty() && Self::normalize_app_name(rule) == app_name
+            }) {
+                return Some(format!("应用 {}", rule));
+            }
+        }
+
+        if let Some(title) = window.window_title.as_deref() {
+            if let Some(rule) = self.title_patterns.iter().find(|rule| Self::title_matches(rule, title)) {
+                return Some(format!("标题 {}", rule));
+            }
+        }
+
+        None
+    }
+
+    pub(crate) fn normalize_app_name(name: &str) -> String {
+        let name = name.trim().to_lowercase();
+        name.strip_suffix(".exe").map(str::to_string).unwrap_or(name)
+    }
+
+    /// 标题包含关键字即命中，* 匹配任意字符
+    fn title_matches(pattern: &str, title: &str) -> bool {
+        let pattern = pattern.trim();
+        if pattern.is_empty() {
+            return false;
+        }
+        let escaped: Vec<String> = pattern.split('*').map(regex::escape).collect();
+        regex::RegexBuilder::new(&escaped.join(".*"))
+            .case_insensitive(true)
+            .build()
+            .map(|regex| regex.is_match(title))
+            .unwrap_or(false)
+    }
+}
+
+fn default_skip_unchanged_frames() -> bool {
+    true
+}
+
+fn default_frame_change_threshold() -> u32 {
+    6
+}
+
+fn default_crop_to_active_window() -> bool {
+    true
+}
+
+fn default_ocr_languages() -> String {
+    "chi_sim+eng".to_string()
+}
+
+fn default_ocr_psm() -> u8 {
+    6
+}
+
+fn default_ocr_oem() -> u8 {
+    3
+}
+
+fn default_degraded_image_features() -> bool {
+    true
+}
+
+fn default_vision_max_dimension() -> u32 {
+    1280
 }
 
 impl Default for MonitoringConfig {
@@ -25,11 +169,52 @@ impl Default for MonitoringConfig {
             whitelist: vec![],
             blacklist: vec![],
             ai_config: AIConfig::default(),
+            capture_mode: CaptureMode::default(),
+            skip_unchanged_frames: default_skip_unchanged_frames(),
+            frame_change_threshold: default_frame_change_threshold(),
+            crop_to_active_window: default_crop_to_active_window(),
+            ocr_engine: OcrEngineKind::default(),
+            ocr_languages: default_ocr_languages(),
+            ocr_psm: default_ocr_psm(),
+            ocr_oem: default_ocr_oem(),
+            degraded_image_features: default_degraded_image_features(),
+            vision_mode: false,
+            vision_max_dimension: default_vision_max_dimension(),
+            prompt_budget: PromptBudgetConfig::default(),
+            tesseract_path: None,
+            tessdata_dir: None,
+            redaction: RedactionConfig::default(),
+            never_capture: PrivacyExclusions::default(),
+            classification_cache: ClassificationCacheConfig::default(),
+            prompt_language: PromptLanguage::default(),
+            corrections: CorrectionsConfig::default(),
+            local_classifier: LocalClassifierConfig::default(),
+            rules: vec![],
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl MonitoringConfig {
+    /// 当前配置对应的OCR识别参数
+    pub fn ocr_options(&self) -> OcrOptions {
+        let languages = self.ocr_languages.trim();
+        OcrOptions {
+            languages: if languages.is_empty() { default_ocr_languages() } else { languages.to_string() },
+            psm: self.ocr_psm.min(13),
+            oem: self.ocr_oem.min(3),
+        }
+    }
+    
+    /// 当前配置的Tesseract路径
+    pub fn tesseract_settings(&self) -> TesseractSettings {
+        TesseractSettings {
+            tesseract_path: self.tesseract_path.clone(),
+            tessdata_dir: self.tessdata_dir.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum FocusState {
     Focused,
     Distracted,
@@ -37,6 +222,17 @@ pub enum FocusState {
     Unknown,
 }
 
+/// 实际截取的显示区域
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapturedDisplay {
+    pub mode: CaptureMode,
+    pub display_ids: Vec<u32>, // 参与截图的屏幕ID
+    pub x: i32,                // 截图区域在虚拟桌面中的位置
+    pub y: i32,
+    pub width: u32,            // 截图区域大小（逻辑像素）
+    pub height: u32,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MonitoringResult {
     pub timestamp: DateTime<Utc>,
@@ -46,6 +242,367 @@ pub struct MonitoringResult {
     pub ocr_text: Option<String>,
     pub ai_analysis: Option<String>,
     pub confidence: f32,
+    #[serde(default)]
+    pub captured_display: Option<CapturedDisplay>,
+    #[serde(default)]
+    pub frame_hash: Option<String>, // 截图的感知哈希（十六进制）
+    #[serde(default)]
+    pub carried_over: bool,         // 画面未变化，沿用上次判断结果
+    #[serde(default)]
+    pub ocr_status: OcrStatus,
+    #[serde(default)]
+    pub image_features: Option<ImageFeatures>, // OCR不可用时计算的图像统计特征
+    #[serde(default)]
+    pub vision_used: bool,          // 截图已直接发送给视觉模型
+    #[serde(default)]
+    pub redactions: RedactionCounts, // 屏幕文字和窗口标题中被脱敏的数量
+    #[serde(default)]
+    pub private: bool,              // 前台窗口命中隐私排除规则，未截图也未调用AI
+    #[serde(default)]
+    pub activity_category: Option<ActivityCategory>, // 模型判断的活动类别
+    #[serde(default)]
+    pub task_relevance: Option<f32>, // 与当前任务的相关度 (0-1)
+    #[serde(default)]
+    pub ai_unavailable: bool,       // AI服务不可用，没有得到专注判断（区别于“无法判断”）
+    #[serde(default)]
+    pub answered_by: Option<AnsweredBy>, // 实际回复的AI服务（可能是备用服务）
+    #[serde(default)]
+    pub budget_mode: Option<BudgetFallback>, // 超出AI预算后改用的判断方式
+    #[serde(default)]
+    pub cache_hit: bool,            // 复用了相同窗口上下文的缓存判断，未调用AI
+    #[serde(default)]
+    pub prompt_version: Option<String>, // 生成判断所用的提示词模板版本，如 zh-builtin-1
+    #[serde(default)]
+    pub user_label: Option<FocusState>, // 用户标注的实际状态（纠正或确认模型判断）
+    #[serde(default)]
+    pub local_classifier: bool,     // 由本地分类器判断
+    #[serde(default)]
+    pub matched_rule: Option<String>, // 命中的专注规则编号，未调用AI
+}
+
+impl MonitoringResult {
+    /// 隐私窗口的记录：不保存窗口标题，也不做专注判断
+    pub fn private(window: &ForegroundWindow) -> Self {
+        Self {
+            private: true,
+            ..Self::skipped(window.app_name.clone(), None, "隐私窗口，已跳过截图和AI分析")
+        }
+    }
+
+    /// AI服务熔断期间的记录：不截图也不调用AI
+    pub fn ai_unavailable(window: &ForegroundWindow, reason: impl Into<String>) -> Self {
+        Self {
+            ai_unavailable: true,
+            ..Self::skipped(window.app_name.clone(), window.window_title.clone(), reason)
+        }
+    }
+
+    /// 超出AI预算且没有本地模型时的记录：只按专注规则和白名单/黑名单判断，与调用AI前的规则匹配一致
+    pub fn rule_only(config: &MonitoringConfig, window: &ForegroundWindow, reason: &str) -> Self {
+        let rule = RuleEngine::for_config(config).evaluate(window);
+        let (focus_state, analysis) = match &rule {
+            Some(rule) => (rule.focus_state(), format!("命中规则 {}（{}）", rule.rule_id, rule.description)),
+            None => (FocusState::Unknown, "没有命中任何规则，无法判断".to_string()),
+        };
+        Self {
+            confidence: if focus_state == FocusState::Unknown { 0.0 } else { 0.6 },
+            focus_state,
+            matched_rule: rule.map(|rule| rule.rule_id),
+            budget_mode: Some(BudgetFallback::AppRules),
+            ..Self::skipped(
+                window.app_name.clone(),
+                window.window_title.clone(),
+                format!("{}，仅按应用规则判断：{}", reason, analysis),
+            )
+        }
+    }
+
+    /// 命中专注规则时的记录：按规则判断，不调用AI
+    pub fn rule_matched(window: &ForegroundWindow, rule: &RuleMatch) -> Self {
+        Self {
+            focus_state: rule.focus_state(),
+            confidence: if rule.focus_state() == FocusState::Unknown { 0.0 } else { 1.0 },
+            matched_rule: Some(rule.rule_id.clone()),
+            ..Self::skipped(
+                window.app_name.clone(),
+                window.window_title.clone(),
+                format!("命中规则 {}（{}）", rule.rule_id, rule.description),
+            )
+        }
+    }
+
+    /// 画面未变化时沿用上次的判断
+    ///
+    /// 只沿用判断本身；用户标注、缓存、本地分类器、规则和预算等字段记录的是本次检查的情况。
+    pub fn carried_over(
+        previous: MonitoringResult,
+        window: &ForegroundWindow,
+        screen: &ScreenContent,
+        redactions: RedactionCounts,
+        budget_mode: Option<BudgetFallback>,
+    ) -> Self {
+        Self {
+            focus_state: previous.focus_state,
+            confidence: previous.confidence,
+            ai_analysis: previous.ai_analysis,
+            activity_category: previous.activity_category,
+            task_relevance: previous.task_relevance,
+            answered_by: previous.answered_by,
+            prompt_version: previous.prompt_version,
+            ocr_text: screen.ocr_text.clone(),
+            ocr_status: screen.ocr_status,
+            image_features: screen.image_features.clone(),
+            captured_display: screen.captured_display.clone(),
+            frame_hash: screen.frame_hash.map(|hash| format!("{:016x}", hash)),
+            redactions,
+            budget_mode,
+            carried_over: true,
+            ..Self::skipped(window.app_name.clone(), window.window_title.clone(), "")
+        }
+    }
+
+    /// 跳过截图和AI分析时的记录
+    fn skipped(application_name: Option<String>, window_title: Option<String>, reason: impl Into<String>) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            focus_state: FocusState::Unknown,
+            application_name,
+            window_title,
+            ocr_text: None,
+            ai_analysis: Some(reason.into()),
+            confidence: 0.0,
+            captured_display: None,
+            frame_hash: None,
+            carried_over: false,
+            ocr_status: OcrStatus::Disabled,
+            image_features: None,
+            vision_used: false,
+            redactions: RedactionCounts::default(),
+            private: false,
+            activity_category: None,
+            task_relevance: None,
+            ai_unavailable: false,
+            answered_by: None,
+            budget_mode: None,
+            cache_hit: false,
+            prompt_version: None,
+            user_label: None,
+            local_classifier: false,
+            matched_rule: None,
+        }
+    }
+}
+
+/// 屏幕文字识别状态
+///
+/// 默认值为 `Unavailable`：旧版本保存的结果和未经OCR的 `ScreenContent` 不应被当作识别成功。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum OcrStatus {
+    Recognized,  // 识别到文字
+    NoText,      // OCR正常运行但没有识别到文字
+    #[default]
+    Unavailable, // 截图或OCR失败
+    Disabled,    // 配置中关闭了OCR
+}
+
+impl OcrStatus {
+    /// 是否只能依据应用名称和窗口标题判断
+    pub fn is_degraded(&self) -> bool {
+        !matches!(self, OcrStatus::Recognized)
+    }
+}
+
+/// 截图的图像统计特征
+///
+/// 这些只是像素统计，不代表屏幕上的文字内容，提示词中会如实标注。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageFeatures {
+    pub dominant_colors: Vec<String>, // 占比最高的颜色 (#rrggbb)，按占比降序
+    pub edge_density: f32,            // 强边缘像素比例 (0-1)，文字和细节越多越高
+    pub frame_change: Option<f32>,    // 与上次截图相比感知哈希变化的比例 (0-1)
+}
+
+impl ImageFeatures {
+    /// 边缘强度阈值（相邻像素亮度差）
+    const EDGE_THRESHOLD: i16 = 40;
+
+    /// 从截图计算图像特征，`previous_hash` 为上次截图的感知哈希
+    fn from_image_data(image_data: &[u8], frame_hash: u64, previous_hash: Option<u64>) -> Result<Self> {
+        use std::collections::HashMap;
+
+        let img = image::load_from_memory(image_data)
+            .map_err(|e| anyhow::anyhow!("图像解码失败: {}", e))?;
+
+        // 主要颜色：缩小后把每个通道量化为8级再统计
+        let small = img.thumbnail(64, 64).to_rgb8();
+        let mut buckets: HashMap<[u8; 3], usize> = HashMap::new();
+        for pixel in small.pixels() {
+            *buckets.entry([pixel[0] >> 5, pixel[1] >> 5, pixel[2] >> 5]).or_default() += 1;
+        }
+        let mut buckets: Vec<_> = buckets.into_iter().collect();
+        buckets.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        let dominant_colors = buckets
+            .iter()
+            .take(3)
+            .map(|(bucket, _)| {
+                let [r, g, b] = bucket.map(|level| (level << 5) | 0x10);
+                format!("#{:02x}{:02x}{:02x}", r, g, b)
+            })
+            .collect();
+
+        // 边缘密度：水平方向相邻像素亮度差超过阈值的比例，文字密集的界面明显更高
+        let gray = img.thumbnail(480, 480).to_luma8();
+        let mut edges = 0usize;
+        let mut total = 0usize;
+        for y in 0..gray.height() {
+            for x in 1..gray.width() {
+                let diff = gray.get_pixel(x, y)[0] as i16 - gray.get_pixel(x - 1, y)[0] as i16;
+                if diff.abs() > Self::EDGE_THRESHOLD {
+                    edges += 1;
+                }
+                total += 1;
+            }
+        }
+        let edge_density = if total > 0 { edges as f32 / total as f32 } else { 0.0 };
+
+        let frame_change = previous_hash.map(|previous| (frame_hash ^ previous).count_ones() as f32 / 64.0);
+
+        Ok(Self { dominant_colors, edge_density, frame_change })
+    }
+
+    /// 提示词中的描述
+    pub fn describe(&self, language: PromptLanguage) -> String {
+        let zh = language == PromptLanguage::Zh;
+        let mut parts = Vec::new();
+        if !self.dominant_colors.is_empty() {
+            let label = if zh { "主要颜色" } else { "dominant colors" };
+            parts.push(format!("{} {}", label, self.dominant_colors.join(", ")));
+        }
+        parts.push(if zh {
+            format!("边缘密度 {:.2}（文字或细节越多越高，纯色或视频画面较低）", self.edge_density)
+        } else {
+            format!("edge density {:.2} (higher with more text or detail, lower for solid colors or video)", self.edge_density)
+        });
+        if let Some(change) = self.frame_change {
+            parts.push(if zh {
+                format!("与上次截图的画面变化 {:.0}%（持续大幅变化可能是视频或游戏）", change * 100.0)
+            } else {
+                format!("{:.0}% change since the last screenshot (sustained large changes suggest video or games)", change * 100.0)
+            });
+        }
+        parts.join(if zh { "；" } else { "; " })
+    }
+}
+
+/// OCR裁剪区域（相对截图宽高的比例，0.0-1.0）
+#[derive(Debug, Clone, Copy)]
+pub struct OcrCrop {
+    pub left: f64,
+    pub top: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl OcrCrop {
+    /// 窗口在截图中占比过小时不裁剪（通常是误判或最小化的窗口）
+    const MIN_FRACTION: f64 = 0.05;
+
+    /// 计算前台窗口在截图区域中的位置，窗口不在截图范围内时返回 None
+    pub fn from_window(bounds: &WindowBounds, display: &CapturedDisplay) -> Option<Self> {
+        if display.width == 0 || display.height == 0 {
+            return None;
+        }
+
+        let left = bounds.x.max(display.x);
+        let top = bounds.y.max(display.y);
+        let right = (bounds.x + bounds.width as i32).min(display.x + display.width as i32);
+        let bottom = (bounds.y + bounds.height as i32).min(display.y + display.height as i32);
+        if right <= left || bottom <= top {
+            return None;
+        }
+
+        let crop = Self {
+            left: (left - display.x) as f64 / display.width as f64,
+            top: (top - display.y) as f64 / display.height as f64,
+            width: (right - left) as f64 / display.width as f64,
+            height: (bottom - top) as f64 / display.height as f64,
+        };
+        if crop.width < Self::MIN_FRACTION || crop.height < Self::MIN_FRACTION {
+            return None;
+        }
+        Some(crop)
+    }
+
+    /// 换算为图像像素坐标 (x, y, width, height)
+    fn to_pixels(self, image_width: u32, image_height: u32) -> (u32, u32, u32, u32) {
+        let x = (self.left * image_width as f64).round() as u32;
+        let y = (self.top * image_height as f64).round() as u32;
+        let width = ((self.width * image_width as f64).round() as u32).clamp(1, image_width.saturating_sub(x).max(1));
+        let height = ((self.height * image_height as f64).round() as u32).clamp(1, image_height.saturating_sub(y).max(1));
+        (x, y, width, height)
+    }
+}
+
+/// 一次屏幕截图和OCR的结果
+#[derive(Debug, Clone, Default)]
+pub struct ScreenContent {
+    pub ocr_text: Option<String>,
+    pub ocr_status: OcrStatus,
+    pub image_features: Option<ImageFeatures>,
+    pub vision_image: Option<Vec<u8>>, // 视觉模式下发送给模型的缩小JPEG
+    pub captured_display: Option<CapturedDisplay>,
+    pub frame_hash: Option<u64>,
+    pub unchanged: bool, // 与上次结果相比画面和窗口均未变化，已跳过OCR
+}
+
+impl ScreenContent {
+    /// 提示词中“屏幕内容”部分
+    ///
+    /// OCR不可用时明确告知模型只能依据应用名称和窗口标题判断，不把任何推测当作屏幕文字。
+    /// OCR文字按 `ocr_tokens` 预算压缩，信息量高的行排在前面。
+    pub fn describe_for_prompt(&self, ocr_tokens: usize, language: PromptLanguage) -> String {
+        let zh = language == PromptLanguage::Zh;
+        if self.vision_image.is_some() {
+            let mut description = match (self.ocr_status, self.ocr_text.as_deref()) {
+                (OcrStatus::Recognized, Some(text)) => format!(
+                    "{}: {}",
+                    if zh { "OCR文字（可能有识别错误）" } else { "OCR text (may contain recognition errors)" },
+                    prompt_budget::fit_ocr_text(text, ocr_tokens)
+                ),
+                _ if zh => "OCR未提供文字".to_string(),
+                _ => "OCR provided no text".to_string(),
+            };
+            description.push_str(if zh {
+                "\n- 屏幕截图: 已附带当前屏幕截图，请以截图中的实际内容为主要判断依据"
+            } else {
+                "\n- Screenshot: the current screenshot is attached; base your judgment mainly on what it shows"
+            });
+            return description;
+        }
+        
+        let mut description = match (self.ocr_status, self.ocr_text.as_deref(), zh) {
+            (OcrStatus::Recognized, Some(text), _) => prompt_budget::fit_ocr_text(text, ocr_tokens),
+            (OcrStatus::Disabled, _, true) => "未读取（OCR已关闭），请仅根据应用程序和窗口标题判断".to_string(),
+            (OcrStatus::Disabled, _, false) => "Not read (OCR is off); judge from the application and window title only".to_string(),
+            (OcrStatus::NoText, _, true) => "OCR未识别到文字，请主要根据应用程序和窗口标题判断".to_string(),
+            (OcrStatus::NoText, _, false) => "OCR found no text; judge mainly from the application and window title".to_string(),
+            (_, _, true) => "不可用（屏幕文字识别失败），请仅根据应用程序和窗口标题判断，信息不足时降低置信度".to_string(),
+            (_, _, false) => "Unavailable (screen text recognition failed); judge from the application and window title only, and lower your confidence when information is insufficient".to_string(),
+        };
+        if let Some(ref features) = self.image_features {
+            let label = if zh { "图像统计特征（非屏幕文字，仅供参考）" } else { "Image statistics (not screen text, for reference only)" };
+            description.push_str(&format!("\n- {}: {}", label, features.describe(language)));
+        }
+        description
+    }
+    
+    /// 去掉截图后的内容，用于视觉模型调用失败时回退到OCR文本
+    pub fn without_vision(&self) -> Self {
+        Self {
+            vision_image: None,
+            ..self.clone()
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -226,6 +783,9 @@ impl MonitorService {
                 println!("✅ 第 {} 次监控检查成功完成", loop_count);
             }
 
+            // 本地分类器按 retrain_hours 在后台重新训练，不阻塞监控检查
+            LocalClassifier::schedule_retrain(&config_snapshot.local_classifier);
+
             // 计算并显示本次迭代耗时
             let iteration_duration = iteration_start.elapsed();
             println!("⏱️ 第 {} 次迭代总耗时: {:?}", loop_count, iteration_duration);
@@ -262,33 +822,50 @@ impl MonitorService {
         // 1. 获取当前活动应用信息
         println!("📱 步骤1: 获取当前应用信息");
         let app_start = Instant::now();
-        let (app_name, window_title) = Self::get_current_application_info().await?;
+        let raw_foreground = Self::get_foreground_window().await;
+        // 窗口标题在发送给AI和写入磁盘之前先脱敏
+        let redactor = Redactor::new(&config.redaction);
+        let mut redactions = RedactionCounts::default();
+        let foreground = redactor.redact_window(&raw_foreground, &mut redactions);
+        let app_name = foreground.app_name.clone();
+        let window_title = foreground.window_title.clone();
         println!("⏱️ 应用信息获取耗时: {:?}", app_start.elapsed());
         println!("📋 当前应用: {:?}", app_name);
-        println!("🪟 窗口标题: {:?}", window_title);
         
-        // 2. 截取屏幕并进行OCR
-        println!("📸 步骤2: 屏幕截图和OCR识别");
-        let ocr_start = Instant::now();
-        let ocr_text = Self::capture_screen_and_ocr().await?;
-        println!("⏱️ 截图+OCR总耗时: {:?}", ocr_start.elapsed());
+        // 隐私排除检查先于输出窗口标题，命中时标题不出现在日志中
+        let never_capture_rule = config.never_capture.matched_rule(&raw_foreground);
+        match never_capture_rule {
+            Some(_) => println!("🪟 窗口标题: <private>"),
+            None => println!("🪟 窗口标题: {:?}", window_title),
+        }
         
-        // 3. 调用AI进行专注判断
-        println!("🤖 步骤3: AI专注状态分析");
-        let ai_start = Instant::now();
-        let ai_result = Self::analyze_focus_with_ai(
-            config,
-            &app_name,
-            &window_title,
-            &ocr_text,
-        ).await?;
-        println!("⏱️ AI分析耗时: {:?}", ai_start.elapsed());
+        // 专注规则按脱敏前的窗口信息匹配，命中时不截图也不调用AI
+        let rules = RuleEngine::for_config(config);
+        let ai_result = match never_capture_rule {
+            Some(rule) => {
+                println!("🔒 前台窗口命中隐私排除规则（{}），跳过截图、OCR和AI分析", rule);
+                MonitoringResult::private(&foreground)
+            }
+            None => match rules.evaluate(&raw_foreground) {
+                Some(rule) => {
+                    println!("📏 命中专注规则 {}（{}），跳过截图和AI分析: {:?}", rule.rule_id, rule.description, rule.verdict);
+                    let mut result = MonitoringResult::rule_matched(&foreground, &rule);
+                    result.redactions = redactions;
+                    let current_task = Self::get_current_task_name().await.ok();
+                    Self::check_distraction_intervention(&result.focus_state, &result, current_task.as_deref()).await;
+                    result
+                }
+                None => {
+                    Self::capture_and_analyze(config, &foreground, &redactor, redactions, &rules, last_result).await?
+                }
+            },
+        };
         println!("🎯 AI分析结果: {:?} (置信度: {:.2})", ai_result.focus_state, ai_result.confidence);
 
         // 4. 更新当前活动状态
         let activity = CurrentActivity {
             application_name: app_name.clone(),
-            window_title: window_title.clone(),
+            window_title: ai_result.window_title.clone(),
             is_productive: Some(matches!(ai_result.focus_state, FocusState::Focused)),
             timestamp: Utc::now(),
         };
@@ -334,6 +911,157 @@ impl MonitorService {
         Ok(())
     }
 
+    /// 截图、OCR并调用AI判断专注状态（画面未变化时沿用上次结果）
+    async fn capture_and_analyze(
+        config: &MonitoringConfig,
+        foreground: &ForegroundWindow,
+        redactor: &Redactor,
+        mut redactions: RedactionCounts,
+        rules: &RuleEngine,
+        last_result: &Arc<Mutex<Option<MonitoringResult>>>,
+    ) -> Result<MonitoringResult> {
+        use std::time::Instant;
+
+        // 只在需要时取用内存中的本地模型：兜底模式下只有AI不可用时才会调用
+        let predict_locally = |ocr_text: Option<&str>| {
+            LocalClassifier::predict_current(
+                &config.local_classifier,
+                foreground.app_name.as_deref(),
+                foreground.window_title.as_deref(),
+                ocr_text,
+            )
+        };
+
+        // 超出AI预算时改用本地模型，没有本地模型时只按应用规则判断
+        let budget_config;
+        let (config, budget_mode) = match UsageService::default().detection_budget_mode(&config.ai_config) {
+            BudgetMode::Normal => (config, None),
+            BudgetMode::LocalOnly(ai_config) => {
+                println!("💰 已超出AI预算，改用本地模型: {}", ai_config.detection_model);
+                budget_config = MonitoringConfig { ai_config: *ai_config, ..config.clone() };
+                (&budget_config, Some(BudgetFallback::LocalModel))
+            }
+            BudgetMode::RuleOnly(reason) => {
+                println!("💰 {}，跳过截图和AI分析，仅按应用规则判断", reason);
+                let mut result = MonitoringResult::rule_only(config, foreground, &reason);
+                if let (FocusState::Unknown, Some(prediction)) = (&result.focus_state, predict_locally(None)) {
+                    println!("🧮 应用规则无法判断，改用本地分类器: {:?}", prediction.state);
+                    result = prediction.apply(result, &reason);
+                }
+                result.redactions = redactions;
+                return Ok(result);
+            }
+        };
+
+        // AI服务熔断期间不截图也不调用AI，直接记录为不可用
+        if let Some(circuit) = AIService::new(config.ai_config.clone()).unavailable_status("detection") {
+            println!("🔌 AI服务暂不可用，跳过截图和AI分析: {}", circuit.last_error.as_deref().unwrap_or(""));
+            let mut result = MonitoringResult::ai_unavailable(foreground, circuit.unavailable_message());
+            if let Some(prediction) = predict_locally(None) {
+                println!("🧮 AI服务不可用，改用本地分类器: {:?}", prediction.state);
+                result = prediction.apply(result, "AI服务暂不可用");
+            }
+            result.redactions = redactions;
+            return Ok(result);
+        }
+
+        // 2. 截取屏幕并进行OCR
+        println!("📸 步骤2: 屏幕截图和OCR识别");
+        let ocr_start = Instant::now();
+        let previous_result = last_result.lock().await.clone();
+        let mut screen = Self::capture_screen_and_ocr(config, foreground, previous_result.as_ref()).await?;
+        screen.ocr_text = redactor.redact_option(&screen.ocr_text, &mut redactions);
+        println!("⏱️ 截图+OCR总耗时: {:?}", ocr_start.elapsed());
+        Self::log_redactions(config, &screen, &redactions);
+
+        // 窗口标题中没有网址时，按屏幕文字（浏览器地址栏）中的域名匹配规则
+        if let Some(rule) = screen.ocr_text.as_deref().and_then(|text| rules.evaluate_screen_text(text)) {
+            println!("📏 屏幕文字命中专注规则 {}（{}），跳过AI分析: {:?}", rule.rule_id, rule.description, rule.verdict);
+            let result = MonitoringResult {
+                ocr_text: screen.ocr_text.clone(),
+                ocr_status: screen.ocr_status,
+                image_features: screen.image_features.clone(),
+                captured_display: screen.captured_display,
+                frame_hash: screen.frame_hash.map(|hash| format!("{:016x}", hash)),
+                redactions,
+                ..MonitoringResult::rule_matched(foreground, &rule)
+            };
+            let current_task = Self::get_current_task_name().await.ok();
+            Self::check_distraction_intervention(&result.focus_state, &result, current_task.as_deref()).await;
+            return Ok(result);
+        }
+        
+        // 3. 调用AI进行专注判断（画面未变化时沿用上次结果）
+        let ai_result = match previous_result {
+            Some(previous) if screen.unchanged && !previous.ai_unavailable => {
+                println!("♻️ 步骤3: 画面未变化，沿用上次判断结果，跳过AI调用");
+                MonitoringResult::carried_over(previous, foreground, &screen, redactions, budget_mode)
+            }
+            _ => {
+                let local_prediction = match config.local_classifier.mode {
+                    LocalClassifierMode::FirstStage => predict_locally(screen.ocr_text.as_deref()),
+                    _ => None,
+                };
+                let mut ai_result = match local_prediction {
+                    Some(prediction) if prediction.confidence >= config.local_classifier.confidence_threshold => {
+                        println!("🧮 步骤3: 本地分类器判断为 {:?}（置信度 {:.2}），跳过AI调用", prediction.state, prediction.confidence);
+                        let result = prediction.apply(
+                            MonitoringResult {
+                                ocr_text: screen.ocr_text.clone(),
+                                ocr_status: screen.ocr_status,
+                                image_features: screen.image_features.clone(),
+                                ..MonitoringResult::skipped(foreground.app_name.clone(), foreground.window_title.clone(), "")
+                            },
+                            "置信度达到阈值",
+                        );
+                        let current_task = Self::get_current_task_name().await.ok();
+                        Self::check_distraction_intervention(&result.focus_state, &result, current_task.as_deref()).await;
+                        result
+                    }
+                    _ => {
+                        println!("🤖 步骤3: AI专注状态分析");
+                        let ai_start = Instant::now();
+                        let result = Self::analyze_focus_with_ai(
+                            config,
+                            &foreground.app_name,
+                            &foreground.window_title,
+                            &screen,
+                        ).await?;
+                        println!("⏱️ AI分析耗时: {:?}", ai_start.elapsed());
+                        let fallback = if result.ai_unavailable {
+                            local_prediction.or_else(|| predict_locally(screen.ocr_text.as_deref()))
+                        } else {
+                            None
+                        };
+                        match fallback {
+                            Some(prediction) => {
+                                println!("🧮 AI服务不可用，改用本地分类器: {:?}", prediction.state);
+                                prediction.apply(result, "AI服务不可用")
+                            }
+                            None => result,
+                        }
+                    }
+                };
+                ai_result.captured_display = screen.captured_display;
+                ai_result.frame_hash = screen.frame_hash.map(|hash| format!("{:016x}", hash));
+                ai_result.redactions = redactions;
+                ai_result.budget_mode = budget_mode;
+                ai_result
+            }
+        };
+        Ok(ai_result)
+    }
+
+    /// 输出脱敏统计（只记录数量，不输出原文）
+    pub fn log_redactions(config: &MonitoringConfig, screen: &ScreenContent, redactions: &RedactionCounts) {
+        if redactions.total() > 0 {
+            println!("🔒 已脱敏 {} 处敏感信息: {:?}", redactions.total(), redactions);
+        }
+        if config.redaction.enabled && screen.vision_image.is_some() {
+            println!("⚠️ 已允许发送未脱敏截图，图像中的敏感信息不会被隐藏");
+        }
+    }
+
     /// 保存监控结果到存储服务
     async fn save_monitoring_result(result: &MonitoringResult) -> Result<()> {
         // 获取应用数据目录
@@ -361,119 +1089,155 @@ impl MonitorService {
 
     /// 获取当前活动应用程序和窗口信息
     pub async fn get_current_application_info() -> Result<(Option<String>, Option<String>)> {
+        let window = Self::get_foreground_window().await;
+        Ok((window.app_name, window.window_title))
+    }
+
+    /// 获取前台窗口的完整信息（应用、标题、位置）
+    pub async fn get_foreground_window() -> ForegroundWindow {
         use std::time::Instant;
         
         println!("📱 获取当前活动应用信息...");
         
         let app_info_start = Instant::now();
         
-        #[cfg(windows)]
-        {
-            tokio::task::spawn_blocking(move || {
-                unsafe {
-                    use winapi::um::winuser::{GetForegroundWindow, GetWindowTextW, GetWindowThreadProcessId};
-                    use winapi::um::processthreadsapi::OpenProcess;
-                    use winapi::um::psapi::GetModuleBaseNameW;
-                    use winapi::um::winnt::{PROCESS_QUERY_INFORMATION, PROCESS_VM_READ};
-                    use winapi::um::handleapi::CloseHandle;
-                    
-                    let hwnd = GetForegroundWindow();
-                    if hwnd.is_null() {
-                        println!("⚠️ 无法获取前台窗口");
-                        return Ok((None, None));
-                    }
-                    
-                    // 获取窗口标题
-                    let mut window_title = vec![0u16; 256];
-                    let title_len = GetWindowTextW(hwnd, window_title.as_mut_ptr(), 256);
-                    let window_title_str = if title_len > 0 {
-                        let title = String::from_utf16_lossy(&window_title[..title_len as usize]);
-                        println!("🪟 窗口标题: {}", title);
-                        Some(title)
-                    } else {
-                        println!("⚠️ 无法获取窗口标题");
-                        None
-                    };
-                    
-                    // 获取进程ID
-                    let mut process_id = 0u32;
-                    GetWindowThreadProcessId(hwnd, &mut process_id);
-                    
-                    if process_id == 0 {
-                        println!("⚠️ 无法获取进程ID");
-                        return Ok((None, window_title_str));
-                    }
-                    
-                    // 打开进程
-                    let process_handle = OpenProcess(
-                        PROCESS_QUERY_INFORMATION | PROCESS_VM_READ,
-                        0,
-                        process_id
-                    );
-                    
-                    if process_handle.is_null() {
-                        println!("⚠️ 无法打开进程 (PID: {})", process_id);
-                        return Ok((None, window_title_str));
-                    }
-                    
-                    // 获取进程名称
-                    let mut process_name = vec![0u16; 256];
-                    let name_len = GetModuleBaseNameW(
-                        process_handle,
-                        std::ptr::null_mut(),
-                        process_name.as_mut_ptr(),
-                        256
-                    );
-                    
-                    CloseHandle(process_handle);
-                    
-                    let app_name = if name_len > 0 {
-                        let name = String::from_utf16_lossy(&process_name[..name_len as usize]);
-                        println!("📋 应用程序: {} (PID: {})", name, process_id);
-                        Some(name)
-                    } else {
-                        println!("⚠️ 无法获取进程名称");
-                        None
-                    };
-                    
-                    let app_info_duration = app_info_start.elapsed();
-                    println!("⏱️ 应用信息获取耗时: {:?}", app_info_duration);
-                    
-                    Ok((app_name, window_title_str))
-                }
-            }).await?
-        }
-        
-        #[cfg(not(windows))]
-        {
-            println!("⚠️ 非Windows系统，返回模拟应用信息");
-            Ok((Some("测试应用".to_string()), Some("测试窗口".to_string())))
+        // 平台相关的提供者可能持有非线程安全的句柄，在阻塞线程内创建并使用
+        let window = tokio::task::spawn_blocking(|| {
+            let provider = window_info_service::platform_provider();
+            println!("🔌 窗口信息提供者: {}", provider.name());
+            provider.foreground_window()
+        }).await;
+        
+        let app_info_duration = app_info_start.elapsed();
+        println!("⏱️ 应用信息获取耗时: {:?}", app_info_duration);
+        
+        match window {
+            Ok(Ok(window)) => window,
+            Ok(Err(e)) => {
+                println!("⚠️ 获取前台窗口信息失败: {}", e);
+                ForegroundWindow::default()
+            }
+            Err(e) => {
+                println!("❌ 窗口信息任务执行失败: {}", e);
+                ForegroundWindow::default()
+            }
         }
     }
 
     /// 截取屏幕并进行OCR识别  
-    pub async fn capture_screen_and_ocr() -> Result<Option<String>> {
+    ///
+    /// 传入上次的监控结果时，若画面感知哈希和应用/窗口标题都未变化，则跳过OCR并标记为 `unchanged`。
+    pub async fn capture_screen_and_ocr(
+        config: &MonitoringConfig,
+        window: &ForegroundWindow,
+        previous: Option<&MonitoringResult>,
+    ) -> Result<ScreenContent> {
         use std::time::Instant;
         
         let capture_start = Instant::now();
         println!("📸 开始屏幕截图和OCR识别...");
         
         // 截取屏幕
-        match Self::capture_screenshot_sync() {
-            Ok(Some(image_data)) => {
+        match Self::capture_screenshot_sync(config.capture_mode, window.bounds.as_ref()) {
+            Ok(Some((image_data, captured_display, frame_hash))) => {
                 println!("✅ 屏幕截图成功，图像大小: {} KB", image_data.len() / 1024);
+                println!("🔑 画面感知哈希: {:016x}", frame_hash);
+                
+                if config.skip_unchanged_frames {
+                    if let Some(previous) = previous {
+                        if Self::is_screen_unchanged(config, window, frame_hash, previous) {
+                            println!("♻️ 画面与窗口均未变化，跳过OCR识别");
+                            return Ok(ScreenContent {
+                                ocr_text: previous.ocr_text.clone(),
+                                ocr_status: previous.ocr_status,
+                                image_features: previous.image_features.clone(),
+                                vision_image: None,
+                                captured_display: Some(captured_display),
+                                frame_hash: Some(frame_hash),
+                                unchanged: true,
+                            });
+                        }
+                    }
+                }
+                
+                // 裁剪到前台窗口区域，排除任务栏和背景窗口的干扰
+                let crop = if config.crop_to_active_window {
+                    window.bounds.as_ref().and_then(|bounds| OcrCrop::from_window(bounds, &captured_display))
+                } else {
+                    None
+                };
+                match crop {
+                    Some(crop) => println!("✂️ OCR区域裁剪到前台窗口: {:?}", crop),
+                    None => println!("🖥️ OCR识别整个截图区域"),
+                }
                 
                 // 执行OCR
-                let ocr_result = Self::perform_ocr(&image_data).await?;
+                let (ocr_status, ocr_text) = Self::perform_ocr(
+                    &image_data,
+                    crop,
+                    config.ocr_engine,
+                    config.ocr_options(),
+                    config.tesseract_settings(),
+                ).await?;
+                
+                // 视觉模式：准备缩小后的截图，与OCR文本一起发送
+                let vision_image = if Self::vision_enabled(config) {
+                    let image_data = image_data.clone();
+                    let max_dimension = config.vision_max_dimension;
+                    match tokio::task::spawn_blocking(move || {
+                        Self::encode_vision_image(&image_data, crop, max_dimension)
+                    }).await? {
+                        Ok(image) => Some(image),
+                        Err(e) => {
+                            println!("⚠️ 准备视觉模型截图失败，仅使用OCR文本: {}", e);
+                            None
+                        }
+                    }
+                } else {
+                    None
+                };
+                
+                // OCR不可用时计算图像统计特征，作为如实标注的补充信息
+                let image_features = if ocr_status.is_degraded() && vision_image.is_none() && config.degraded_image_features {
+                    let previous_hash = previous
+                        .and_then(|p| p.frame_hash.as_deref())
+                        .and_then(|h| u64::from_str_radix(h, 16).ok());
+                    let image_data = image_data.clone();
+                    match tokio::task::spawn_blocking(move || {
+                        ImageFeatures::from_image_data(&image_data, frame_hash, previous_hash)
+                    }).await? {
+                        Ok(features) => {
+                            println!("🎨 图像统计特征: {}", features.describe(PromptLanguage::Zh));
+                            Some(features)
+                        }
+                        Err(e) => {
+                            println!("⚠️ 计算图像特征失败: {}", e);
+                            None
+                        }
+                    }
+                } else {
+                    None
+                };
                 
                 let total_duration = capture_start.elapsed();
                 println!("⏱️ 截图+OCR总耗时: {:?}", total_duration);
                 
-                Ok(ocr_result)
+                Ok(ScreenContent {
+                    ocr_text,
+                    ocr_status,
+                    image_features,
+                    vision_image,
+                    captured_display: Some(captured_display),
+                    frame_hash: Some(frame_hash),
+                    unchanged: false,
+                })
             }
             Ok(None) => {
                 println!("⚠️ 屏幕截图失败");
-        Ok(None)
+                Ok(ScreenContent {
+                    ocr_status: OcrStatus::Unavailable,
+                    ..Default::default()
+                })
             }
             Err(e) => {
                 println!("❌ 屏幕截图错误: {}", e);
@@ -482,296 +1246,346 @@ impl MonitorService {
         }
     }
 
+    /// 判断当前画面和窗口是否与上次结果相同
+    fn is_screen_unchanged(
+        config: &MonitoringConfig,
+        window: &ForegroundWindow,
+        frame_hash: u64,
+        previous: &MonitoringResult,
+    ) -> bool {
+        if window.app_name != previous.application_name || window.window_title != previous.window_title {
+            println!("🔄 应用或窗口标题已变化");
+            return false;
+        }
+        
+        // 截图模式变化时哈希不可比较
+        if previous.captured_display.as_ref().map(|d| d.mode) != Some(config.capture_mode) {
+            return false;
+        }
+        
+        let previous_hash = match previous.frame_hash.as_deref().and_then(|h| u64::from_str_radix(h, 16).ok()) {
+            Some(hash) => hash,
+            None => return false,
+        };
+        
+        let distance = (frame_hash ^ previous_hash).count_ones();
+        println!("📏 与上次画面的哈希距离: {} (阈值: {})", distance, config.frame_change_threshold);
+        
+        distance <= config.frame_change_threshold
+    }
+
+    /// 是否启用视觉模式（需要检测模型被标记为支持图像输入）
+    ///
+    /// 截图中的文字无法脱敏，开启脱敏时除非用户明确允许发送未脱敏截图，否则改用脱敏后的OCR文本。
+    fn vision_enabled(config: &MonitoringConfig) -> bool {
+        if !config.vision_mode {
+            return false;
+        }
+        if config.redaction.enabled && !config.redaction.allow_unredacted_images {
+            println!("🔒 已开启脱敏且未允许发送未脱敏截图，视觉模式改用OCR文本");
+            return false;
+        }
+        let model = &config.ai_config.detection_model;
+        if config.ai_config.model_supports_vision(model) {
+            true
+        } else {
+            println!("⚠️ 已开启视觉模式，但模型 {} 未标记为支持图像输入，仅使用OCR文本", model);
+            false
+        }
+    }
+
+    /// 把截图裁剪并缩小为适合发送给视觉模型的JPEG
+    fn encode_vision_image(image_data: &[u8], crop: Option<OcrCrop>, max_dimension: u32) -> Result<Vec<u8>> {
+        use image::ImageOutputFormat;
+        use std::io::Cursor;
+        
+        let img = image::load_from_memory(image_data)
+            .map_err(|e| anyhow::anyhow!("图像解码失败: {}", e))?;
+        let img = match crop {
+            Some(crop) => {
+                let (x, y, width, height) = crop.to_pixels(img.width(), img.height());
+                img.crop_imm(x, y, width, height)
+            }
+            None => img,
+        };
+        
+        let max_dimension = max_dimension.max(256);
+        let img = if img.width() > max_dimension || img.height() > max_dimension {
+            img.resize(max_dimension, max_dimension, image::imageops::FilterType::Triangle)
+        } else {
+            img
+        };
+        
+        let mut cursor = Cursor::new(Vec::new());
+        img.to_rgb8().write_to(&mut cursor, ImageOutputFormat::Jpeg(75))
+            .map_err(|e| anyhow::anyhow!("编码视觉模型截图失败: {}", e))?;
+        let jpeg = cursor.into_inner();
+        println!("🖼️ 视觉模型截图: {}x{}, {} KB", img.width(), img.height(), jpeg.len() / 1024);
+        Ok(jpeg)
+    }
+
     /// 同步截图函数
-    fn capture_screenshot_sync() -> Result<Option<Vec<u8>>> {
+    fn capture_screenshot_sync(
+        mode: CaptureMode,
+        window_bounds: Option<&WindowBounds>,
+    ) -> Result<Option<(Vec<u8>, CapturedDisplay, u64)>> {
         use screenshots::Screen;
-        use image::{ImageOutputFormat, DynamicImage, RgbaImage};
+        use image::{ImageOutputFormat, DynamicImage};
         use std::io::Cursor;
 
-        println!("📸 开始屏幕截图...");
+        println!("📸 开始屏幕截图 (模式: {:?})...", mode);
         
         let screens = Screen::all()?;
         println!("🖥️ 检测到 {} 个屏幕", screens.len());
         
-        if let Some(screen) = screens.first() {
-            println!("📐 主屏幕分辨率: {}x{}", screen.display_info.width, screen.display_info.height);
-            
-            let screenshot_start = std::time::Instant::now();
-            let screenshot_image = screen.capture()?;
-            let screenshot_duration = screenshot_start.elapsed();
-            
-            println!("⏱️ 截图耗时: {:?}", screenshot_duration);
-            println!("🖼️ 截图尺寸: {}x{}", screenshot_image.width(), screenshot_image.height());
-            
-            // 将screenshots::Image转换为image::RgbaImage
-            let width = screenshot_image.width() as u32;
-            let height = screenshot_image.height() as u32;
-            let raw_data = screenshot_image.rgba();
-            
-            let rgba_image = RgbaImage::from_raw(width, height, raw_data.to_vec())
-                .ok_or_else(|| anyhow::anyhow!("无法创建RGBA图像"))?;
-            
-            let dynamic_image = DynamicImage::ImageRgba8(rgba_image);
-            
-            // 压缩为JPEG格式以减少数据量
-            let mut cursor = Cursor::new(Vec::new());
-            let compression_start = std::time::Instant::now();
-            dynamic_image.write_to(&mut cursor, ImageOutputFormat::Jpeg(85))?;
-            let compression_duration = compression_start.elapsed();
-            
-            let compressed_data = cursor.into_inner();
-            println!("⏱️ JPEG压缩耗时: {:?}", compression_duration);
-            println!("📊 压缩后大小: {} KB (压缩率: {:.1}%)", 
-                compressed_data.len() / 1024,
-                (compressed_data.len() as f64 / (width * height * 4) as f64) * 100.0
-            );
-            
-            Ok(Some(compressed_data))
-        } else {
+        if screens.is_empty() {
             println!("❌ 未检测到可用屏幕");
-        Ok(None)
+            return Ok(None);
+        }
+        
+        let screenshot_start = std::time::Instant::now();
+        let (rgba_image, captured_display) = match mode {
+            CaptureMode::AllScreens => Self::capture_all_screens(&screens)?,
+            CaptureMode::PrimaryOnly => Self::capture_single_screen(Self::primary_screen(&screens), mode)?,
+            CaptureMode::ForegroundScreen => {
+                // 找到前台窗口中心所在的屏幕，找不到时回退到主屏幕
+                let screen = window_bounds
+                    .map(|bounds| bounds.center())
+                    .and_then(|(cx, cy)| screens.iter().find(|s| {
+                        let info = &s.display_info;
+                        cx >= info.x && cx < info.x + info.width as i32
+                            && cy >= info.y && cy < info.y + info.height as i32
+                    }))
+                    .unwrap_or_else(|| {
+                        println!("⚠️ 无法确定前台窗口所在屏幕，使用主屏幕");
+                        Self::primary_screen(&screens)
+                    });
+                Self::capture_single_screen(screen, mode)?
+            }
+        };
+        let screenshot_duration = screenshot_start.elapsed();
+        
+        let width = rgba_image.width();
+        let height = rgba_image.height();
+        println!("⏱️ 截图耗时: {:?}", screenshot_duration);
+        println!("🖼️ 截图尺寸: {}x{} (屏幕: {:?})", width, height, captured_display.display_ids);
+        
+        let dynamic_image = DynamicImage::ImageRgba8(rgba_image);
+        let frame_hash = Self::compute_frame_hash(&dynamic_image);
+        
+        // 压缩为JPEG格式以减少数据量
+        let mut cursor = Cursor::new(Vec::new());
+        let compression_start = std::time::Instant::now();
+        dynamic_image.write_to(&mut cursor, ImageOutputFormat::Jpeg(85))?;
+        let compression_duration = compression_start.elapsed();
+        
+        let compressed_data = cursor.into_inner();
+        println!("⏱️ JPEG压缩耗时: {:?}", compression_duration);
+        println!("📊 压缩后大小: {} KB (压缩率: {:.1}%)", 
+            compressed_data.len() / 1024,
+            (compressed_data.len() as f64 / (width * height * 4) as f64) * 100.0
+        );
+        
+        Ok(Some((compressed_data, captured_display, frame_hash)))
+    }
+
+    /// 计算画面的差值感知哈希 (dHash)
+    ///
+    /// 缩放为9x8灰度图，比较相邻像素亮度得到64位指纹；
+    /// 光标闪烁、时钟跳动等细小变化只会改变少数几位。
+    fn compute_frame_hash(image: &image::DynamicImage) -> u64 {
+        let thumbnail = image.thumbnail_exact(9, 8).to_luma8();
+        let mut hash = 0u64;
+        for y in 0..8 {
+            for x in 0..8 {
+                let left = thumbnail.get_pixel(x, y)[0];
+                let right = thumbnail.get_pixel(x + 1, y)[0];
+                hash = (hash << 1) | u64::from(left > right);
+            }
+        }
+        hash
+    }
+
+    /// 选择主屏幕（没有标记主屏幕时使用第一个）
+    fn primary_screen(screens: &[screenshots::Screen]) -> &screenshots::Screen {
+        screens
+            .iter()
+            .find(|s| s.display_info.is_primary)
+            .unwrap_or(&screens[0])
+    }
+
+    /// 截取单个屏幕
+    fn capture_single_screen(
+        screen: &screenshots::Screen,
+        mode: CaptureMode,
+    ) -> Result<(image::RgbaImage, CapturedDisplay)> {
+        let info = &screen.display_info;
+        println!("📐 截取屏幕 {}: {}x{} @ ({}, {})", info.id, info.width, info.height, info.x, info.y);
+        
+        let rgba_image = Self::screenshot_to_rgba(screen.capture()?)?;
+        
+        Ok((rgba_image, CapturedDisplay {
+            mode,
+            display_ids: vec![info.id],
+            x: info.x,
+            y: info.y,
+            width: info.width,
+            height: info.height,
+        }))
+    }
+
+    /// 截取所有屏幕并按虚拟桌面布局拼接
+    fn capture_all_screens(screens: &[screenshots::Screen]) -> Result<(image::RgbaImage, CapturedDisplay)> {
+        let min_x = screens.iter().map(|s| s.display_info.x).min().unwrap_or(0);
+        let min_y = screens.iter().map(|s| s.display_info.y).min().unwrap_or(0);
+        let max_x = screens.iter().map(|s| s.display_info.x + s.display_info.width as i32).max().unwrap_or(0);
+        let max_y = screens.iter().map(|s| s.display_info.y + s.display_info.height as i32).max().unwrap_or(0);
+        
+        let mut captures = Vec::with_capacity(screens.len());
+        for screen in screens {
+            let info = &screen.display_info;
+            println!("📐 截取屏幕 {}: {}x{} @ ({}, {})", info.id, info.width, info.height, info.x, info.y);
+            captures.push((info, Self::screenshot_to_rgba(screen.capture()?)?));
+        }
+        
+        // 截图为物理像素，布局为逻辑坐标；按最大缩放比例放置各屏幕
+        let scale = captures
+            .iter()
+            .map(|(info, img)| img.width() as f64 / info.width.max(1) as f64)
+            .fold(1.0_f64, f64::max);
+        let canvas_width = ((max_x - min_x) as f64 * scale).round() as u32;
+        let canvas_height = ((max_y - min_y) as f64 * scale).round() as u32;
+        println!("🧩 拼接 {} 个屏幕: {}x{}", captures.len(), canvas_width, canvas_height);
+        
+        let mut canvas = image::RgbaImage::new(canvas_width, canvas_height);
+        for (info, img) in &captures {
+            let offset_x = ((info.x - min_x) as f64 * scale).round() as i64;
+            let offset_y = ((info.y - min_y) as f64 * scale).round() as i64;
+            image::imageops::overlay(&mut canvas, img, offset_x, offset_y);
         }
+        
+        Ok((canvas, CapturedDisplay {
+            mode: CaptureMode::AllScreens,
+            display_ids: captures.iter().map(|(info, _)| info.id).collect(),
+            x: min_x,
+            y: min_y,
+            width: (max_x - min_x) as u32,
+            height: (max_y - min_y) as u32,
+        }))
+    }
+
+    /// 将screenshots::Image转换为image::RgbaImage
+    fn screenshot_to_rgba(screenshot_image: screenshots::Image) -> Result<image::RgbaImage> {
+        let width = screenshot_image.width();
+        let height = screenshot_image.height();
+        let raw_data = screenshot_image.rgba();
+        
+        image::RgbaImage::from_raw(width, height, raw_data.to_vec())
+            .ok_or_else(|| anyhow::anyhow!("无法创建RGBA图像"))
     }
 
-    /// 便携式Tesseract OCR识别 (命令行版本)
-    async fn perform_ocr(image_data: &[u8]) -> Result<Option<String>> {
+    /// OCR识别 (按配置选择引擎)
+    ///
+    /// 识别失败时不会编造内容，而是返回 `OcrStatus::Unavailable`，由调用方切换到仅依据标题的判断。
+    async fn perform_ocr(
+        image_data: &[u8],
+        crop: Option<OcrCrop>,
+        engine_kind: OcrEngineKind,
+        options: OcrOptions,
+        settings: TesseractSettings,
+    ) -> Result<(OcrStatus, Option<String>)> {
         let ocr_start = std::time::Instant::now();
         
-        println!("🔍 开始便携式Tesseract OCR识别...");
+        if engine_kind == OcrEngineKind::Disabled {
+            println!("🚫 OCR已禁用，仅使用应用名称和窗口标题");
+            return Ok((OcrStatus::Disabled, None));
+        }
+        
+        println!("🔍 开始OCR识别...");
         println!("📊 图像数据大小: {} KB", image_data.len() / 1024);
         
         tokio::task::spawn_blocking({
             let image_data = image_data.to_vec();
             move || {
-                // 使用命令行方式调用便携式Tesseract
-                match Self::perform_command_line_ocr(&image_data) {
+                match Self::recognize_screen_text(&image_data, crop, engine_kind, &options, &settings) {
                     Ok(Some(text)) => {
                         let ocr_duration = ocr_start.elapsed();
                         println!("⏱️ OCR识别耗时: {:?}", ocr_duration);
                         println!("✅ OCR识别成功，文本长度: {} 字符", text.len());
                         
-                        if text.len() > 200 {
-                            println!("📖 文本预览: {}...", &text[..200]);
+                        if text.chars().count() > 200 {
+                            println!("📖 文本预览: {}...", prompt_budget::truncate_chars(&text, 200));
                         } else if !text.is_empty() {
                             println!("📖 识别文本: {}", text);
                         }
                         
-                        Ok(Some(text))
+                        Ok((OcrStatus::Recognized, Some(text)))
                     }
                     Ok(None) => {
-                        println!("⚠️ OCR识别结果为空，使用智能分析");
-                        Ok(Self::smart_image_analysis(&image_data))
+                        println!("⚠️ OCR识别结果为空");
+                        Ok((OcrStatus::NoText, None))
                     }
                     Err(e) => {
                         println!("❌ OCR识别失败: {}", e);
-                        println!("🔄 回退到智能图像分析");
-                        Ok(Self::smart_image_analysis(&image_data))
+                        println!("🔄 进入降级模式：仅依据应用名称和窗口标题判断");
+                        Ok((OcrStatus::Unavailable, None))
                     }
                 }
             }
         }).await?
     }
 
-    /// 便携式Tesseract命令行OCR实现
-    fn perform_command_line_ocr(image_data: &[u8]) -> Result<Option<String>> {
-        use std::env;
-        use std::process::Command;
-        
-        println!("🔧 启动便携式Tesseract命令行OCR...");
-        
-        // 1. 创建临时文件
-        let temp_dir = env::temp_dir();
-        let temp_image = temp_dir.join("my_focus_ocr_input.png");
-        let temp_output = temp_dir.join("my_focus_ocr_output");
-        
-        println!("📁 临时文件路径:");
-        println!("   输入: {}", temp_image.display());
-        println!("   输出: {}", temp_output.display());
+    /// 预处理截图并交给OCR引擎识别
+    fn recognize_screen_text(
+        image_data: &[u8],
+        crop: Option<OcrCrop>,
+        engine_kind: OcrEngineKind,
+        options: &OcrOptions,
+        settings: &TesseractSettings,
+    ) -> Result<Option<String>> {
+        use image::{DynamicImage, ImageOutputFormat};
+        use std::io::Cursor;
         
-        // 2. 保存图像文件
-        println!("🖼️ 保存图像到临时文件...");
+        // 1. 解码、裁剪并转换为灰度PNG
+        println!("🖼️ 准备OCR输入图像...");
         let img = image::load_from_memory(image_data)
             .map_err(|e| anyhow::anyhow!("图像解码失败: {}", e))?;
         
-        let gray = img.to_luma8();
-        gray.save(&temp_image)
-            .map_err(|e| anyhow::anyhow!("保存临时图像失败: {}", e))?;
-        
-        println!("✅ 图像已保存: {}x{}", img.width(), img.height());
-        
-        // 3. 查找便携式Tesseract
-        let tesseract_exe = Self::find_portable_tesseract()?;
-        println!("📍 使用Tesseract: {}", tesseract_exe);
-        
-        // 4. 执行OCR识别 (先尝试中英文，失败则用英文)
-        let result = Self::execute_tesseract_command(&tesseract_exe, &temp_image, &temp_output, "chi_sim+eng")
-            .or_else(|e| {
-                println!("⚠️ 中英文识别失败: {}", e);
-                println!("🔄 尝试仅英文识别...");
-                Self::execute_tesseract_command(&tesseract_exe, &temp_image, &temp_output, "eng")
-            });
-        
-        // 5. 清理临时文件
-        let _ = std::fs::remove_file(&temp_image);
-        let _ = std::fs::remove_file(format!("{}.txt", temp_output.to_string_lossy()));
-        
-        result
-    }
-    
-    /// 查找便携式Tesseract可执行文件
-    fn find_portable_tesseract() -> Result<String> {
-        use std::process::Command;
-        
-        println!("🔍 查找便携式Tesseract...");
-        
-        // 获取应用程序目录
-        let app_dir = std::env::current_exe()
-            .map_err(|e| anyhow::anyhow!("无法获取应用程序路径: {}", e))?
-            .parent()
-            .ok_or_else(|| anyhow::anyhow!("无法获取应用程序目录"))?
-            .to_path_buf();
-        
-        // 可能的便携式Tesseract路径
-        let possible_paths = vec![
-            // 开发模式路径
-            app_dir.join("../../../resources/tesseract/tesseract.exe"),
-            app_dir.join("../../resources/tesseract/tesseract.exe"),
-            app_dir.join("../resources/tesseract/tesseract.exe"),
-            // 生产模式路径
-            app_dir.join("resources/tesseract/tesseract.exe"),
-            app_dir.join("tesseract/tesseract.exe"),
-            app_dir.join("tesseract.exe"),
-        ];
-        
-        for tesseract_path in possible_paths {
-            println!("   检查: {}", tesseract_path.display());
-            if tesseract_path.exists() {
-                let path_str = tesseract_path.to_string_lossy().to_string();
-                println!("✅ 找到便携式Tesseract: {}", path_str);
-                return Ok(path_str);
-            }
-        }
-        
-        // 如果找不到便携式版本，尝试系统安装
-        println!("⚠️ 未找到便携式Tesseract，检查系统安装...");
-        match Command::new("tesseract").arg("--version").output() {
-            Ok(output) if output.status.success() => {
-                println!("✅ 发现系统Tesseract");
-                Ok("tesseract".to_string())
-            }
-            _ => {
-                Err(anyhow::anyhow!(
-                    "无法找到Tesseract！\n\
-                    请确保:\n\
-                    1. 将tesseract.exe复制到resources/tesseract/目录\n\
-                    2. 或者在系统中安装Tesseract并添加到PATH"
-                ))
-            }
-        }
-    }
-    
-    /// 执行Tesseract命令行识别
-    fn execute_tesseract_command(
-        tesseract_exe: &str,
-        input_image: &std::path::Path,
-        output_base: &std::path::Path,
-        language: &str,
-    ) -> Result<Option<String>> {
-        use std::process::Command;
-        
-        println!("🔤 执行Tesseract识别 (语言: {})...", language);
-        
-        // 构建命令
-        let output = Command::new(tesseract_exe)
-            .arg(input_image)
-            .arg(output_base)
-            .arg("-l")
-            .arg(language)
-            .arg("--psm")
-            .arg("6")  // 单个统一文本块
-            .arg("--oem")
-            .arg("3")  // 默认OCR引擎模式
-            .output()
-            .map_err(|e| anyhow::anyhow!("执行Tesseract失败: {}", e))?;
-        
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(anyhow::anyhow!("Tesseract执行失败: {}", stderr));
-        }
-        
-        println!("✅ Tesseract命令执行成功");
-        
-        // 读取结果文件
-        let output_file = format!("{}.txt", output_base.to_string_lossy());
-        match std::fs::read_to_string(&output_file) {
-            Ok(text) => {
-                let text = text.trim().to_string();
-                if text.is_empty() {
-                    println!("⚠️ OCR识别结果为空");
-                    Ok(None)
-                } else {
-                    println!("📖 OCR识别成功，识别到 {} 字符", text.len());
-                    
-                    // 清理文本
-                    let cleaned_text = text
-                        .lines()
-                        .map(|line| line.trim())
-                        .filter(|line| !line.is_empty())
-                        .collect::<Vec<_>>()
-                        .join("\n");
-                    
-                    if cleaned_text.is_empty() {
-                        Ok(None)
-                    } else {
-                        println!("✨ 文本清理完成，最终长度: {} 字符", cleaned_text.len());
-                        Ok(Some(cleaned_text))
-                    }
-                }
-            }
-            Err(e) => {
-                Err(anyhow::anyhow!("读取OCR结果失败: {}", e))
+        let img = match crop {
+            Some(crop) => {
+                let (x, y, width, height) = crop.to_pixels(img.width(), img.height());
+                println!("✂️ 裁剪图像: {}x{} @ ({}, {})", width, height, x, y);
+                img.crop_imm(x, y, width, height)
             }
-        }
-    }
-    
-    /// 智能图像分析 (Tesseract失败时的备选方案)
-    fn smart_image_analysis(image_data: &[u8]) -> Option<String> {
-        println!("🧠 执行智能图像分析...");
-        
-        // 基于图像特征进行智能分析
-        let image_size_kb = image_data.len() / 1024;
-        let timestamp = chrono::Utc::now();
-        
-        // 根据图像大小和时间推断可能的内容类型
-        let analysis = if image_size_kb > 1000 {
-            "高分辨率屏幕内容，可能包含大量文本信息，建议检查是否在进行文档编辑或网页浏览"
-        } else if image_size_kb > 500 {
-            "中等分辨率屏幕内容，可能包含应用界面和文本，建议分析当前应用使用情况"
-        } else if image_size_kb > 100 {
-            "标准分辨率屏幕内容，包含基本界面元素，可能正在使用桌面应用"
-        } else {
-            "低分辨率或压缩度高的屏幕内容，文本信息有限"
+            None => img,
         };
         
-        // 构建智能分析结果
-        let smart_analysis = format!(
-            "智能屏幕分析报告:\n\
-            内容评估: {}\n\
-            图像大小: {} KB\n\
-            分析时间: {}\n\
-            建议: 基于图像特征进行专注状态判断\n\
-            备注: 使用Tesseract OCR v0.14简化版本", 
-            analysis,
-            image_size_kb,
-            timestamp.format("%Y-%m-%d %H:%M:%S")
-        );
-        
-        println!("📊 智能分析完成: {} 字符", smart_analysis.len());
-        Some(smart_analysis)
+        let gray = img.to_luma8();
+        let mut png_data = Cursor::new(Vec::new());
+        DynamicImage::ImageLuma8(gray)
+            .write_to(&mut png_data, ImageOutputFormat::Png)
+            .map_err(|e| anyhow::anyhow!("编码OCR图像失败: {}", e))?;
+        let png_data = png_data.into_inner();
+        
+        println!("✅ 图像已准备: {}x{}, {} KB", img.width(), img.height(), png_data.len() / 1024);
+        
+        // 2. 创建OCR引擎
+        let engine = ocr_service::create_engine(engine_kind, settings)?;
+        println!("🔧 使用OCR引擎: {}", engine.name());
+        
+        // 3. 执行OCR识别 (配置的语言失败时用英文重试，例如缺少对应的语言包)
+        engine.recognize(&png_data, options).or_else(|e| {
+            if options.languages == "eng" {
+                return Err(e);
+            }
+            println!("⚠️ 语言 {} 识别失败: {}", options.languages, e);
+            println!("🔄 尝试仅英文识别...");
+            engine.recognize(&png_data, &options.with_languages("eng"))
+        })
     }
     
     /// 获取当前任务名称
-    async fn get_current_task_name() -> Result<String> {
+    pub(crate) async fn get_current_task_name() -> Result<String> {
         // 尝试从存储服务获取当前选中的任务
         match crate::commands::get_storage_service().await {
             Ok(storage_service) => {
@@ -794,7 +1608,7 @@ impl MonitorService {
         config: &MonitoringConfig,
         app_name: &Option<String>,
         window_title: &Option<String>,
-        ocr_text: &Option<String>,
+        screen: &ScreenContent,
     ) -> Result<MonitoringResult> {
         use std::time::Instant;
         
@@ -804,10 +1618,11 @@ impl MonitorService {
         println!("📊 输入数据统计:");
         println!("   - 应用名称: {:?}", app_name);
         println!("   - 窗口标题: {:?}", window_title);
-        if let Some(text) = ocr_text {
+        println!("   - OCR状态: {:?}", screen.ocr_status);
+        if let Some(ref text) = screen.ocr_text {
             println!("   - OCR文本长度: {} 字符", text.len());
-            if text.len() > 100 {
-                println!("   - OCR文本预览: {}...", &text[..100]);
+            if text.chars().count() > 100 {
+                println!("   - OCR文本预览: {}...", prompt_budget::truncate_chars(text, 100));
             }
         } else {
             println!("   - OCR文本: 无");
@@ -820,12 +1635,8 @@ impl MonitorService {
         println!("   - API类型: {}", config.ai_config.api_type);
         println!("   - API URL: {}", config.ai_config.api_url);
         println!("   - 检测模型: {}", config.ai_config.detection_model);
-        println!("   - API密钥: {}***", 
-            if config.ai_config.api_key.len() > 8 { 
-                &config.ai_config.api_key[..8] 
-            } else { 
-                "短密钥" 
-            }
+        println!("   - API密钥: {}",
+            if config.ai_config.api_key.is_empty() { "未配置" } else { "已配置" }
         );
         
         // 构建AI分析提示
@@ -834,13 +1645,54 @@ impl MonitorService {
         
         // 尝试获取当前任务信息（从存储服务）
         let current_task = Self::get_current_task_name().await.ok();
+        let template = PromptTemplateStore::load_default().active(config.prompt_language);
+        println!("📄 提示词模板: {}", template.label());
+        let examples = CorrectionStore::load_default().similar(
+            app_name.as_deref(),
+            window_title.as_deref(),
+            config.corrections.few_shot_examples,
+        );
+        if !examples.is_empty() {
+            println!("✍️ 加入 {} 条相似的用户纠正作为示例", examples.len());
+        }
+        
+        // 相同应用、标题、任务、模板且屏幕文字相近时复用缓存的判断
+        let mut cache = None;
+        if config.classification_cache.enabled {
+            if let Some(key) = CacheKey::new(app_name.as_deref(), window_title.as_deref(), current_task.as_deref(), &template.label(), screen) {
+                let mut classification_cache = ClassificationCache::load_default();
+                if let Some(cached) = classification_cache.lookup(&key, &config.classification_cache) {
+                    println!("♻️ 命中分类缓存（已命中 {} 次），跳过AI调用", cached.hits);
+                    if let Err(e) = classification_cache.save() {
+                        println!("⚠️ 保存分类缓存失败: {}", e);
+                    }
+                    let result = MonitoringResult {
+                        focus_state: cached.focus_state,
+                        confidence: cached.confidence,
+                        ocr_text: screen.ocr_text.clone(),
+                        ocr_status: screen.ocr_status,
+                        image_features: screen.image_features.clone(),
+                        activity_category: cached.activity_category,
+                        task_relevance: cached.task_relevance,
+                        cache_hit: true,
+                        prompt_version: Some(template.label()),
+                        ..MonitoringResult::skipped(app_name.clone(), window_title.clone(), cached.reason)
+                    };
+                    Self::check_distraction_intervention(&result.focus_state, &result, current_task.as_deref()).await;
+                    return Ok(result);
+                }
+                cache = Some((key, classification_cache));
+            }
+        }
         
-        let prompt = Self::build_analysis_prompt(
+        let prompt = prompt_templates::build_detection_prompt(
             config,
-            app_name,
-            window_title,
-            ocr_text,
+            &template,
+            app_name.as_deref(),
+            window_title.as_deref(),
+            screen,
             current_task.as_deref(),
+            &examples,
         );
         let prompt_duration = prompt_start.elapsed();
         
@@ -854,7 +1706,29 @@ impl MonitorService {
         // 调用AI模型进行分析
         println!("🤖 调用AI模型进行分析...");
         let ai_call_start = Instant::now();
-        let ai_response = Self::call_ai_model(&ai_service, &prompt).await?;
+        let ((classification, ai_response), vision_used) = match screen.vision_image.as_deref() {
+            Some(image) => match focus_classification::request_classification(&ai_service, &prompt, Some(image), template.language).await {
+                Ok(response) => {
+                    println!("✅ 视觉模型调用成功");
+                    (response, true)
+                }
+                Err(e) => {
+                    // 视觉请求失败（模型不支持、图像过大等）时回退到OCR文本
+                    println!("⚠️ 视觉模型调用失败，回退到OCR文本: {}", e);
+                    let text_prompt = prompt_templates::build_detection_prompt(
+                        config,
+                        &template,
+                        app_name.as_deref(),
+                        window_title.as_deref(),
+                        &screen.without_vision(),
+                        current_task.as_deref(),
+                        &examples,
+                    );
+                    (Self::call_ai_model(&ai_service, &text_prompt, template.language).await?, false)
+                }
+            },
+            None => (Self::call_ai_model(&ai_service, &prompt, template.language).await?, false),
+        };
         let ai_call_duration = ai_call_start.elapsed();
         
         println!("⏱️ AI模型调用耗时: {:?}", ai_call_duration);
@@ -864,16 +1738,13 @@ impl MonitorService {
         println!("{}", ai_response);
         println!("{}", "─".repeat(50));
         
-        // 解析AI响应
-        println!("🔍 解析AI响应...");
-        let parse_start = Instant::now();
-        let (focus_state, confidence) = Self::parse_ai_response(&ai_response);
-        let parse_duration = parse_start.elapsed();
-        
-        println!("⏱️ 响应解析耗时: {:?}", parse_duration);
+        let focus_state = classification.state.clone();
+        let confidence = classification.confidence;
         println!("🎯 解析结果:");
         println!("   - 专注状态: {:?}", focus_state);
         println!("   - 置信度: {:.2} ({:.1}%)", confidence, confidence * 100.0);
+        println!("   - 活动类别: {:?}", classification.activity_category);
+        println!("   - 任务相关度: {:?}", classification.task_relevance);
         
         // 生成最终结果
         let result = MonitoringResult {
@@ -881,89 +1752,49 @@ impl MonitorService {
             focus_state: focus_state.clone(),
             application_name: app_name.clone(),
             window_title: window_title.clone(),
-            ocr_text: ocr_text.clone(),
-            ai_analysis: Some(ai_response),
+            ocr_text: screen.ocr_text.clone(),
+            ai_analysis: Some(classification.reason),
             confidence,
+            captured_display: None,
+            frame_hash: None,
+            carried_over: false,
+            ocr_status: screen.ocr_status,
+            image_features: screen.image_features.clone(),
+            vision_used,
+            redactions: RedactionCounts::default(),
+            private: false,
+            activity_category: classification.activity_category,
+            task_relevance: classification.task_relevance,
+            ai_unavailable: classification.ai_unavailable,
+            answered_by: classification.answered_by,
+            budget_mode: None,
+            cache_hit: false,
+            prompt_version: Some(template.label()),
+            user_label: None,
+            local_classifier: false,
+            matched_rule: None,
         };
         
         println!("✅ AI分析完成: {:?} (置信度: {:.2})", focus_state, confidence);
         
+        // 只缓存AI给出明确判断的结果
+        if let Some((key, mut classification_cache)) = cache {
+            if !result.ai_unavailable && !matches!(result.focus_state, FocusState::Unknown) {
+                classification_cache.insert(&key, &result, &config.classification_cache);
+                if let Err(e) = classification_cache.save() {
+                    println!("⚠️ 保存分类缓存失败: {}", e);
+                }
+            }
+        }
+        
         // 检查是否需要分心干预
         Self::check_distraction_intervention(&focus_state, &result, current_task.as_deref()).await;
         
         Ok(result)
     }
 
-    /// 构建AI分析提示
-    /// 构建AI分析提示
-    fn build_analysis_prompt(
-        config: &MonitoringConfig,
-        app_name: &Option<String>,
-        window_title: &Option<String>,
-        ocr_text: &Option<String>,
-        current_task: Option<&str>,
-    ) -> String {
-        let mut prompt = String::new();
-
-        // 基础分析指令
-        prompt.push_str("请分析用户当前的专注状态和任务执行情况。\n\n");
-
-        // 当前任务信息
-        if let Some(task) = current_task {
-            prompt.push_str(&format!("**当前用户任务**: {}\n\n", task));
-        } else {
-            prompt.push_str("**当前用户任务**: 无明确任务设定\n\n");
-        }
-
-        // 应用规则配置
-        if !config.whitelist.is_empty() || !config.blacklist.is_empty() {
-            prompt.push_str("**应用使用规则**:\n");
-            if !config.whitelist.is_empty() {
-                prompt.push_str("白名单应用（通常有助于专注）: ");
-                prompt.push_str(&config.whitelist.join(", "));
-                prompt.push_str("\n");
-            }
-            if !config.blacklist.is_empty() {
-                prompt.push_str("黑名单应用（通常导致分心）: ");
-                prompt.push_str(&config.blacklist.join(", "));
-                prompt.push_str("\n");
-            }
-            prompt.push_str("\n");
-        }
-
-        // 当前活动信息
-        prompt.push_str("**当前活动信息**:\n");
-        let app_info = app_name.as_deref().unwrap_or("未知应用");
-        let title_info = window_title.as_deref().unwrap_or("无标题");
-        let text_info = ocr_text.as_deref().unwrap_or("无文本内容");
-        
-        prompt.push_str(&format!("- 应用程序: {}\n", app_info));
-        prompt.push_str(&format!("- 窗口标题: {}\n", title_info));
-        prompt.push_str(&format!("- 屏幕内容: {}\n", text_info));
-        prompt.push_str(&format!("当前时间: {}\n\n", chrono::Utc::now().format("%Y-%m-%d %H:%M:%S")));
-
-        // 分析要求
-        prompt.push_str("请根据以上信息判断用户当前的专注状态，并按以下格式回答：\n\n");
-        prompt.push_str("状态: [专注/分心/严重分心]\n");
-        prompt.push_str("分析: [详细说明判断理由]\n\n");
-
-        // 判断标准
-        prompt.push_str("判断标准：\n");
-        if current_task.is_some() {
-            prompt.push_str("- 专注：当前活动与设定任务相关，或使用有助于任务完成的工具\n");
-            prompt.push_str("- 分心：当前活动与设定任务无关，但不影响长期目标\n");
-            prompt.push_str("- 严重分心：长时间从事与任务完全无关的活动，可能影响工作效率\n");
-        } else {
-            prompt.push_str("- 专注：使用白名单中的应用，或从事提升个人能力的活动\n");
-            prompt.push_str("- 分心：使用黑名单中的应用，或从事娱乐休闲活动\n");
-            prompt.push_str("- 严重分心：长时间沉迷娱乐，可能影响个人发展\n");
-        }
-
-        prompt
-    }
-
-    /// 调用AI模型
-    async fn call_ai_model(ai_service: &AIService, prompt: &str) -> Result<String> {
+    /// 调用AI模型并解析结构化结果
+    async fn call_ai_model(ai_service: &AIService, prompt: &str, language: PromptLanguage) -> Result<(FocusClassification, String)> {
         use std::time::Instant;
         
         println!("📡 准备调用AI模型...");
@@ -972,8 +1803,8 @@ impl MonitorService {
         let api_call_start = Instant::now();
         
         // 使用配置的检测模型调用AI服务
-        match ai_service.analyze_content(prompt, "detection").await {
-            Ok(response) => {
+        match focus_classification::request_classification(ai_service, prompt, None, language).await {
+            Ok((classification, response)) => {
                 let api_call_duration = api_call_start.elapsed();
                 println!("✅ AI模型调用成功");
                 println!("⏱️ API调用耗时: {:?}", api_call_duration);
@@ -983,58 +1814,20 @@ impl MonitorService {
                 let chars_per_second = (response.len() as f64) / api_call_duration.as_secs_f64();
                 println!("📊 响应速度: {:.1} 字符/秒", chars_per_second);
                 
-                Ok(response)
+                Ok((classification, response))
             }
             Err(e) => {
                 let api_call_duration = api_call_start.elapsed();
                 println!("❌ AI模型调用失败 (耗时: {:?}): {}", api_call_duration, e);
                 println!("🔄 使用备用分析方案");
                 
-                // 如果AI调用失败，返回基础分析
-                let fallback_response = "状态: 未知\n分析: AI服务暂不可用，无法进行专注状态分析。请检查网络连接和API配置。".to_string();
-                println!("📋 备用响应: {}", fallback_response);
-                
-                Ok(fallback_response)
+                // 如果AI调用失败，返回未知状态
+                let fallback = FocusClassification::unavailable(format!("AI服务不可用: {}", e));
+                Ok((fallback, String::new()))
             }
         }
     }
 
-    /// 解析AI响应
-    fn parse_ai_response(response: &str) -> (FocusState, f32) {
-        let response_lower = response.to_lowercase();
-        
-        // 优先检查明确的状态标识
-        if response_lower.contains("状态: 严重分心") || response_lower.contains("状态:严重分心") {
-            println!("🎯 解析到明确状态: 严重分心");
-            return (FocusState::SeverelyDistracted, 0.95);
-        }
-        
-        if response_lower.contains("状态: 分心") || response_lower.contains("状态:分心") {
-            println!("🎯 解析到明确状态: 分心");
-            return (FocusState::Distracted, 0.90);
-        }
-        
-        if response_lower.contains("状态: 专注") || response_lower.contains("状态:专注") {
-            println!("🎯 解析到明确状态: 专注");
-            return (FocusState::Focused, 0.90);
-        }
-        
-        // 如果没有明确的状态标识，使用关键词检查（按严重程度排序）
-        if response_lower.contains("严重分心") {
-            println!("🎯 关键词匹配: 严重分心");
-            (FocusState::SeverelyDistracted, 0.85)
-        } else if response_lower.contains("分心") {
-            println!("🎯 关键词匹配: 分心");
-            (FocusState::Distracted, 0.75)
-        } else if response_lower.contains("专注") {
-            println!("🎯 关键词匹配: 专注");
-            (FocusState::Focused, 0.70)
-        } else {
-            println!("🎯 无法识别状态，返回未知");
-            (FocusState::Unknown, 0.5)
-        }
-    }
-
     /// 获取当前活动状态
     pub async fn get_current_activity(&self) -> Option<CurrentActivity> {
         self.current_activity.lock().await.clone()
@@ -1407,6 +2200,7 @@ impl MonitorService {
         if let Some(ref handle) = *handle_guard {
             // 构建专注状态事件数据
             let focus_state_str = match result.focus_state {
+                _ if result.ai_unavailable => "ai_unavailable",
                 FocusState::Focused => "focused",
                 FocusState::Distracted => "distracted", 
                 FocusState::SeverelyDistracted => "severely_distracted",
@@ -1419,7 +2213,15 @@ impl MonitorService {
                 "application_name": result.application_name,
                 "window_title": result.window_title,
                 "timestamp": result.timestamp,
-                "ai_analysis": result.ai_analysis
+                "ai_analysis": result.ai_analysis,
+                "carried_over": result.carried_over,
+                "ocr_status": result.ocr_status,
+                "vision_used": result.vision_used,
+                "private": result.private,
+                "ai_unavailable": result.ai_unavailable,
+                "answered_by": result.answered_by,
+                "cache_hit": result.cache_hit,
+                "matched_rule": result.matched_rule
             });
             
             // 发送专注状态变化事件
@@ -1481,4 +2283,78 @@ impl MonitorService {
             Err(anyhow::anyhow!("AppHandle未设置，无法发送事件"))
         }
     }
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(app_name: &str) -> ForegroundWindow {
+        ForegroundWindow { app_name: Some(app_name.to_string()), ..Default::default() }
+    }
+
+    #[test]
+    fn rule_only_matches_app_lists_exactly() {
+        let config = MonitoringConfig {
+            whitelist: vec!["VSCode.exe".to_string()],
+            blacklist: vec!["WeChat.exe".to_string()],
+            ..Default::default()
+        };
+
+        let result = MonitoringResult::rule_only(&config, &window("code.exe"), "超出预算");
+        assert_eq!(result.focus_state, FocusState::Unknown);
+        assert!(result.matched_rule.is_none());
+        let result = MonitoringResult::rule_only(&config, &window("c"), "超出预算");
+        assert_eq!(result.focus_state, FocusState::Unknown);
+
+        let result = MonitoringResult::rule_only(&config, &window("vscode"), "超出预算");
+        assert_eq!(result.focus_state, FocusState::Focused);
+        assert_eq!(result.matched_rule.as_deref(), Some("whitelist-1"));
+        let result = MonitoringResult::rule_only(&config, &window("WECHAT.EXE"), "超出预算");
+        assert_eq!(result.focus_state, FocusState::Distracted);
+        assert_eq!(result.budget_mode, Some(BudgetFallback::AppRules));
+    }
+
+    #[test]
+    fn carried_over_result_keeps_only_the_judgement() {
+        let previous = MonitoringResult {
+            focus_state: FocusState::Distracted,
+            confidence: 0.8,
+            ai_analysis: Some("在看视频".to_string()),
+            task_relevance: Some(0.1),
+            prompt_version: Some("zh-builtin-1".to_string()),
+            user_label: Some(FocusState::Focused),
+            cache_hit: true,
+            local_classifier: true,
+            vision_used: true,
+            matched_rule: Some("rule-1".to_string()),
+            redactions: RedactionCounts { emails: 2, ..Default::default() },
+            budget_mode: Some(BudgetFallback::LocalModel),
+            ..MonitoringResult::skipped(Some("old.exe".to_string()), Some("旧标题".to_string()), "")
+        };
+        let screen = ScreenContent {
+            ocr_text: Some("屏幕文字".to_string()),
+            ocr_status: OcrStatus::Recognized,
+            frame_hash: Some(0xff),
+            unchanged: true,
+            ..Default::default()
+        };
+        let redactions = RedactionCounts { api_keys: 1, ..Default::default() };
+
+        let result = MonitoringResult::carried_over(previous, &window("chrome.exe"), &screen, redactions.clone(), None);
+        assert!(result.carried_over);
+        assert_eq!(result.focus_state, FocusState::Distracted);
+        assert_eq!(result.confidence, 0.8);
+        assert_eq!(result.ai_analysis.as_deref(), Some("在看视频"));
+        assert_eq!(result.task_relevance, Some(0.1));
+        assert_eq!(result.prompt_version.as_deref(), Some("zh-builtin-1"));
+
+        assert_eq!(result.application_name.as_deref(), Some("chrome.exe"));
+        assert_eq!(result.ocr_text.as_deref(), Some("屏幕文字"));
+        assert_eq!(result.frame_hash.as_deref(), Some("00000000000000ff"));
+        assert_eq!(result.redactions, redactions);
+        assert_eq!(result.budget_mode, None);
+        assert_eq!(result.user_label, None);
+        assert!(!result.cache_hit && !result.local_classifier && !result.vision_used);
+        assert!(result.matched_rule.is_none());
+    }
+}
@@ -0,0 +1,471 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// OCR引擎类型
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum OcrEngineKind {
+    #[default]
+    TesseractCli, // 调用便携式/系统 tesseract 可执行文件
+    InProcess,    // 进程内调用libtesseract (需启用 in-process-ocr 特性)
+    Disabled,     // 不进行OCR，仅依据应用名称和窗口标题判断
+}
+
+/// OCR识别参数
+#[derive(Debug, Clone, PartialEq)]
+pub struct OcrOptions {
+    pub languages: String, // Tesseract语言代码，多个用 `+` 连接，如 `chi_tra+eng`
+    pub psm: u8,           // 页面分割模式 (0-13)
+    pub oem: u8,           // OCR引擎模式 (0-3)
+}
+
+impl OcrOptions {
+    /// 使用其他语言的同一组参数
+    pub fn with_languages(&self, languages: &str) -> Self {
+        Self {
+            languages: languages.to_string(),
+            ..self.clone()
+        }
+    }
+}
+
+/// Tesseract 可执行文件和语言数据的位置，未设置时自动查找
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TesseractSettings {
+    pub tesseract_path: Option<String>,
+    pub tessdata_dir: Option<String>,
+}
+
+/// 检测到的Tesseract环境
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TesseractInfo {
+    pub executable: String,
+    pub version: Option<String>,
+    pub tessdata_dir: Option<String>,
+    pub languages: Vec<String>,
+}
+
+/// OCR引擎
+pub trait OcrEngine {
+    /// 引擎名称（用于日志）
+    fn name(&self) -> &'static str;
+
+    /// 识别PNG图像中的文字，没有识别到文字时返回 `None`
+    fn recognize(&self, png_data: &[u8], options: &OcrOptions) -> Result<Option<String>>;
+}
+
+/// 根据配置创建OCR引擎
+///
+/// 引擎可能启动外部进程或持有原生句柄，应在 `spawn_blocking` 线程内创建并使用。
+pub fn create_engine(kind: OcrEngineKind, settings: &TesseractSettings) -> Result<Box<dyn OcrEngine>> {
+    match kind {
+        OcrEngineKind::TesseractCli => Ok(Box::new(TesseractCliEngine::locate(settings)?)),
+        OcrEngineKind::Disabled => Ok(Box::new(DisabledOcrEngine)),
+        OcrEngineKind::InProcess => {
+            #[cfg(feature = "in-process-ocr")]
+            {
+                Ok(Box::new(InProcessTesseractEngine::new(settings)))
+            }
+
+            #[cfg(not(feature = "in-process-ocr"))]
+            {
+                println!("⚠️ 未启用 in-process-ocr 特性，改用Tesseract命令行引擎");
+                Ok(Box::new(TesseractCliEngine::locate(settings)?))
+            }
+        }
+    }
+}
+
+/// 清理OCR文本：去除空行和行首尾空白
+fn clean_ocr_text(text: &str) -> Option<String> {
+    let cleaned_text = text
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    if cleaned_text.is_empty() {
+        None
+    } else {
+        Some(cleaned_text)
+    }
+}
+
+/// 当前平台的Tesseract可执行文件名
+#[cfg(windows)]
+const TESSERACT_EXE: &str = "tesseract.exe";
+#[cfg(not(windows))]
+const TESSERACT_EXE: &str = "tesseract";
+
+/// 常见的系统安装位置（图形界面启动的应用在macOS上不会继承shell的PATH）
+#[cfg(target_os = "macos")]
+const SYSTEM_TESSERACT_PATHS: &[&str] = &[
+    "/opt/homebrew/bin/tesseract", // Homebrew (Apple Silicon)
+    "/usr/local/bin/tesseract",    // Homebrew (Intel)
+    "/opt/local/bin/tesseract",    // MacPorts
+];
+#[cfg(target_os = "linux")]
+const SYSTEM_TESSERACT_PATHS: &[&str] = &[
+    "/usr/bin/tesseract",
+    "/usr/local/bin/tesseract",
+    "/snap/bin/tesseract",
+];
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+const SYSTEM_TESSERACT_PATHS: &[&str] = &[];
+
+/// 去除空白后为空的设置视为未设置
+fn non_empty(value: Option<&str>) -> Option<&str> {
+    value.map(str::trim).filter(|value| !value.is_empty())
+}
+
+/// 确定语言数据目录
+///
+/// 优先级：配置的 `tessdata_dir` > `TESSDATA_PREFIX` 环境变量 > 便携式Tesseract旁的 `tessdata`。
+/// 都没有时返回 `None`，由Tesseract使用编译时的默认位置。
+fn resolve_tessdata_dir(settings: &TesseractSettings, executable: Option<&Path>) -> Option<PathBuf> {
+    if let Some(dir) = non_empty(settings.tessdata_dir.as_deref()) {
+        let dir = PathBuf::from(dir);
+        if !dir.is_dir() {
+            println!("⚠️ 配置的tessdata目录不存在: {}", dir.display());
+        }
+        return Some(dir);
+    }
+
+    if let Ok(prefix) = std::env::var("TESSDATA_PREFIX") {
+        if !prefix.trim().is_empty() {
+            println!("📚 使用TESSDATA_PREFIX: {}", prefix);
+            return Some(PathBuf::from(prefix));
+        }
+    }
+
+    executable
+        .and_then(|exe| exe.parent())
+        .map(|dir| dir.join("tessdata"))
+        .filter(|dir| dir.is_dir())
+}
+
+/// 便携式Tesseract可能所在的目录（开发模式和生产模式）
+fn portable_tesseract_dirs() -> Result<Vec<PathBuf>> {
+    let app_dir = std::env::current_exe()
+        .map_err(|e| anyhow::anyhow!("无法获取应用程序路径: {}", e))?
+        .parent()
+        .ok_or_else(|| anyhow::anyhow!("无法获取应用程序目录"))?
+        .to_path_buf();
+
+    Ok(vec![
+        // 开发模式路径
+        app_dir.join("../../../resources/tesseract"),
+        app_dir.join("../../resources/tesseract"),
+        app_dir.join("../resources/tesseract"),
+        // 生产模式路径
+        app_dir.join("resources/tesseract"),
+        app_dir.join("tesseract"),
+        app_dir,
+    ])
+}
+
+// ===== 禁用OCR =====
+
+/// 不进行OCR识别
+pub struct DisabledOcrEngine;
+
+impl OcrEngine for DisabledOcrEngine {
+    fn name(&self) -> &'static str {
+        "disabled"
+    }
+
+    fn recognize(&self, _png_data: &[u8], _options: &OcrOptions) -> Result<Option<String>> {
+        Ok(None)
+    }
+}
+
+// ===== Tesseract 命令行 =====
+
+/// 通过 tesseract 可执行文件识别
+///
+/// 图像通过stdin传入、识别结果从stdout读取，不使用固定路径的临时文件，
+/// 因此手动检查与后台循环同时运行时不会互相覆盖。
+pub struct TesseractCliEngine {
+    executable: PathBuf,
+    tessdata_dir: Option<PathBuf>,
+}
+
+impl TesseractCliEngine {
+    /// 查找Tesseract
+    ///
+    /// 依次使用配置的 `tesseract_path`、随应用分发的便携版本、PATH 中的 `tesseract`
+    /// 和当前平台的常见安装位置。
+    pub fn locate(settings: &TesseractSettings) -> Result<Self> {
+        let executable = Self::find_executable(settings)?;
+        let tessdata_dir = resolve_tessdata_dir(settings, Some(&executable));
+        if let Some(ref dir) = tessdata_dir {
+            println!("📚 tessdata目录: {}", dir.display());
+        }
+        Ok(Self { executable, tessdata_dir })
+    }
+
+    fn find_executable(settings: &TesseractSettings) -> Result<PathBuf> {
+        use std::process::Command;
+
+        if let Some(path) = non_empty(settings.tesseract_path.as_deref()) {
+            let mut path = PathBuf::from(path);
+            if path.is_dir() {
+                path = path.join(TESSERACT_EXE);
+            }
+            if path.is_file() {
+                println!("✅ 使用配置的Tesseract: {}", path.display());
+                return Ok(path);
+            }
+            return Err(anyhow::anyhow!("配置的Tesseract路径不存在: {}", path.display()));
+        }
+
+        println!("🔍 查找便携式Tesseract...");
+
+        for dir in portable_tesseract_dirs()? {
+            let tesseract_path = dir.join(TESSERACT_EXE);
+            println!("   检查: {}", tesseract_path.display());
+            if tesseract_path.is_file() {
+                println!("✅ 找到便携式Tesseract: {}", tesseract_path.display());
+                return Ok(tesseract_path);
+            }
+        }
+
+        // 如果找不到便携式版本，尝试系统安装
+        println!("⚠️ 未找到便携式Tesseract，检查系统安装...");
+        if matches!(Command::new("tesseract").arg("--version").output(), Ok(output) if output.status.success()) {
+            println!("✅ 发现系统Tesseract");
+            return Ok(PathBuf::from("tesseract"));
+        }
+
+        if let Some(path) = SYSTEM_TESSERACT_PATHS.iter().map(Path::new).find(|path| path.is_file()) {
+            println!("✅ 发现系统Tesseract: {}", path.display());
+            return Ok(path.to_path_buf());
+        }
+
+        Err(anyhow::anyhow!(
+            "无法找到Tesseract！\n\
+            请确保:\n\
+            1. 将tesseract.exe复制到resources/tesseract/目录\n\
+            2. 或者在系统中安装Tesseract并添加到PATH (Linux: apt install tesseract-ocr，macOS: brew install tesseract)\n\
+            3. 或者在监控设置中指定Tesseract路径"
+        ))
+    }
+
+    /// 读取版本号和已安装的语言
+    pub fn info(&self) -> Result<TesseractInfo> {
+        use std::process::Command;
+
+        // 旧版本把 --version 和 --list-langs 的结果输出到stderr
+        let read_output = |output: std::process::Output| {
+            let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+            if stdout.trim().is_empty() {
+                String::from_utf8_lossy(&output.stderr).to_string()
+            } else {
+                stdout
+            }
+        };
+
+        let version_output = Command::new(&self.executable)
+            .arg("--version")
+            .output()
+            .map_err(|e| anyhow::anyhow!("执行Tesseract失败: {}", e))?;
+        let version = read_output(version_output)
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .map(str::to_string);
+
+        let mut list_langs = Command::new(&self.executable);
+        if let Some(ref dir) = self.tessdata_dir {
+            list_langs.arg("--tessdata-dir").arg(dir);
+        }
+        let langs_output = list_langs
+            .arg("--list-langs")
+            .output()
+            .map_err(|e| anyhow::anyhow!("执行Tesseract失败: {}", e))?;
+        if !langs_output.status.success() {
+            let stderr = String::from_utf8_lossy(&langs_output.stderr);
+            return Err(anyhow::anyhow!("读取语言列表失败: {}", stderr));
+        }
+
+        // 第一行是 "List of available languages in ..." 标题
+        let languages = read_output(langs_output)
+            .lines()
+            .skip(1)
+            .map(str::trim)
+            .filter(|lang| !lang.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        Ok(TesseractInfo {
+            executable: self.executable.to_string_lossy().to_string(),
+            version,
+            tessdata_dir: self.tessdata_dir.as_ref().map(|dir| dir.to_string_lossy().to_string()),
+            languages,
+        })
+    }
+
+    /// 通过 `tesseract - stdout` 识别：图像写入stdin，文本从stdout读取
+    fn run_piped(&self, png_data: &[u8], options: &OcrOptions) -> Result<String> {
+        use std::io::Write;
+        use std::process::{Command, Stdio};
+
+        let mut child = Command::new(&self.executable)
+            .arg("-")
+            .arg("stdout")
+            .args(self.command_options(options))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| anyhow::anyhow!("执行Tesseract失败: {}", e))?;
+
+        // 在独立线程写入stdin，避免与读取stdout互相阻塞
+        let mut stdin = child.stdin.take()
+            .ok_or_else(|| anyhow::anyhow!("无法打开Tesseract标准输入"))?;
+        let input = png_data.to_vec();
+        let writer = std::thread::spawn(move || stdin.write_all(&input));
+
+        let output = child.wait_with_output()
+            .map_err(|e| anyhow::anyhow!("等待Tesseract结束失败: {}", e))?;
+        let write_result = writer.join()
+            .map_err(|_| anyhow::anyhow!("写入Tesseract标准输入的线程异常退出"))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!("Tesseract执行失败: {}", stderr));
+        }
+        write_result.map_err(|e| anyhow::anyhow!("写入Tesseract标准输入失败: {}", e))?;
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// 在本次调用独占的临时目录中识别，目录在返回（包括出错）时自动删除
+    fn run_with_temp_dir(&self, png_data: &[u8], options: &OcrOptions) -> Result<String> {
+        use std::process::Command;
+
+        let temp_dir = tempfile::Builder::new()
+            .prefix("my_focus_ocr_")
+            .tempdir()
+            .map_err(|e| anyhow::anyhow!("创建临时目录失败: {}", e))?;
+        let input_image = temp_dir.path().join("input.png");
+        let output_base = temp_dir.path().join("output");
+
+        println!("📁 临时目录: {}", temp_dir.path().display());
+
+        std::fs::write(&input_image, png_data)
+            .map_err(|e| anyhow::anyhow!("保存临时图像失败: {}", e))?;
+
+        let output = Command::new(&self.executable)
+            .arg(&input_image)
+            .arg(&output_base)
+            .args(self.command_options(options))
+            .output()
+            .map_err(|e| anyhow::anyhow!("执行Tesseract失败: {}", e))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!("Tesseract执行失败: {}", stderr));
+        }
+
+        std::fs::read_to_string(output_base.with_extension("txt"))
+            .map_err(|e| anyhow::anyhow!("读取OCR结果失败: {}", e))
+    }
+
+    /// Tesseract识别参数
+    fn command_options(&self, options: &OcrOptions) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(ref dir) = self.tessdata_dir {
+            args.push("--tessdata-dir".to_string());
+            args.push(dir.to_string_lossy().to_string());
+        }
+        args.extend([
+            "-l".to_string(),
+            options.languages.clone(),
+            "--psm".to_string(),
+            options.psm.to_string(),
+            "--oem".to_string(),
+            options.oem.to_string(),
+        ]);
+        args
+    }
+}
+
+impl OcrEngine for TesseractCliEngine {
+    fn name(&self) -> &'static str {
+        "tesseract-cli"
+    }
+
+    fn recognize(&self, png_data: &[u8], options: &OcrOptions) -> Result<Option<String>> {
+        println!(
+            "🔤 执行Tesseract识别 (语言: {}, PSM: {}, OEM: {})...",
+            options.languages, options.psm, options.oem
+        );
+        println!("📍 使用Tesseract: {}", self.executable.display());
+
+        // 优先使用管道；部分旧版本无法从stdin读取图像，回退到独立的临时目录
+        let text = match self.run_piped(png_data, options) {
+            Ok(text) => text,
+            Err(e) => {
+                println!("⚠️ 管道方式识别失败: {}", e);
+                println!("🔄 改用临时文件方式识别...");
+                self.run_with_temp_dir(png_data, options)?
+            }
+        };
+
+        println!("✅ Tesseract命令执行成功");
+        Ok(clean_ocr_text(&text))
+    }
+}
+
+// ===== 进程内 libtesseract =====
+
+/// 通过 leptess 在进程内调用 libtesseract，省去每次启动外部进程的开销
+///
+/// 需要以 `--features in-process-ocr` 编译，并在系统中安装 tesseract 与 leptonica 开发库。
+/// libtesseract 只能在初始化时选择OCR引擎模式，因此该引擎忽略 `oem` 参数。
+#[cfg(feature = "in-process-ocr")]
+pub struct InProcessTesseractEngine {
+    tessdata_dir: Option<String>,
+}
+
+#[cfg(feature = "in-process-ocr")]
+impl InProcessTesseractEngine {
+    fn new(settings: &TesseractSettings) -> Self {
+        // 没有配置目录和 TESSDATA_PREFIX 时使用随应用分发的 tessdata
+        let portable_exe = portable_tesseract_dirs()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|dir| dir.join(TESSERACT_EXE))
+            .find(|exe| exe.with_file_name("tessdata").is_dir());
+        let tessdata_dir = resolve_tessdata_dir(settings, portable_exe.as_deref())
+            .map(|dir| dir.to_string_lossy().to_string());
+        Self { tessdata_dir }
+    }
+}
+
+#[cfg(feature = "in-process-ocr")]
+impl OcrEngine for InProcessTesseractEngine {
+    fn name(&self) -> &'static str {
+        "tesseract-in-process"
+    }
+
+    fn recognize(&self, png_data: &[u8], options: &OcrOptions) -> Result<Option<String>> {
+        println!(
+            "🔤 执行进程内Tesseract识别 (语言: {}, PSM: {})...",
+            options.languages, options.psm
+        );
+
+        let mut tess = leptess::LepTess::new(self.tessdata_dir.as_deref(), &options.languages)
+            .map_err(|e| anyhow::anyhow!("初始化Tesseract失败: {}", e))?;
+        tess.set_variable(leptess::Variable::TesseditPagesegMode, &options.psm.to_string())
+            .map_err(|e| anyhow::anyhow!("设置页面分割模式失败: {}", e))?;
+        tess.set_image_from_mem(png_data)
+            .map_err(|e| anyhow::anyhow!("加载OCR图像失败: {}", e))?;
+
+        let text = tess.get_utf8_text()
+            .map_err(|e| anyhow::anyhow!("读取OCR结果失败: {}", e))?;
+        Ok(clean_ocr_text(&text))
+    }
+}
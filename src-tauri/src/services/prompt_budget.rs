@@ -0,0 +1,311 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::services::prompt_templates::PromptLanguage;
+
+/// 提示词预算配置：模型上下文窗口及各部分可占用的比例
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptBudgetConfig {
+    #[serde(default = "default_context_window_tokens")]
+    pub context_window_tokens: u32, // 检测模型的上下文窗口
+    #[serde(default = "default_reserved_tokens")]
+    pub reserved_tokens: u32, // 预留给固定说明文字和模型输出的token数
+    #[serde(default = "default_task_share")]
+    pub task_share: f32, // 当前任务
+    #[serde(default = "default_rules_share")]
+    pub rules_share: f32, // 白名单/黑名单规则
+    #[serde(default = "default_title_share")]
+    pub title_share: f32, // 应用名称和窗口标题
+    #[serde(default = "default_ocr_share")]
+    pub ocr_share: f32, // 屏幕文字
+}
+
+fn default_context_window_tokens() -> u32 {
+    4096
+}
+
+fn default_reserved_tokens() -> u32 {
+    1024
+}
+
+fn default_task_share() -> f32 {
+    0.1
+}
+
+fn default_rules_share() -> f32 {
+    0.15
+}
+
+fn default_title_share() -> f32 {
+    0.1
+}
+
+fn default_ocr_share() -> f32 {
+    0.65
+}
+
+impl Default for PromptBudgetConfig {
+    fn default() -> Self {
+        Self {
+            context_window_tokens: default_context_window_tokens(),
+            reserved_tokens: default_reserved_tokens(),
+            task_share: default_task_share(),
+            rules_share: default_rules_share(),
+            title_share: default_title_share(),
+            ocr_share: default_ocr_share(),
+        }
+    }
+}
+
+/// 按各部分比例分配后的token预算
+#[derive(Debug, Clone, Copy)]
+pub struct PromptBudget {
+    pub task_tokens: usize,
+    pub rules_tokens: usize,
+    pub title_tokens: usize,
+    pub ocr_tokens: usize,
+}
+
+impl PromptBudget {
+    /// 每部分至少保留的token数，避免比例配置过小时整段消失
+    const MIN_SECTION_TOKENS: usize = 16;
+
+    pub fn from_config(config: &PromptBudgetConfig) -> Self {
+        let available = config.context_window_tokens.saturating_sub(config.reserved_tokens) as usize;
+
+        // 比例之和超过1时按比例缩小
+        let shares = [config.task_share, config.rules_share, config.title_share, config.ocr_share]
+            .map(|share| if share.is_finite() { share.max(0.0) } else { 0.0 });
+        let total: f32 = shares.iter().sum();
+        let scale = if total > 1.0 { 1.0 / total } else { 1.0 };
+        let tokens = shares.map(|share| ((available as f32 * share * scale) as usize).max(Self::MIN_SECTION_TOKENS));
+
+        Self {
+            task_tokens: tokens[0],
+            rules_tokens: tokens[1],
+            title_tokens: tokens[2],
+            ocr_tokens: tokens[3],
+        }
+    }
+}
+
+/// 是否为中日韩字符（这些字符通常每个占约1个token）
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF   // 日文假名
+        | 0x3400..=0x4DBF // CJK扩展A
+        | 0x4E00..=0x9FFF // CJK统一汉字
+        | 0xAC00..=0xD7AF // 韩文音节
+        | 0xF900..=0xFAFF // CJK兼容汉字
+        | 0xFF00..=0xFFEF // 全角字符
+    )
+}
+
+/// 粗略估算文本的token数：中日韩字符各算1个，其余字符约4个算1个
+pub fn estimate_tokens(text: &str) -> usize {
+    let mut cjk = 0usize;
+    let mut other = 0usize;
+    for c in text.chars() {
+        if is_cjk(c) {
+            cjk += 1;
+        } else {
+            other += 1;
+        }
+    }
+    cjk + other.div_ceil(4)
+}
+
+/// 按字符数截断，保证不会在多字节字符中间切断
+pub fn truncate_chars(text: &str, max_chars: usize) -> &str {
+    match text.char_indices().nth(max_chars) {
+        Some((index, _)) => &text[..index],
+        None => text,
+    }
+}
+
+/// 截断时追加的省略号
+const ELLIPSIS: &str = "...";
+
+/// 按估算的token数截断，被截断时追加省略号（省略号也计入预算）
+pub fn truncate_to_tokens(text: &str, max_tokens: usize) -> String {
+    if estimate_tokens(text) <= max_tokens {
+        return text.to_string();
+    }
+    if max_tokens == 0 {
+        return String::new();
+    }
+
+    let mut cjk = 0usize;
+    let mut other = ELLIPSIS.len();
+    let mut end = 0usize;
+    for (index, c) in text.char_indices() {
+        if is_cjk(c) {
+            cjk += 1;
+        } else {
+            other += 1;
+        }
+        if cjk + other.div_ceil(4) > max_tokens {
+            break;
+        }
+        end = index + c.len_utf8();
+    }
+    format!("{}{}", &text[..end], ELLIPSIS)
+}
+
+/// OCR文本行的信息量评分
+///
+/// 字母、数字和汉字越多分数越高；纯符号、过短的行（图标、菜单分隔符等）得分很低。
+fn line_score(line: &str) -> f32 {
+    let total = line.chars().count();
+    if total == 0 {
+        return 0.0;
+    }
+    let meaningful = line.chars().filter(|c| c.is_alphanumeric()).count();
+    if meaningful < 2 {
+        return 0.0;
+    }
+    let cjk = line.chars().filter(|c| is_cjk(*c)).count();
+    let ratio = meaningful as f32 / total as f32;
+    // 汉字信息密度更高，额外加权
+    (meaningful + cjk) as f32 * ratio
+}
+
+/// 把OCR文本压缩到token预算内
+///
+/// 去除重复行后按信息量从高到低排列，依次放入直到用完预算，使最有用的内容排在最前。
+pub fn fit_ocr_text(text: &str, max_tokens: usize) -> String {
+    let mut seen = HashSet::new();
+    let mut lines: Vec<(f32, &str)> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && seen.insert(*line))
+        .map(|line| (line_score(line), line))
+        .filter(|(score, _)| *score > 0.0)
+        .collect();
+    // 稳定排序，同分的行保持原有顺序
+    lines.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut result = String::new();
+    let mut used = 0usize;
+    for (_, line) in lines {
+        let cost = estimate_tokens(line) + 1;
+        if used + cost > max_tokens {
+            if result.is_empty() {
+                // 第一行就超出预算时截断该行
+                result = truncate_to_tokens(line, max_tokens);
+                used = estimate_tokens(&result);
+            }
+            continue;
+        }
+        if !result.is_empty() {
+            result.push('\n');
+        }
+        result.push_str(line);
+        used += cost;
+    }
+
+    // 全部是符号等低信息量内容时保留原文开头
+    if result.is_empty() {
+        return truncate_to_tokens(text.trim(), max_tokens);
+    }
+    result
+}
+
+/// 把列表（白名单、黑名单等）拼接到token预算内，放不下的项目以“等N项”概括
+pub fn fit_list(items: &[String], language: PromptLanguage, max_tokens: usize) -> String {
+    let mut result = String::new();
+    let mut used = 0usize;
+    for (index, item) in items.iter().enumerate() {
+        let cost = estimate_tokens(item) + 1;
+        if used + cost > max_tokens {
+            let remaining = items.len() - index;
+            if !result.is_empty() {
+                result.push(' ');
+            }
+            result.push_str(&match language {
+                PromptLanguage::Zh => format!("等{}项", remaining),
+                PromptLanguage::En => format!("and {} more", remaining),
+            });
+            break;
+        }
+        if !result.is_empty() {
+            result.push_str(", ");
+        }
+        result.push_str(item);
+        used += cost;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimates_cjk_per_character_and_other_text_per_four_chars() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("专注"), 2);
+        assert_eq!(estimate_tokens("focus"), 2);
+        assert_eq!(estimate_tokens("写Rust代码"), 4);
+    }
+
+    #[test]
+    fn truncate_chars_keeps_whole_characters() {
+        assert_eq!(truncate_chars("你好世界", 2), "你好");
+        assert_eq!(truncate_chars("a😀b", 2), "a😀");
+        assert_eq!(truncate_chars("a😀b", 0), "");
+        assert_eq!(truncate_chars("a😀b", 1), "a");
+        assert_eq!(truncate_chars("你好", 10), "你好");
+    }
+
+    #[test]
+    fn truncate_to_tokens_cuts_inside_multibyte_text_within_budget() {
+        let texts = ["正在编写专注监控的提示词预算模块", "😀😀😀😀😀😀😀😀😀😀😀😀😀😀😀😀", "混合 mixed 文本 😀 text 中文"];
+        for text in texts {
+            for max_tokens in 0..=estimate_tokens(text) + 1 {
+                let truncated = truncate_to_tokens(text, max_tokens);
+                assert!(estimate_tokens(&truncated) <= max_tokens, "{:?} 超出 {} 个token", truncated, max_tokens);
+                let kept = truncated.strip_suffix(ELLIPSIS).unwrap_or(&truncated);
+                assert!(text.starts_with(kept));
+            }
+        }
+
+        assert_eq!(truncate_to_tokens("正在编写代码", 0), "");
+        assert_eq!(truncate_to_tokens("正在编写代码", 1), "...");
+        assert_eq!(truncate_to_tokens("正在编写代码", 3), "正在...");
+        assert_eq!(truncate_to_tokens("正在编写代码", 6), "正在编写代码");
+    }
+
+    #[test]
+    fn fit_ocr_text_puts_informative_lines_first() {
+        let text = "|||\n- -\n登录\nRust 所有权与借用 第四章 教程\n登录\n设置";
+        let fitted = fit_ocr_text(text, 100);
+        let lines: Vec<&str> = fitted.lines().collect();
+        assert_eq!(lines, ["Rust 所有权与借用 第四章 教程", "登录", "设置"]);
+
+        // 预算只够一行时保留信息量最高的一行
+        assert_eq!(fit_ocr_text(text, 15), "Rust 所有权与借用 第四章 教程");
+    }
+
+    #[test]
+    fn fit_ocr_text_never_exceeds_budget() {
+        let text = "浏览器 - 哔哩哔哩 (゜-゜)つロ 干杯~\n😀😀😀 表情包合集 😀😀😀\nSettings > Privacy\n===\n会议纪要：下周发布版本";
+        for max_tokens in 0..=estimate_tokens(text) + 2 {
+            let fitted = fit_ocr_text(text, max_tokens);
+            assert!(estimate_tokens(&fitted) <= max_tokens, "{:?} 超出 {} 个token", fitted, max_tokens);
+        }
+        assert_eq!(fit_ocr_text(text, 0), "");
+
+        // 全部是符号时保留原文开头
+        assert_eq!(fit_ocr_text("=== ---", 100), "=== ---");
+    }
+
+    #[test]
+    fn fit_list_summarizes_remaining_items_in_template_language() {
+        let items: Vec<String> = ["Code.exe", "Chrome.exe", "微信"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(fit_list(&items, PromptLanguage::Zh, 100), "Code.exe, Chrome.exe, 微信");
+        assert_eq!(fit_list(&items, PromptLanguage::Zh, 3), "Code.exe 等2项");
+        assert_eq!(fit_list(&items, PromptLanguage::En, 3), "Code.exe and 2 more");
+        assert_eq!(fit_list(&items, PromptLanguage::En, 0), "and 3 more");
+    }
+}
@@ -0,0 +1,370 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use chrono::{DateTime, Local, Utc};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::services::corrections::{self, Correction};
+use crate::services::focus_classification;
+use crate::services::monitor_service::{MonitoringConfig, ScreenContent};
+use crate::services::prompt_budget::{self, PromptBudget};
+
+const TEMPLATES_FILE: &str = "prompt_templates.json";
+
+/// 内置模板的版本，修改内置模板内容时递增
+const BUILTIN_VERSION: u32 = 1;
+
+/// 模板中可以使用的变量，写作 {{task}}
+pub const TEMPLATE_VARIABLES: [&str; 7] = ["task", "app", "title", "ocr", "rules", "time", "examples"];
+
+const BUILTIN_ZH: &str = r#"请分析用户当前的专注状态和任务执行情况。
+
+**当前用户任务**: {{task}}
+
+**应用使用规则**:
+{{rules}}
+
+**当前活动信息**:
+- 应用程序: {{app}}
+- 窗口标题: {{title}}
+- 屏幕内容: {{ocr}}
+当前时间: {{time}}
+
+请根据以上信息判断用户当前的专注状态。
+判断标准：
+- 专注：当前活动与设定任务相关，或使用白名单中的应用、有助于任务完成的工具、提升个人能力的活动
+- 分心：当前活动与设定任务无关，或使用黑名单中的应用、从事娱乐休闲活动
+- 严重分心：长时间从事与任务完全无关的活动或沉迷娱乐，明显影响工作效率
+"#;
+
+const BUILTIN_EN: &str = r#"Assess the user's current focus state and progress on their task.
+
+**Current task**: {{task}}
+
+**App rules**:
+{{rules}}
+
+**Current activity**:
+- Application: {{app}}
+- Window title: {{title}}
+- Screen content: {{ocr}}
+Current time: {{time}}
+
+Judge the user's current focus state from the information above.
+Criteria:
+- focused: the activity is related to the task, or uses whitelisted apps, tools that help finish the task, or self-improvement activities
+- distracted: the activity is unrelated to the task, or uses blacklisted apps, entertainment or leisure
+- severely_distracted: prolonged activity completely unrelated to the task, or binging entertainment that clearly hurts productivity
+"#;
+
+/// 提示词语言
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptLanguage {
+    #[default]
+    Zh,
+    En,
+}
+
+impl PromptLanguage {
+    pub fn code(self) -> &'static str {
+        match self {
+            PromptLanguage::Zh => "zh",
+            PromptLanguage::En => "en",
+        }
+    }
+}
+
+/// 一个版本的专注检测提示词模板
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptTemplate {
+    pub language: PromptLanguage,
+    pub version: u32,
+    #[serde(default)]
+    pub builtin: bool,
+    pub body: String,
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>, // 内置模板为空
+}
+
+impl PromptTemplate {
+    /// 内置模板
+    pub fn builtin(language: PromptLanguage) -> Self {
+        Self {
+            language,
+            version: BUILTIN_VERSION,
+            builtin: true,
+            body: match language {
+                PromptLanguage::Zh => BUILTIN_ZH,
+                PromptLanguage::En => BUILTIN_EN,
+            }
+            .to_string(),
+            created_at: None,
+        }
+    }
+
+    /// 版本标识，如 zh-builtin-1、en-v3，记录在每条监控结果上
+    pub fn label(&self) -> String {
+        if self.builtin {
+            format!("{}-builtin-{}", self.language.code(), self.version)
+        } else {
+            format!("{}-v{}", self.language.code(), self.version)
+        }
+    }
+
+    /// 替换变量并追加输出格式要求（格式由程序维护，保证回复可以解析）
+    ///
+    /// 模板中没有 {{examples}} 时，用户纠正示例追加在输出格式要求之前。
+    pub fn render(&self, variables: &PromptVariables) -> String {
+        let mut uses_examples = false;
+        let mut prompt = VARIABLE_PATTERN
+            .replace_all(&self.body, |caps: &regex::Captures| {
+                uses_examples |= &caps[1] == "examples";
+                variables.get(&caps[1]).unwrap_or(&caps[0]).to_string()
+            })
+            .into_owned();
+        if !prompt.ends_with('\n') {
+            prompt.push('\n');
+        }
+        if !uses_examples && !variables.examples.is_empty() {
+            prompt.push_str(match self.language {
+                PromptLanguage::Zh => "\n**用户纠正过的类似判断**（请参考）:\n",
+                PromptLanguage::En => "\n**Similar past judgments corrected by the user** (take these into account):\n",
+            });
+            prompt.push_str(&variables.examples);
+            prompt.push_str("\n\n");
+        }
+        prompt.push_str(match self.language {
+            PromptLanguage::Zh => focus_classification::RESPONSE_FORMAT_INSTRUCTIONS,
+            PromptLanguage::En => focus_classification::RESPONSE_FORMAT_INSTRUCTIONS_EN,
+        });
+        prompt
+    }
+}
+
+lazy_static! {
+    // 模板变量 {{name}}，允许花括号内有空白
+    static ref VARIABLE_PATTERN: Regex = Regex::new(r"\{\{\s*(\w+)\s*\}\}").unwrap();
+}
+
+/// 检查模板：不能为空，只能使用已知变量
+pub fn validate_template(body: &str) -> Result<()> {
+    if body.trim().is_empty() {
+        return Err(anyhow::anyhow!("模板内容不能为空"));
+    }
+    let unknown: Vec<String> = VARIABLE_PATTERN
+        .captures_iter(body)
+        .map(|caps| caps[1].to_string())
+        .filter(|name| !TEMPLATE_VARIABLES.contains(&name.as_str()))
+        .collect();
+    if !unknown.is_empty() {
+        return Err(anyhow::anyhow!(
+            "未知的模板变量: {}（可用变量: {}）",
+            unknown.join(", "),
+            TEMPLATE_VARIABLES.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+/// 模板变量的值，已按 `prompt_budget` 截断
+pub struct PromptVariables {
+    pub task: String,
+    pub app: String,
+    pub title: String,
+    pub ocr: String,
+    pub rules: String,
+    pub time: String,
+    pub examples: String, // 相似的用户纠正，没有时为空
+}
+
+impl PromptVariables {
+    /// 任务、规则、标题和屏幕文字按 `prompt_budget` 配置的比例分配上下文窗口
+    ///
+    /// 有用户纠正示例时，示例占规则预算的一半。
+    pub fn new(
+        config: &MonitoringConfig,
+        app_name: Option<&str>,
+        window_title: Option<&str>,
+        screen: &ScreenContent,
+        current_task: Option<&str>,
+        examples: &[Correction],
+        language: PromptLanguage,
+    ) -> Self {
+        let budget = PromptBudget::from_config(&config.prompt_budget);
+        let zh = language == PromptLanguage::Zh;
+        let examples_tokens = if examples.is_empty() { 0 } else { budget.rules_tokens / 2 };
+
+        let task = match current_task {
+            Some(task) => prompt_budget::truncate_to_tokens(task, budget.task_tokens),
+            None if zh => "无明确任务设定".to_string(),
+            None => "No task set".to_string(),
+        };
+
+        // 白名单和黑名单平分剩余的规则预算
+        let list_tokens = (budget.rules_tokens - examples_tokens) / 2;
+        let mut rules = Vec::new();
+        if !config.whitelist.is_empty() {
+            let label = if zh { "白名单应用（通常有助于专注）" } else { "Whitelisted apps (usually help focus)" };
+            rules.push(format!("{}: {}", label, prompt_budget::fit_list(&config.whitelist, language, list_tokens)));
+        }
+        if !config.blacklist.is_empty() {
+            let label = if zh { "黑名单应用（通常导致分心）" } else { "Blacklisted apps (usually distracting)" };
+            rules.push(format!("{}: {}", label, prompt_budget::fit_list(&config.blacklist, language, list_tokens)));
+        }
+        if rules.is_empty() {
+            rules.push(if zh { "未设置白名单或黑名单" } else { "No whitelist or blacklist configured" }.to_string());
+        }
+
+        // 应用名称最多占标题预算的四分之一
+        let app_tokens = budget.title_tokens / 4;
+        let app = prompt_budget::truncate_to_tokens(
+            app_name.unwrap_or(if zh { "未知应用" } else { "Unknown application" }),
+            app_tokens,
+        );
+        let title = prompt_budget::truncate_to_tokens(
+            window_title.unwrap_or(if zh { "无标题" } else { "Untitled" }),
+            budget.title_tokens - app_tokens,
+        );
+
+        Self {
+            task,
+            app,
+            title,
+            ocr: screen.describe_for_prompt(budget.ocr_tokens, language),
+            rules: rules.join("\n"),
+            time: Local::now().format("%Y-%m-%d %H:%M").to_string(),
+            examples: corrections::format_examples(examples, language, examples_tokens),
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        match name {
+            "task" => Some(&self.task),
+            "app" => Some(&self.app),
+            "title" => Some(&self.title),
+            "ocr" => Some(&self.ocr),
+            "rules" => Some(&self.rules),
+            "time" => Some(&self.time),
+            "examples" => Some(&self.examples),
+            _ => None,
+        }
+    }
+}
+
+/// 保存在数据目录中的模板文件
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StoredTemplates {
+    #[serde(default)]
+    templates: Vec<PromptTemplate>, // 用户保存的所有版本，只追加不修改
+    #[serde(default)]
+    active: HashMap<PromptLanguage, String>, // 每种语言当前使用的版本标识，缺省为内置模板
+}
+
+/// 提示词模板及其版本历史
+pub struct PromptTemplateStore {
+    path: PathBuf,
+    stored: StoredTemplates,
+}
+
+impl PromptTemplateStore {
+    /// 读取模板文件，文件不存在或无法解析时只有内置模板
+    pub fn load(data_dir: PathBuf) -> Self {
+        let path = data_dir.join(TEMPLATES_FILE);
+        let stored = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self { path, stored }
+    }
+
+    /// 与其他服务一致，使用应用本地目录
+    pub fn load_default() -> Self {
+        Self::load(PathBuf::from("data"))
+    }
+
+    /// 所有模板：内置模板在前，用户版本按保存顺序排列
+    pub fn templates(&self) -> Vec<PromptTemplate> {
+        let mut templates = vec![
+            PromptTemplate::builtin(PromptLanguage::Zh),
+            PromptTemplate::builtin(PromptLanguage::En),
+        ];
+        templates.extend(self.stored.templates.iter().cloned());
+        templates
+    }
+
+    fn find(&self, label: &str) -> Option<PromptTemplate> {
+        self.templates().into_iter().find(|template| template.label() == label)
+    }
+
+    /// 某种语言当前使用的模板，找不到已选版本时使用内置模板
+    pub fn active(&self, language: PromptLanguage) -> PromptTemplate {
+        self.stored
+            .active
+            .get(&language)
+            .and_then(|label| self.find(label))
+            .filter(|template| template.language == language)
+            .unwrap_or_else(|| PromptTemplate::builtin(language))
+    }
+
+    /// 保存为新版本并设为当前模板
+    pub fn save_version(&mut self, language: PromptLanguage, body: &str) -> Result<PromptTemplate> {
+        validate_template(body)?;
+        let version = self
+            .stored
+            .templates
+            .iter()
+            .filter(|template| template.language == language)
+            .map(|template| template.version)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        let template = PromptTemplate {
+            language,
+            version,
+            builtin: false,
+            body: body.to_string(),
+            created_at: Some(Utc::now()),
+        };
+        self.stored.templates.push(template.clone());
+        self.stored.active.insert(language, template.label());
+        self.save()?;
+        Ok(template)
+    }
+
+    /// 切换到已有版本（包括内置模板）
+    pub fn activate(&mut self, label: &str) -> Result<PromptTemplate> {
+        let template = self
+            .find(label)
+            .ok_or_else(|| anyhow::anyhow!("找不到模板版本: {}", label))?;
+        self.stored.active.insert(template.language, template.label());
+        self.save()?;
+        Ok(template)
+    }
+
+    fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&self.stored)?)?;
+        Ok(())
+    }
+}
+
+/// 用指定模板生成专注检测提示词
+pub fn build_detection_prompt(
+    config: &MonitoringConfig,
+    template: &PromptTemplate,
+    app_name: Option<&str>,
+    window_title: Option<&str>,
+    screen: &ScreenContent,
+    current_task: Option<&str>,
+    examples: &[Correction],
+) -> String {
+    let variables = PromptVariables::new(config, app_name, window_title, screen, current_task, examples, template.language);
+    template.render(&variables)
+}
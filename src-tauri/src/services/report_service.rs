@@ -4,7 +4,9 @@ use std::collections::HashMap;
 use anyhow::{Result, anyhow};
 
 use crate::services::storage_service::StorageService;
-use crate::services::ai_service::AIService;
+use tauri::{AppHandle, Manager};
+
+use crate::services::ai_service::{AIService, ChatOptions};
 use crate::services::monitor_service::{MonitoringResult, FocusState};
 use crate::models::FocusSession;
 
@@ -107,18 +109,35 @@ pub struct AIInsights {
     pub productivity_suggestions: String,
 }
 
+/// 报告生成过程中逐段发送给前端的AI洞察文本
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportChunk {
+    pub report_type: String, // "daily" 或 "weekly"
+    pub date: String,        // 日报告的日期或周报告的起始日期
+    pub chunk: String,
+    pub done: bool,          // 输出结束（成功或失败）
+}
+
 /// 报告服务
 pub struct ReportService {
     storage_service: StorageService,
+    app_handle: Option<AppHandle>,
 }
 
 impl ReportService {
     pub fn new(storage_service: StorageService) -> Self {
         Self {
             storage_service,
+            app_handle: None,
         }
     }
 
+    /// 设置AppHandle后，AI洞察以 report_chunk 事件流式发送给前端
+    pub fn with_app_handle(mut self, app_handle: AppHandle) -> Self {
+        self.app_handle = Some(app_handle);
+        self
+    }
+
     /// 生成日报告
     pub async fn generate_daily_report(&self, date: &str, ai_service: &AIService) -> Result<DailyReport> {
         println!("📊 开始生成日报告: {}", date);
@@ -144,7 +163,7 @@ impl ReportService {
         let time_analysis = self.analyze_time_slots(&monitoring_results)?;
 
         // 4. 生成AI洞察
-        let ai_insights = self.generate_ai_insights(&summary, &focus_patterns, &app_usage, &monitoring_results, ai_service).await?;
+        let ai_insights = self.generate_ai_insights(date, &summary, &focus_patterns, &app_usage, &monitoring_results, ai_service).await?;
         
         // 5. 生成个性化建议
         let recommendations = self.generate_recommendations(&summary, &focus_patterns, &app_usage)?;
@@ -207,7 +226,7 @@ impl ReportService {
         let focus_improvement = self.analyze_focus_improvement(&daily_data)?;
         
         // 生成AI洞察
-        let ai_insights = self.generate_weekly_ai_insights(&daily_data, &all_monitoring_results, ai_service).await?;
+        let ai_insights = self.generate_weekly_ai_insights(week_start, &daily_data, &all_monitoring_results, ai_service).await?;
         
         // 生成周建议
         let weekly_recommendations = self.generate_weekly_recommendations(&summary, &focus_improvement)?;
@@ -453,6 +472,7 @@ impl ReportService {
     /// 生成AI洞察
     async fn generate_ai_insights(
         &self,
+        date: &str,
         summary: &DailyReportSummary,
         focus_patterns: &FocusPatternStats,
         app_usage: &[AppUsageStats],
@@ -463,7 +483,7 @@ impl ReportService {
 
         let prompt = self.build_daily_analysis_prompt(summary, focus_patterns, app_usage, monitoring_results);
         
-        match ai_service.analyze_content(&prompt, "report").await {
+        match self.request_report_text(ai_service, &prompt, "daily", date).await {
             Ok(ai_response) => {
                 println!("✅ AI分析完成");
                 Ok(self.parse_ai_insights(&ai_response))
@@ -475,6 +495,42 @@ impl ReportService {
         }
     }
 
+    /// 调用报告模型；有AppHandle时流式输出，每段文本通过 report_chunk 事件发送
+    async fn request_report_text(
+        &self,
+        ai_service: &AIService,
+        prompt: &str,
+        report_type: &str,
+        date: &str,
+    ) -> Result<String, String> {
+        // 超出预算且没有本地模型时不再调用AI，由调用方使用默认洞察
+        let ai_service = &ai_service.within_budget("report").map_err(|reason| {
+            println!("💰 {}，跳过AI报告生成", reason);
+            reason
+        })?;
+
+        let Some(handle) = self.app_handle.clone() else {
+            return ai_service.analyze_content(prompt, "report").await;
+        };
+
+        let emit = move |chunk: &str, done: bool| {
+            let payload = ReportChunk {
+                report_type: report_type.to_string(),
+                date: date.to_string(),
+                chunk: chunk.to_string(),
+                done,
+            };
+            if let Err(e) = handle.emit_all("report_chunk", payload) {
+                println!("⚠️ 发送报告片段事件失败: {}", e);
+            }
+        };
+
+        let mut on_chunk = |chunk: &str| emit(chunk, false);
+        let result = ai_service.chat_stream(prompt, "report", &ChatOptions::default(), &mut on_chunk).await;
+        emit("", true);
+        result.map(|reply| reply.text)
+    }
+
     /// 构建AI分析提示词
     fn build_daily_analysis_prompt(
         &self,
@@ -720,13 +776,14 @@ impl ReportService {
     /// 生成周AI洞察
     async fn generate_weekly_ai_insights(
         &self,
+        week_start: &str,
         daily_data: &[DailyTrendData],
         monitoring_results: &[MonitoringResult],
         ai_service: &AIService,
     ) -> Result<AIInsights> {
         let prompt = self.build_weekly_analysis_prompt(daily_data, monitoring_results);
         
-        match ai_service.analyze_content(&prompt, "report").await {
+        match self.request_report_text(ai_service, &prompt, "weekly", week_start).await {
             Ok(ai_response) => Ok(self.parse_ai_insights(&ai_response)),
             Err(_) => Ok(self.generate_default_weekly_insights(daily_data)),
         }
@@ -0,0 +1,309 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::Result;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use lazy_static::lazy_static;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+/// 系统密钥服务中的服务名
+const SERVICE_NAME: &str = "my-focus";
+const VAULT_FILE: &str = "secrets.vault";
+/// 用于校验口令的明文
+const VAULT_CHECK: &str = "my-focus-vault";
+
+lazy_static! {
+    // 系统密钥服务是否可用，首次访问后确定
+    static ref KEYRING_AVAILABLE: Mutex<Option<bool>> = Mutex::new(None);
+    // 解锁后的密钥库密钥，只保存在内存中
+    static ref VAULT_KEY: Mutex<Option<[u8; 32]>> = Mutex::new(None);
+    // 无法持久保存的密钥只在本次运行中保留
+    static ref SESSION_SECRETS: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
+    // 仍以明文保存旧版本密钥、等待迁移的配置文件
+    static ref PLAINTEXT_PENDING: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+}
+
+/// 密钥实际保存的位置
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum SecretBackend {
+    Keyring, // 系统密钥服务（Linux 上为 D-Bus Secret Service）
+    Vault,   // 口令加密的本地密钥库
+    Session, // 仅在本次运行中有效
+}
+
+/// 密钥存储状态
+#[derive(Debug, Clone, Serialize)]
+pub struct SecretStorageStatus {
+    pub backend: SecretBackend, // 新保存的密钥会写入的位置
+    pub keyring_available: bool,
+    pub vault_exists: bool,
+    pub vault_unlocked: bool,
+    pub session_only: usize, // 只保存在内存中的密钥数量
+    pub plaintext_pending: Vec<String>, // 无法迁移、仍以明文保存密钥的配置（ai_config / monitoring_config）
+}
+
+/// 加密后的值
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct EncryptedValue {
+    nonce: String,
+    ciphertext: String,
+}
+
+/// 密钥库文件：Argon2id 派生密钥，AES-256-GCM 加密每个值
+#[derive(Debug, Serialize, Deserialize)]
+struct VaultFile {
+    salt: String,
+    check: EncryptedValue,
+    #[serde(default)]
+    entries: HashMap<String, EncryptedValue>,
+}
+
+fn vault_path() -> PathBuf {
+    // 与其他服务一致，使用应用本地目录
+    PathBuf::from("data").join(VAULT_FILE)
+}
+
+fn read_vault() -> Option<VaultFile> {
+    let content = fs::read_to_string(vault_path()).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn write_vault(vault: &VaultFile) -> Result<()> {
+    let path = vault_path();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(vault)?)?;
+    Ok(())
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32]> {
+    let mut key = [0u8; 32];
+    argon2::Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow::anyhow!("派生密钥失败: {}", e))?;
+    Ok(key)
+}
+
+fn encrypt(key: &[u8; 32], plaintext: &str) -> Result<EncryptedValue> {
+    let mut nonce = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let cipher = Aes256Gcm::new(key.into());
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_bytes())
+        .map_err(|_| anyhow::anyhow!("加密失败"))?;
+    Ok(EncryptedValue {
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    })
+}
+
+fn decrypt(key: &[u8; 32], value: &EncryptedValue) -> Result<String> {
+    let nonce = BASE64.decode(&value.nonce)?;
+    let ciphertext = BASE64.decode(&value.ciphertext)?;
+    if nonce.len() != 12 {
+        return Err(anyhow::anyhow!("密钥库数据已损坏"));
+    }
+    let cipher = Aes256Gcm::new(key.into());
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| anyhow::anyhow!("解密失败，口令错误或数据已损坏"))?;
+    Ok(String::from_utf8(plaintext)?)
+}
+
+fn vault_key() -> Option<[u8; 32]> {
+    *VAULT_KEY.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn session_secrets() -> std::sync::MutexGuard<'static, HashMap<String, String>> {
+    SESSION_SECRETS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// 访问系统密钥服务；服务不可用时记录下来，之后不再尝试
+fn with_keyring<T>(operation: impl FnOnce(&keyring::Entry) -> keyring::Result<T>, name: &str) -> Option<keyring::Result<T>> {
+    let mut available = KEYRING_AVAILABLE.lock().unwrap_or_else(|e| e.into_inner());
+    if *available == Some(false) {
+        return None;
+    }
+    let result = keyring::Entry::new(SERVICE_NAME, name).and_then(|entry| operation(&entry));
+    match result {
+        Err(keyring::Error::PlatformFailure(ref e)) | Err(keyring::Error::NoStorageAccess(ref e)) => {
+            println!("⚠️ 系统密钥服务不可用，改用本地密钥库: {}", e);
+            *available = Some(false);
+            None
+        }
+        result => {
+            *available = Some(true);
+            Some(result)
+        }
+    }
+}
+
+/// 保存密钥：系统密钥服务 → 已解锁的密钥库 → 仅本次运行
+///
+/// 需要在阻塞线程中调用（Linux 上的 Secret Service 客户端会阻塞等待 D-Bus）。
+pub fn store(name: &str, value: &str) -> SecretBackend {
+    session_secrets().remove(name);
+
+    match with_keyring(|entry| entry.set_password(value), name) {
+        Some(Ok(())) => return SecretBackend::Keyring,
+        Some(Err(e)) => println!("⚠️ 写入系统密钥服务失败: {}", e),
+        None => {}
+    }
+
+    if let Some(key) = vault_key() {
+        let result = read_vault()
+            .ok_or_else(|| anyhow::anyhow!("密钥库文件不存在"))
+            .and_then(|mut vault| {
+                vault.entries.insert(name.to_string(), encrypt(&key, value)?);
+                write_vault(&vault)
+            });
+        match result {
+            Ok(()) => return SecretBackend::Vault,
+            Err(e) => println!("⚠️ 写入密钥库失败: {}", e),
+        }
+    }
+
+    session_secrets().insert(name.to_string(), value.to_string());
+    SecretBackend::Session
+}
+
+/// 读取密钥，依次查找本次运行、系统密钥服务和已解锁的密钥库
+pub fn load(name: &str) -> Option<String> {
+    if let Some(value) = session_secrets().get(name) {
+        return Some(value.clone());
+    }
+
+    match with_keyring(|entry| entry.get_password(), name) {
+        Some(Ok(value)) => return Some(value),
+        Some(Err(keyring::Error::NoEntry)) | None => {}
+        Some(Err(e)) => println!("⚠️ 读取系统密钥服务失败: {}", e),
+    }
+
+    let key = vault_key()?;
+    let value = read_vault()?.entries.get(name).cloned()?;
+    match decrypt(&key, &value) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            println!("⚠️ 读取密钥库失败: {}", e);
+            None
+        }
+    }
+}
+
+/// 从所有位置删除密钥
+pub fn delete(name: &str) {
+    session_secrets().remove(name);
+
+    match with_keyring(|entry| entry.delete_credential(), name) {
+        Some(Ok(())) | Some(Err(keyring::Error::NoEntry)) | None => {}
+        Some(Err(e)) => println!("⚠️ 删除系统密钥服务中的密钥失败: {}", e),
+    }
+
+    // 密钥库未解锁时读不到其中的密钥，配置中的空密钥不代表用户清除了它
+    if vault_key().is_none() {
+        return;
+    }
+    if let Some(mut vault) = read_vault() {
+        if vault.entries.remove(name).is_some() {
+            if let Err(e) = write_vault(&vault) {
+                println!("⚠️ 更新密钥库失败: {}", e);
+            }
+        }
+    }
+}
+
+/// 用口令解锁密钥库，第一次使用时以该口令创建密钥库
+///
+/// 解锁后把只保存在本次运行中的密钥写入密钥库，返回写入的数量。
+pub fn unlock_vault(passphrase: &str) -> Result<usize> {
+    if passphrase.is_empty() {
+        return Err(anyhow::anyhow!("口令不能为空"));
+    }
+
+    let mut vault = match read_vault() {
+        Some(vault) => {
+            let salt = BASE64.decode(&vault.salt)?;
+            let key = derive_key(passphrase, &salt)?;
+            if decrypt(&key, &vault.check).ok().as_deref() != Some(VAULT_CHECK) {
+                return Err(anyhow::anyhow!("口令错误"));
+            }
+            *VAULT_KEY.lock().unwrap_or_else(|e| e.into_inner()) = Some(key);
+            vault
+        }
+        None => {
+            let mut salt = [0u8; 16];
+            rand::thread_rng().fill_bytes(&mut salt);
+            let key = derive_key(passphrase, &salt)?;
+            let vault = VaultFile {
+                salt: BASE64.encode(salt),
+                check: encrypt(&key, VAULT_CHECK)?,
+                entries: HashMap::new(),
+            };
+            write_vault(&vault)?;
+            *VAULT_KEY.lock().unwrap_or_else(|e| e.into_inner()) = Some(key);
+            println!("🔐 已创建本地密钥库");
+            vault
+        }
+    };
+
+    let pending: Vec<(String, String)> = session_secrets().drain().collect();
+    if pending.is_empty() {
+        return Ok(0);
+    }
+    let key = vault_key().ok_or_else(|| anyhow::anyhow!("密钥库未解锁"))?;
+    for (name, value) in &pending {
+        vault.entries.insert(name.clone(), encrypt(&key, value)?);
+    }
+    write_vault(&vault)?;
+    println!("🔐 已把 {} 个密钥写入密钥库", pending.len());
+    Ok(pending.len())
+}
+
+/// 当前的密钥存储状态
+pub fn status() -> SecretStorageStatus {
+    // 尚未访问过系统密钥服务时先探测一次
+    let known = *KEYRING_AVAILABLE.lock().unwrap_or_else(|e| e.into_inner());
+    let keyring_available = match known {
+        Some(available) => available,
+        None => {
+            let probe = with_keyring(|entry| entry.get_password(), "availability-probe");
+            matches!(probe, Some(Ok(_)) | Some(Err(keyring::Error::NoEntry)))
+        }
+    };
+    let vault_unlocked = vault_key().is_some();
+    SecretStorageStatus {
+        backend: if keyring_available {
+            SecretBackend::Keyring
+        } else if vault_unlocked {
+            SecretBackend::Vault
+        } else {
+            SecretBackend::Session
+        },
+        keyring_available,
+        vault_exists: vault_path().exists(),
+        vault_unlocked,
+        session_only: session_secrets().len(),
+        plaintext_pending: PLAINTEXT_PENDING.lock().unwrap_or_else(|e| e.into_inner()).iter().cloned().collect(),
+    }
+}
+
+/// 配置文件中是否仍有等待迁移的明文密钥
+pub fn is_plaintext_pending(scope: &str) -> bool {
+    PLAINTEXT_PENDING.lock().unwrap_or_else(|e| e.into_inner()).contains(scope)
+}
+
+/// 记录配置文件中是否仍有等待迁移的明文密钥
+pub fn set_plaintext_pending(scope: &str, pending: bool) {
+    let mut scopes = PLAINTEXT_PENDING.lock().unwrap_or_else(|e| e.into_inner());
+    if pending {
+        scopes.insert(scope.to_string());
+    } else {
+        scopes.remove(scope);
+    }
+}
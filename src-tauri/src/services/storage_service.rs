@@ -5,6 +5,12 @@ use serde_json;
 use crate::commands::{UserSettings, Task};
 use crate::models::{FocusSession, ApplicationActivity};
 use crate::services::ai_service::AIConfig;
+use crate::services::prompt_budget;
+use crate::services::secret_service;
+
+/// 两个配置文件中的API密钥分开保存，清空其中一个不会删除另一个
+pub(crate) const AI_CONFIG_SECRETS: &str = "ai_config";
+pub(crate) const MONITORING_CONFIG_SECRETS: &str = "monitoring_config";
 
 pub struct StorageService {
     data_dir: PathBuf,
@@ -115,6 +121,25 @@ impl StorageService {
         }
     }
 
+    /// 为指定时间的监控结果写入用户标注，找不到该结果时返回 None
+    pub async fn label_monitoring_result(
+        &self,
+        timestamp: chrono::DateTime<chrono::Utc>,
+        label: crate::services::monitor_service::FocusState,
+    ) -> Result<Option<crate::services::monitor_service::MonitoringResult>> {
+        let mut results = self.load_monitoring_results().await?;
+        let Some(result) = results.iter_mut().find(|r| r.timestamp == timestamp) else {
+            return Ok(None);
+        };
+        result.user_label = Some(label);
+        let labeled = result.clone();
+
+        let file_path = self.data_dir.join("monitoring_results.json");
+        let json_data = serde_json::to_string_pretty(&results)?;
+        fs::write(file_path, json_data)?;
+        Ok(Some(labeled))
+    }
+
     /// 获取今日监控统计
     pub async fn get_today_monitoring_stats(&self) -> Result<crate::commands::TodayStats> {
         let results = self.load_monitoring_results().await.unwrap_or_default();
@@ -164,46 +189,112 @@ impl StorageService {
         })
     }
 
-    /// 保存AI配置
-    pub async fn save_ai_config(&self, config: &AIConfig) -> Result<()> {
+    /// 保存AI配置，API密钥写入密钥存储，不写入配置文件
+    ///
+    /// 返回API密钥是否已持久保存；为 false 时密钥只在本次运行中有效，需要提示用户解锁密钥库。
+    pub async fn save_ai_config(&self, config: &AIConfig) -> Result<bool> {
         let file_path = self.data_dir.join("ai_config.json");
-        let json_data = serde_json::to_string_pretty(config)?;
+        let previous = Self::read_stored::<AIConfig>(&file_path);
+        let (stored, persisted) = Self::strip_secrets(config, previous, AI_CONFIG_SECRETS).await?;
+        let json_data = serde_json::to_string_pretty(&stored)?;
         fs::write(file_path, json_data)?;
-        Ok(())
+        Ok(persisted)
     }
 
-    /// 加载AI配置
+    /// 加载AI配置，并从密钥存储中取回API密钥
     pub async fn load_ai_config(&self) -> Result<AIConfig> {
         let file_path = self.data_dir.join("ai_config.json");
         if file_path.exists() {
-            let json_data = fs::read_to_string(file_path)?;
-            let config: AIConfig = serde_json::from_str(&json_data)?;
-            Ok(config)
+            let json_data = fs::read_to_string(&file_path)?;
+            let mut config: AIConfig = serde_json::from_str(&json_data)?;
+            // 旧版本的备用服务没有编号，先生成，迁移和界面保存时使用同一个编号
+            config.ensure_endpoint_ids();
+            // 旧版本把密钥明文写在配置文件中，读取时迁移
+            if config.has_plaintext_secrets() {
+                if let Some(stored) = Self::migrate_plaintext_secrets(&config, AI_CONFIG_SECRETS).await? {
+                    fs::write(&file_path, serde_json::to_string_pretty(&stored)?)?;
+                }
+            }
+            Ok(tokio::task::spawn_blocking(move || config.with_secrets(AI_CONFIG_SECRETS)).await?)
         } else {
             Ok(AIConfig::default())
         }
     }
 
-    /// 保存监控配置
-    pub async fn save_monitoring_config(&self, config: &crate::services::monitor_service::MonitoringConfig) -> Result<()> {
+    /// 保存监控配置，其中的API密钥写入密钥存储，返回密钥是否已持久保存
+    pub async fn save_monitoring_config(&self, config: &crate::services::monitor_service::MonitoringConfig) -> Result<bool> {
         let file_path = self.data_dir.join("monitoring_config.json");
-        let json_data = serde_json::to_string_pretty(config)?;
+        let previous = Self::read_stored::<crate::services::monitor_service::MonitoringConfig>(&file_path)
+            .map(|previous| previous.ai_config);
+        let mut stored = config.clone();
+        let (ai_config, persisted) = Self::strip_secrets(&config.ai_config, previous, MONITORING_CONFIG_SECRETS).await?;
+        stored.ai_config = ai_config;
+        let json_data = serde_json::to_string_pretty(&stored)?;
         fs::write(file_path, json_data)?;
-        Ok(())
+        Ok(persisted)
     }
 
-    /// 加载监控配置
+    /// 加载监控配置，并从密钥存储中取回API密钥
     pub async fn load_monitoring_config(&self) -> Result<crate::services::monitor_service::MonitoringConfig> {
         let file_path = self.data_dir.join("monitoring_config.json");
         if file_path.exists() {
-            let json_data = fs::read_to_string(file_path)?;
-            let config: crate::services::monitor_service::MonitoringConfig = serde_json::from_str(&json_data)?;
+            let json_data = fs::read_to_string(&file_path)?;
+            let mut config: crate::services::monitor_service::MonitoringConfig = serde_json::from_str(&json_data)?;
+            config.ai_config.ensure_endpoint_ids();
+            if config.ai_config.has_plaintext_secrets() {
+                if let Some(ai_config) = Self::migrate_plaintext_secrets(&config.ai_config, MONITORING_CONFIG_SECRETS).await? {
+                    let stored = crate::services::monitor_service::MonitoringConfig { ai_config, ..config.clone() };
+                    fs::write(&file_path, serde_json::to_string_pretty(&stored)?)?;
+                }
+            }
+            let ai_config = config.ai_config.clone();
+            config.ai_config = tokio::task::spawn_blocking(move || ai_config.with_secrets(MONITORING_CONFIG_SECRETS)).await?;
             Ok(config)
         } else {
             Ok(crate::services::monitor_service::MonitoringConfig::default())
         }
     }
 
+    /// 读取已保存的配置文件（不取回密钥），不存在或无法解析时返回 None
+    fn read_stored<T: serde::de::DeserializeOwned>(file_path: &std::path::Path) -> Option<T> {
+        let json_data = fs::read_to_string(file_path).ok()?;
+        serde_json::from_str(&json_data).ok()
+    }
+
+    /// 把API密钥移入密钥存储，返回要写入配置文件的配置，以及密钥重启后是否还在
+    ///
+    /// `previous` 是上次保存的配置，用来删除已移除的备用服务的密钥。
+    /// 配置文件中还有等待迁移的明文密钥、而这次仍无法持久保存时，保留明文密钥，
+    /// 以免在用户解锁密钥库之前保存设置导致重启后密钥丢失。
+    async fn strip_secrets(config: &AIConfig, previous: Option<AIConfig>, scope: &'static str) -> Result<(AIConfig, bool)> {
+        let original = config.clone();
+        let (stored, persisted) =
+            tokio::task::spawn_blocking(move || original.without_secrets(scope, previous.as_ref())).await?;
+        if !persisted && secret_service::is_plaintext_pending(scope) {
+            println!("⚠️ 没有可用的密钥存储，{} 中的API密钥继续以明文保存，请解锁本地密钥库", scope);
+            return Ok((config.clone(), true));
+        }
+        secret_service::set_plaintext_pending(scope, false);
+        Ok((stored, persisted))
+    }
+
+    /// 把旧版本明文保存的API密钥移入密钥存储，返回可以写回配置文件的配置
+    ///
+    /// 系统密钥服务不可用且密钥库未解锁时密钥只能保存在内存中，重启后就会丢失。
+    /// 此时返回 `None`，配置文件保持原样，并在密钥存储状态中提示用户解锁密钥库后再迁移。
+    async fn migrate_plaintext_secrets(config: &AIConfig, scope: &'static str) -> Result<Option<AIConfig>> {
+        let config = config.clone();
+        let (stored, persisted) = tokio::task::spawn_blocking(move || config.without_secrets(scope, None)).await?;
+        secret_service::set_plaintext_pending(scope, !persisted);
+        if persisted {
+            println!("🔐 已把API密钥从配置文件迁移到密钥存储");
+            Ok(Some(stored))
+        } else {
+            println!("⚠️ 没有可用的密钥存储，暂不迁移 {} 中的明文API密钥，请解锁本地密钥库", scope);
+            Ok(None)
+        }
+    }
+
     pub async fn save_focus_session(&self, session: &FocusSession) -> Result<()> {
         let mut sessions = self.load_focus_sessions().await.unwrap_or_default();
         
@@ -338,13 +429,12 @@ impl StorageService {
                 // 移除重复的空白字符
                 *ocr_text = ocr_text.split_whitespace().collect::<Vec<_>>().join(" ");
                 
-                // 如果文本太长，只保留前1000个字符
-                if ocr_text.len() > 1000 {
-                    ocr_text.truncate(1000);
-                    ocr_text.push_str("...[截断]");
+                // 如果文本太长，只保留前1000个字符（按字符截断，避免切断中文）
+                if ocr_text.chars().count() > 1000 {
+                    *ocr_text = format!("{}...[截断]", prompt_budget::truncate_chars(ocr_text, 1000));
                 }
                 
-                compressed_bytes += (original_len - ocr_text.len()) as u32;
+                compressed_bytes += original_len.saturating_sub(ocr_text.len()) as u32;
             }
             
             // 压缩AI分析结果
@@ -352,22 +442,18 @@ impl StorageService {
                 let original_len = ai_analysis.len();
                 
                 // 如果AI分析太长，只保留关键部分
-                if ai_analysis.len() > 500 {
+                if ai_analysis.chars().count() > 500 {
                     // 尝试保留"状态:"和"分析:"部分
-                    if let Some(status_pos) = ai_analysis.find("状态:") {
-                        let truncated = if ai_analysis.len() > status_pos + 500 {
-                            format!("{}...[截断]", &ai_analysis[..status_pos + 500])
-                        } else {
-                            ai_analysis.clone()
-                        };
-                        *ai_analysis = truncated;
-                    } else {
-                        ai_analysis.truncate(500);
-                        ai_analysis.push_str("...[截断]");
+                    let start = ai_analysis.find("状态:")
+                        .map(|pos| ai_analysis[..pos].chars().count())
+                        .unwrap_or(0);
+                    let kept = prompt_budget::truncate_chars(ai_analysis, start + 500);
+                    if kept.len() < ai_analysis.len() {
+                        *ai_analysis = format!("{}...[截断]", kept);
                     }
                 }
                 
-                compressed_bytes += (original_len - ai_analysis.len()) as u32;
+                compressed_bytes += original_len.saturating_sub(ai_analysis.len()) as u32;
             }
         }
         
@@ -0,0 +1,359 @@
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::Result;
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+use crate::services::ai_service::AIConfig;
+
+/// 模型价格（美元 / 百万token）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub model: String, // 模型名称，末尾带 * 时按前缀匹配
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+}
+
+impl ModelPrice {
+    fn new(model: &str, input_per_million: f64, output_per_million: f64) -> Self {
+        Self {
+            model: model.to_string(),
+            input_per_million,
+            output_per_million,
+        }
+    }
+
+    fn matches(&self, model: &str) -> bool {
+        let pattern = self.model.trim().to_lowercase();
+        let model = model.to_lowercase();
+        match pattern.strip_suffix('*') {
+            Some(prefix) => model.starts_with(prefix),
+            None => model == pattern,
+        }
+    }
+}
+
+/// 默认价格表（常用模型的公开价格，可在配置中修改）
+pub fn default_price_table() -> Vec<ModelPrice> {
+    vec![
+        ModelPrice::new("gpt-4o-mini*", 0.15, 0.6),
+        ModelPrice::new("gpt-4o*", 2.5, 10.0),
+        ModelPrice::new("gpt-4-turbo*", 10.0, 30.0),
+        ModelPrice::new("gpt-3.5-turbo*", 0.5, 1.5),
+        ModelPrice::new("claude-3-haiku*", 0.25, 1.25),
+        ModelPrice::new("claude-3-5-haiku*", 0.8, 4.0),
+        ModelPrice::new("claude-3-5-sonnet*", 3.0, 15.0),
+        ModelPrice::new("gemini-1.5-flash*", 0.075, 0.3),
+        ModelPrice::new("gemini-1.5-pro*", 1.25, 5.0),
+        ModelPrice::new("deepseek-chat*", 0.27, 1.1),
+    ]
+}
+
+/// AI调用预算（美元），为空表示不限制
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsageBudget {
+    #[serde(default)]
+    pub daily_limit: Option<f64>,
+    #[serde(default)]
+    pub monthly_limit: Option<f64>,
+}
+
+/// 单次AI调用记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageRecord {
+    pub timestamp: DateTime<Utc>,
+    pub api_type: String,
+    pub model: String,
+    pub model_type: String, // "detection" 或 "report"
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    #[serde(default)]
+    pub estimated: bool, // 服务未返回用量，按文本长度估算
+    pub latency_ms: u64,
+    pub cost: f64,       // 美元
+    #[serde(default)]
+    pub priced: bool,    // 价格表中有该模型（本地模型不计费，也视为已计价）
+    #[serde(default)]
+    pub fallback: bool,
+}
+
+/// 一段时间内的用量汇总
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SpendSummary {
+    pub calls: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost: f64,
+    pub unpriced_calls: u64, // 价格表中没有的模型调用次数，未计入费用
+}
+
+impl SpendSummary {
+    fn add(&mut self, record: &UsageRecord) {
+        self.calls += 1;
+        self.prompt_tokens += record.prompt_tokens;
+        self.completion_tokens += record.completion_tokens;
+        self.cost += record.cost;
+        if !record.priced {
+            self.unpriced_calls += 1;
+        }
+    }
+}
+
+/// 某个模型本月的用量
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelSpend {
+    pub api_type: String,
+    pub model: String,
+    #[serde(flatten)]
+    pub summary: SpendSummary,
+}
+
+/// 花费报告
+#[derive(Debug, Clone, Serialize)]
+pub struct SpendReport {
+    pub today: SpendSummary,
+    pub this_month: SpendSummary,
+    pub daily_limit: Option<f64>,
+    pub monthly_limit: Option<f64>,
+    pub budget_exceeded: Option<String>, // 超出预算时的说明
+    pub by_model: Vec<ModelSpend>,       // 本月按费用从高到低排列
+}
+
+/// 超出预算时专注检测的处理方式
+pub enum BudgetMode {
+    Normal,
+    LocalOnly(Box<AIConfig>), // 改用本地模型（不计费）
+    RuleOnly(String),    // 没有本地模型，只按应用规则判断；附带超出预算的说明
+}
+
+/// 超出预算后实际采用的判断方式，保存在监控结果中供界面显示
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum BudgetFallback {
+    LocalModel, // 改用本地模型
+    AppRules,   // 只按应用规则判断
+}
+
+/// 当月用量的内存汇总
+#[derive(Default)]
+struct MonthTotals {
+    month: String, // %Y%m
+    by_day: HashMap<NaiveDate, SpendSummary>,
+    this_month: SpendSummary,
+    by_model: HashMap<(String, String), SpendSummary>,
+}
+
+impl MonthTotals {
+    fn new(month: String, records: Vec<UsageRecord>) -> Self {
+        let mut totals = Self { month, ..Self::default() };
+        for record in &records {
+            totals.add(record);
+        }
+        totals
+    }
+
+    fn add(&mut self, record: &UsageRecord) {
+        self.this_month.add(record);
+        self.by_day
+            .entry(record.timestamp.with_timezone(&Local).date_naive())
+            .or_default()
+            .add(record);
+        self.by_model
+            .entry((record.api_type.clone(), record.model.clone()))
+            .or_default()
+            .add(record);
+    }
+}
+
+lazy_static! {
+    // 按数据目录保存的当月汇总：首次使用时读取一次JSONL，之后随每条记录更新，
+    // 避免每次专注检测检查预算时都重新解析整月的记录
+    static ref MONTH_TOTALS: Mutex<HashMap<PathBuf, MonthTotals>> = Mutex::new(HashMap::new());
+}
+
+/// AI调用用量记录，按月保存为JSONL文件
+pub struct UsageService {
+    data_dir: PathBuf,
+}
+
+impl Default for UsageService {
+    fn default() -> Self {
+        // 与其他服务一致，使用应用本地目录
+        Self::new(PathBuf::from("data"))
+    }
+}
+
+impl UsageService {
+    pub fn new(data_dir: PathBuf) -> Self {
+        Self { data_dir }
+    }
+
+    fn month_file(data_dir: &Path, time: DateTime<Local>) -> PathBuf {
+        data_dir.join(format!("ai_usage_{}.jsonl", time.format("%Y%m")))
+    }
+
+    /// 追加一条调用记录：先更新内存中的汇总，再在阻塞线程中写入文件
+    pub async fn record(&self, record: UsageRecord) -> Result<()> {
+        let month = record.timestamp.with_timezone(&Local).format("%Y%m").to_string();
+        self.with_totals(|totals| {
+            if totals.month == month {
+                totals.add(&record);
+            }
+        });
+
+        let data_dir = self.data_dir.clone();
+        tokio::task::spawn_blocking(move || -> Result<()> {
+            if !data_dir.exists() {
+                fs::create_dir_all(&data_dir)?;
+            }
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(Self::month_file(&data_dir, record.timestamp.with_timezone(&Local)))?;
+            writeln!(file, "{}", serde_json::to_string(&record)?)?;
+            Ok(())
+        })
+        .await?
+    }
+
+    /// 读取本月的调用记录，跳过无法解析的行
+    fn load_this_month(&self) -> Vec<UsageRecord> {
+        let Ok(content) = fs::read_to_string(Self::month_file(&self.data_dir, Local::now())) else {
+            return Vec::new();
+        };
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()
+    }
+
+    /// 访问当月汇总，首次访问或跨月后从文件重新加载
+    fn with_totals<R>(&self, f: impl FnOnce(&mut MonthTotals) -> R) -> R {
+        let month = Local::now().format("%Y%m").to_string();
+        let mut all_totals = MONTH_TOTALS.lock().unwrap_or_else(|e| e.into_inner());
+        if all_totals.get(&self.data_dir).map_or(true, |totals| totals.month != month) {
+            let totals = MonthTotals::new(month, self.load_this_month());
+            all_totals.insert(self.data_dir.clone(), totals);
+        }
+        let totals = all_totals.entry(self.data_dir.clone()).or_default();
+        f(totals)
+    }
+
+    /// 汇总今日和本月的用量
+    pub fn spend_report(&self, config: &AIConfig) -> SpendReport {
+        let today = Local::now().date_naive();
+        let mut report = SpendReport {
+            today: SpendSummary::default(),
+            this_month: SpendSummary::default(),
+            daily_limit: config.budget.daily_limit,
+            monthly_limit: config.budget.monthly_limit,
+            budget_exceeded: None,
+            by_model: Vec::new(),
+        };
+
+        self.with_totals(|totals| {
+            report.this_month = totals.this_month.clone();
+            report.today = totals.by_day.get(&today).cloned().unwrap_or_default();
+            report.by_model = totals.by_model
+                .iter()
+                .map(|((api_type, model), summary)| ModelSpend {
+                    api_type: api_type.clone(),
+                    model: model.clone(),
+                    summary: summary.clone(),
+                })
+                .collect();
+        });
+        report.by_model.sort_by(|a, b| b.summary.cost.total_cmp(&a.summary.cost));
+
+        report.budget_exceeded = match (config.budget.daily_limit, config.budget.monthly_limit) {
+            (Some(limit), _) if report.today.cost >= limit => {
+                Some(format!("今日AI费用 ${:.4} 已达到每日预算 ${:.2}", report.today.cost, limit))
+            }
+            (_, Some(limit)) if report.this_month.cost >= limit => {
+                Some(format!("本月AI费用 ${:.4} 已达到每月预算 ${:.2}", report.this_month.cost, limit))
+            }
+            _ => None,
+        };
+        report
+    }
+
+    /// 超出预算时返回说明；没有设置预算时不读取记录
+    pub fn budget_exceeded(&self, config: &AIConfig) -> Option<String> {
+        if config.budget.daily_limit.is_none() && config.budget.monthly_limit.is_none() {
+            return None;
+        }
+        self.spend_report(config).budget_exceeded
+    }
+
+    /// 专注检测在当前预算下的处理方式
+    pub fn detection_budget_mode(&self, config: &AIConfig) -> BudgetMode {
+        let Some(reason) = self.budget_exceeded(config) else {
+            return BudgetMode::Normal;
+        };
+        match config.local_only("detection") {
+            Some(local) => BudgetMode::LocalOnly(Box::new(local)),
+            None => BudgetMode::RuleOnly(reason),
+        }
+    }
+}
+
+/// 按价格表计算费用，返回 (费用, 是否已计价)
+pub fn calculate_cost(prices: &[ModelPrice], local: bool, model: &str, prompt_tokens: u64, completion_tokens: u64) -> (f64, bool) {
+    match prices.iter().find(|price| price.matches(model)) {
+        Some(price) => {
+            let cost = prompt_tokens as f64 / 1_000_000.0 * price.input_per_million
+                + completion_tokens as f64 / 1_000_000.0 * price.output_per_million;
+            (cost, true)
+        }
+        // 本地模型不计费
+        None if local => (0.0, true),
+        None => (0.0, false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(model: &str, cost: f64) -> UsageRecord {
+        UsageRecord {
+            timestamp: Utc::now(),
+            api_type: "OpenAI Compatible".to_string(),
+            model: model.to_string(),
+            model_type: "detection".to_string(),
+            prompt_tokens: 100,
+            completion_tokens: 20,
+            estimated: false,
+            latency_ms: 300,
+            cost,
+            priced: true,
+            fallback: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn keeps_running_totals_and_appends_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = UsageService::new(dir.path().to_path_buf());
+        let mut config = AIConfig::default();
+        config.budget.daily_limit = Some(0.5);
+
+        service.record(record("gpt-4o-mini", 0.2)).await.unwrap();
+        assert_eq!(service.budget_exceeded(&config), None);
+        service.record(record("gpt-4o", 0.4)).await.unwrap();
+
+        let report = service.spend_report(&config);
+        assert_eq!(report.today.calls, 2);
+        assert_eq!(report.this_month.prompt_tokens, 200);
+        assert_eq!(report.by_model[0].model, "gpt-4o");
+        assert!(report.budget_exceeded.is_some());
+
+        // 文件中的记录与内存汇总一致
+        assert_eq!(service.load_this_month().len(), 2);
+    }
+}
@@ -0,0 +1,565 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// 窗口在虚拟桌面中的位置和大小（像素）
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindowBounds {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl WindowBounds {
+    /// 窗口中心点
+    pub fn center(&self) -> (i32, i32) {
+        (self.x + self.width as i32 / 2, self.y + self.height as i32 / 2)
+    }
+}
+
+/// 前台窗口信息
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ForegroundWindow {
+    pub app_name: Option<String>,
+    pub window_title: Option<String>,
+    pub process_id: Option<u32>,
+    pub bounds: Option<WindowBounds>,
+}
+
+/// 平台相关的前台窗口信息提供者
+pub trait WindowInfoProvider {
+    /// 提供者名称（用于日志）
+    fn name(&self) -> &'static str;
+
+    /// 获取当前前台窗口信息
+    fn foreground_window(&self) -> Result<ForegroundWindow>;
+}
+
+/// 根据当前平台创建窗口信息提供者
+///
+/// 提供者内部可能持有非线程安全的句柄，应在 `spawn_blocking` 线程内创建并使用。
+pub fn platform_provider() -> Box<dyn WindowInfoProvider> {
+    #[cfg(windows)]
+    {
+        Box::new(WindowsWindowInfoProvider)
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        match X11WindowInfoProvider::new() {
+            Ok(provider) => Box::new(provider),
+            Err(e) => {
+                println!("⚠️ 无法初始化X11窗口信息提供者: {}", e);
+                Box::new(UnsupportedWindowInfoProvider)
+            }
+        }
+    }
+
+    #[cfg(not(any(windows, target_os = "linux")))]
+    {
+        Box::new(UnsupportedWindowInfoProvider)
+    }
+}
+
+/// 不支持的平台：不返回任何窗口信息
+pub struct UnsupportedWindowInfoProvider;
+
+impl WindowInfoProvider for UnsupportedWindowInfoProvider {
+    fn name(&self) -> &'static str {
+        "unsupported"
+    }
+
+    fn foreground_window(&self) -> Result<ForegroundWindow> {
+        println!("⚠️ 当前平台不支持获取前台窗口信息");
+        Ok(ForegroundWindow::default())
+    }
+}
+
+// ===== Windows 实现 =====
+
+#[cfg(windows)]
+pub struct WindowsWindowInfoProvider;
+
+#[cfg(windows)]
+impl WindowInfoProvider for WindowsWindowInfoProvider {
+    fn name(&self) -> &'static str {
+        "win32"
+    }
+
+    fn foreground_window(&self) -> Result<ForegroundWindow> {
+        unsafe {
+            use winapi::um::winuser::{GetForegroundWindow, GetWindowRect, GetWindowTextW, GetWindowThreadProcessId};
+            use winapi::shared::windef::RECT;
+            use winapi::um::processthreadsapi::OpenProcess;
+            use winapi::um::psapi::GetModuleBaseNameW;
+            use winapi::um::winnt::{PROCESS_QUERY_INFORMATION, PROCESS_VM_READ};
+            use winapi::um::handleapi::CloseHandle;
+
+            let hwnd = GetForegroundWindow();
+            if hwnd.is_null() {
+                println!("⚠️ 无法获取前台窗口");
+                return Ok(ForegroundWindow::default());
+            }
+
+            // 获取窗口标题
+            let mut window_title = vec![0u16; 256];
+            let title_len = GetWindowTextW(hwnd, window_title.as_mut_ptr(), 256);
+            let window_title_str = if title_len > 0 {
+                // 标题由调用方在隐私排除检查之后再输出
+                Some(String::from_utf16_lossy(&window_title[..title_len as usize]))
+            } else {
+                println!("⚠️ 无法获取窗口标题");
+                None
+            };
+
+            // 获取窗口位置
+            let mut rect = RECT { left: 0, top: 0, right: 0, bottom: 0 };
+            let bounds = if GetWindowRect(hwnd, &mut rect) != 0 && rect.right > rect.left && rect.bottom > rect.top {
+                Some(WindowBounds {
+                    x: rect.left,
+                    y: rect.top,
+                    width: (rect.right - rect.left) as u32,
+                    height: (rect.bottom - rect.top) as u32,
+                })
+            } else {
+                None
+            };
+
+            // 获取进程ID
+            let mut process_id = 0u32;
+            GetWindowThreadProcessId(hwnd, &mut process_id);
+
+            if process_id == 0 {
+                println!("⚠️ 无法获取进程ID");
+                return Ok(ForegroundWindow {
+                    window_title: window_title_str,
+                    bounds,
+                    ..Default::default()
+                });
+            }
+
+            // 打开进程
+            let process_handle = OpenProcess(
+                PROCESS_QUERY_INFORMATION | PROCESS_VM_READ,
+                0,
+                process_id
+            );
+
+            if process_handle.is_null() {
+                println!("⚠️ 无法打开进程 (PID: {})", process_id);
+                return Ok(ForegroundWindow {
+                    window_title: window_title_str,
+                    process_id: Some(process_id),
+                    bounds,
+                    ..Default::default()
+                });
+            }
+
+            // 获取进程名称
+            let mut process_name = vec![0u16; 256];
+            let name_len = GetModuleBaseNameW(
+                process_handle,
+                std::ptr::null_mut(),
+                process_name.as_mut_ptr(),
+                256
+            );
+
+            CloseHandle(process_handle);
+
+            let app_name = if name_len > 0 {
+                let name = String::from_utf16_lossy(&process_name[..name_len as usize]);
+                println!("📋 应用程序: {} (PID: {})", name, process_id);
+                Some(name)
+            } else {
+                println!("⚠️ 无法获取进程名称");
+                None
+            };
+
+            Ok(ForegroundWindow {
+                app_name,
+                window_title: window_title_str,
+                process_id: Some(process_id),
+                bounds,
+            })
+        }
+    }
+}
+
+// ===== Linux (X11) 实现 =====
+
+/// 基于 EWMH 属性的 X11 前台窗口信息提供者
+///
+/// 通过 `_NET_ACTIVE_WINDOW` 定位前台窗口，读取 `_NET_WM_NAME`（回退到 `WM_NAME`）
+/// 作为窗口标题，并用 `_NET_WM_PID` 对应的 `/proc/<pid>/comm` 作为应用名称。
+/// libX11 在运行时动态加载，没有X服务器时返回错误而不会崩溃。
+/// 在 Xvfb 下测试时，设置 `DISPLAY`（如 `:99`）或使用 `with_display` 即可。
+#[cfg(target_os = "linux")]
+pub struct X11WindowInfoProvider {
+    xlib: x11_dl::xlib::Xlib,
+    display_name: Option<std::ffi::CString>,
+}
+
+#[cfg(target_os = "linux")]
+impl X11WindowInfoProvider {
+    /// 使用 `DISPLAY` 环境变量指定的显示
+    pub fn new() -> Result<Self> {
+        let xlib = x11_dl::xlib::Xlib::open()
+            .map_err(|e| anyhow::anyhow!("加载libX11失败: {}", e))?;
+        Ok(Self { xlib, display_name: None })
+    }
+
+    /// 使用指定的显示（例如 Xvfb 的 `:99`）
+    pub fn with_display(display: &str) -> Result<Self> {
+        let mut provider = Self::new()?;
+        provider.display_name = Some(
+            std::ffi::CString::new(display)
+                .map_err(|e| anyhow::anyhow!("无效的显示名称: {}", e))?
+        );
+        Ok(provider)
+    }
+
+    /// 读取窗口属性的原始数据，返回 (实际类型, 格式, 数据)
+    unsafe fn get_property(
+        &self,
+        display: *mut x11_dl::xlib::Display,
+        window: x11_dl::xlib::Window,
+        property: x11_dl::xlib::Atom,
+        req_type: x11_dl::xlib::Atom,
+    ) -> Option<(x11_dl::xlib::Atom, i32, Vec<u8>)> {
+        use std::os::raw::{c_int, c_uchar, c_ulong};
+
+        let mut actual_type: c_ulong = 0;
+        let mut actual_format: c_int = 0;
+        let mut nitems: c_ulong = 0;
+        let mut bytes_after: c_ulong = 0;
+        let mut data: *mut c_uchar = std::ptr::null_mut();
+
+        let status = (self.xlib.XGetWindowProperty)(
+            display,
+            window,
+            property,
+            0,
+            4096,
+            x11_dl::xlib::False,
+            req_type,
+            &mut actual_type,
+            &mut actual_format,
+            &mut nitems,
+            &mut bytes_after,
+            &mut data,
+        );
+
+        if status != x11_dl::xlib::Success as c_int || data.is_null() {
+            return None;
+        }
+
+        // 32位格式的属性在客户端以 c_long 数组存储
+        let item_size = match actual_format {
+            8 => 1,
+            16 => std::mem::size_of::<std::os::raw::c_short>(),
+            32 => std::mem::size_of::<std::os::raw::c_long>(),
+            _ => 0,
+        };
+        let bytes = std::slice::from_raw_parts(data, nitems as usize * item_size).to_vec();
+        (self.xlib.XFree)(data as *mut _);
+
+        if actual_type == 0 || bytes.is_empty() {
+            None
+        } else {
+            Some((actual_type, actual_format, bytes))
+        }
+    }
+
+    /// 读取单个32位属性值（窗口ID、PID等）
+    unsafe fn get_u32_property(
+        &self,
+        display: *mut x11_dl::xlib::Display,
+        window: x11_dl::xlib::Window,
+        property: x11_dl::xlib::Atom,
+        req_type: x11_dl::xlib::Atom,
+    ) -> Option<u64> {
+        let (_, format, bytes) = self.get_property(display, window, property, req_type)?;
+        if format != 32 {
+            return None;
+        }
+        decode_u32_property(&bytes)
+    }
+
+    /// 读取窗口标题：优先 `_NET_WM_NAME` (UTF-8)，回退到 `WM_NAME`
+    unsafe fn get_window_title(
+        &self,
+        display: *mut x11_dl::xlib::Display,
+        window: x11_dl::xlib::Window,
+    ) -> Option<String> {
+        let net_wm_name = self.intern_atom(display, "_NET_WM_NAME");
+        let utf8_string = self.intern_atom(display, "UTF8_STRING");
+
+        if let Some((_, 8, bytes)) = self.get_property(display, window, net_wm_name, utf8_string) {
+            if let Some(title) = decode_wm_name(&bytes) {
+                return Some(title);
+            }
+        }
+
+        let mut name: *mut std::os::raw::c_char = std::ptr::null_mut();
+        if (self.xlib.XFetchName)(display, window, &mut name) != 0 && !name.is_null() {
+            let title = std::ffi::CStr::from_ptr(name).to_string_lossy().to_string();
+            (self.xlib.XFree)(name as *mut _);
+            if !title.is_empty() {
+                return Some(title);
+            }
+        }
+
+        None
+    }
+
+    /// 读取窗口相对根窗口的位置和大小
+    unsafe fn get_window_bounds(
+        &self,
+        display: *mut x11_dl::xlib::Display,
+        root: x11_dl::xlib::Window,
+        window: x11_dl::xlib::Window,
+    ) -> Option<WindowBounds> {
+        let mut geometry_root: x11_dl::xlib::Window = 0;
+        let (mut x, mut y) = (0, 0);
+        let (mut width, mut height, mut border, mut depth) = (0, 0, 0, 0);
+        if (self.xlib.XGetGeometry)(
+            display, window, &mut geometry_root,
+            &mut x, &mut y, &mut width, &mut height, &mut border, &mut depth,
+        ) == 0 {
+            return None;
+        }
+
+        // 窗口管理器通常会重新设置父窗口，需要换算到根窗口坐标
+        let (mut root_x, mut root_y) = (0, 0);
+        let mut child: x11_dl::xlib::Window = 0;
+        if (self.xlib.XTranslateCoordinates)(display, window, root, 0, 0, &mut root_x, &mut root_y, &mut child) == 0 {
+            return None;
+        }
+
+        if width == 0 || height == 0 {
+            None
+        } else {
+            Some(WindowBounds { x: root_x, y: root_y, width, height })
+        }
+    }
+
+    unsafe fn intern_atom(&self, display: *mut x11_dl::xlib::Display, name: &str) -> x11_dl::xlib::Atom {
+        let c_name = std::ffi::CString::new(name).unwrap_or_default();
+        (self.xlib.XInternAtom)(display, c_name.as_ptr(), x11_dl::xlib::False)
+    }
+
+    unsafe fn query_foreground_window(&self, display: *mut x11_dl::xlib::Display) -> Result<ForegroundWindow> {
+        let root = (self.xlib.XDefaultRootWindow)(display);
+        let net_active_window = self.intern_atom(display, "_NET_ACTIVE_WINDOW");
+        let net_wm_pid = self.intern_atom(display, "_NET_WM_PID");
+
+        let window = match self.get_u32_property(display, root, net_active_window, x11_dl::xlib::XA_WINDOW) {
+            Some(window) if window != 0 => window as x11_dl::xlib::Window,
+            _ => {
+                println!("⚠️ 窗口管理器未提供 _NET_ACTIVE_WINDOW");
+                return Ok(ForegroundWindow::default());
+            }
+        };
+
+        let window_title = self.get_window_title(display, window);
+        let process_id = self
+            .get_u32_property(display, window, net_wm_pid, x11_dl::xlib::XA_CARDINAL)
+            .map(|pid| pid as u32);
+        let app_name = process_id.and_then(read_process_name);
+        let bounds = self.get_window_bounds(display, root, window);
+
+        match (&app_name, process_id) {
+            (Some(name), Some(pid)) => println!("📋 应用程序: {} (PID: {})", name, pid),
+            _ => println!("⚠️ 无法获取进程名称"),
+        }
+
+        Ok(ForegroundWindow {
+            app_name,
+            window_title,
+            process_id,
+            bounds,
+        })
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl WindowInfoProvider for X11WindowInfoProvider {
+    fn name(&self) -> &'static str {
+        "x11"
+    }
+
+    fn foreground_window(&self) -> Result<ForegroundWindow> {
+        /// 忽略X错误（例如查询期间窗口已被销毁），避免默认处理器终止进程
+        unsafe extern "C" fn ignore_x_error(
+            _display: *mut x11_dl::xlib::Display,
+            _event: *mut x11_dl::xlib::XErrorEvent,
+        ) -> std::os::raw::c_int {
+            0
+        }
+
+        unsafe {
+            let display_ptr = self
+                .display_name
+                .as_ref()
+                .map(|name| name.as_ptr())
+                .unwrap_or(std::ptr::null());
+            let display = (self.xlib.XOpenDisplay)(display_ptr);
+            if display.is_null() {
+                return Err(anyhow::anyhow!("无法连接X服务器，请检查DISPLAY环境变量"));
+            }
+
+            let previous_handler = (self.xlib.XSetErrorHandler)(Some(ignore_x_error));
+            let result = self.query_foreground_window(display);
+            (self.xlib.XSync)(display, x11_dl::xlib::False);
+            (self.xlib.XSetErrorHandler)(previous_handler);
+            (self.xlib.XCloseDisplay)(display);
+
+            result
+        }
+    }
+}
+
+/// 从 `/proc/<pid>/comm` 读取进程名称
+#[cfg(target_os = "linux")]
+fn read_process_name(pid: u32) -> Option<String> {
+    std::fs::read_to_string(format!("/proc/{}/comm", pid))
+        .ok()
+        .and_then(|comm| parse_process_comm(&comm))
+}
+
+/// 解析 `/proc/<pid>/comm` 的内容（末尾带换行）
+#[cfg(target_os = "linux")]
+fn parse_process_comm(comm: &str) -> Option<String> {
+    let name = comm.trim();
+    if name.is_empty() {
+        None
+    } else {
+        Some(name.to_string())
+    }
+}
+
+/// 解码 `_NET_WM_NAME` 属性：UTF-8 文本，可能带有结尾的NUL
+#[cfg(target_os = "linux")]
+fn decode_wm_name(bytes: &[u8]) -> Option<String> {
+    let title = String::from_utf8_lossy(bytes).trim_end_matches('\0').to_string();
+    if title.is_empty() {
+        None
+    } else {
+        Some(title)
+    }
+}
+
+/// 解码32位格式属性的第一个值
+///
+/// Xlib 在客户端以 `c_long` 存储32位属性，64位系统上高位需要截掉。
+#[cfg(target_os = "linux")]
+fn decode_u32_property(bytes: &[u8]) -> Option<u64> {
+    const LONG_SIZE: usize = std::mem::size_of::<std::os::raw::c_long>();
+
+    if bytes.len() < LONG_SIZE {
+        return None;
+    }
+    let mut raw = [0u8; LONG_SIZE];
+    raw.copy_from_slice(&bytes[..LONG_SIZE]);
+    Some(std::os::raw::c_long::from_ne_bytes(raw) as u64 & 0xFFFF_FFFF)
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_process_comm() {
+        assert_eq!(parse_process_comm("firefox\n"), Some("firefox".to_string()));
+        assert_eq!(parse_process_comm("  code  \n"), Some("code".to_string()));
+        assert_eq!(parse_process_comm("\n"), None);
+    }
+
+    #[test]
+    fn reads_own_process_name() {
+        assert!(read_process_name(std::process::id()).is_some());
+        assert_eq!(read_process_name(u32::MAX), None);
+    }
+
+    #[test]
+    fn decodes_wm_name() {
+        assert_eq!(decode_wm_name("终端 - bash\0".as_bytes()), Some("终端 - bash".to_string()));
+        assert_eq!(decode_wm_name(b"Editor"), Some("Editor".to_string()));
+        assert_eq!(decode_wm_name(b"\0\0"), None);
+        assert_eq!(decode_wm_name(b""), None);
+    }
+
+    #[test]
+    fn decodes_u32_property() {
+        let value: std::os::raw::c_long = 0x0420_0001;
+        assert_eq!(decode_u32_property(&value.to_ne_bytes()), Some(0x0420_0001));
+
+        // 符号扩展后的高位被截掉
+        let negative: std::os::raw::c_long = -1;
+        assert_eq!(decode_u32_property(&negative.to_ne_bytes()), Some(0xFFFF_FFFF));
+
+        assert_eq!(decode_u32_property(&[1, 2]), None);
+    }
+
+    /// 需要运行中的X服务器（不需要窗口管理器），例如：
+    /// `Xvfb :99 & X11_TEST_DISPLAY=:99 cargo test -- --ignored x11`
+    ///
+    /// 测试自己创建窗口，并像窗口管理器一样设置 `_NET_ACTIVE_WINDOW`、`_NET_WM_NAME` 和 `_NET_WM_PID`。
+    #[test]
+    #[ignore]
+    fn x11_provider_against_xvfb() {
+        use std::os::raw::{c_int, c_long};
+        use x11_dl::xlib;
+
+        let display_name = std::env::var("X11_TEST_DISPLAY").unwrap_or_else(|_| ":99".to_string());
+        let provider = X11WindowInfoProvider::with_display(&display_name).expect("加载libX11失败");
+        let x = &provider.xlib;
+        let title = "专注测试 – Xvfb";
+        let pid = std::process::id();
+
+        unsafe {
+            let c_display = std::ffi::CString::new(display_name.as_str()).unwrap();
+            let display = (x.XOpenDisplay)(c_display.as_ptr());
+            assert!(!display.is_null(), "无法连接X服务器 {}", display_name);
+            let root = (x.XDefaultRootWindow)(display);
+            let window = (x.XCreateSimpleWindow)(display, root, 10, 20, 200, 100, 0, 0, 0);
+
+            let net_wm_name = provider.intern_atom(display, "_NET_WM_NAME");
+            let utf8_string = provider.intern_atom(display, "UTF8_STRING");
+            let net_wm_pid = provider.intern_atom(display, "_NET_WM_PID");
+            let net_active_window = provider.intern_atom(display, "_NET_ACTIVE_WINDOW");
+
+            (x.XChangeProperty)(
+                display, window, net_wm_name, utf8_string, 8, xlib::PropModeReplace,
+                title.as_ptr(), title.len() as c_int,
+            );
+            // 32位格式的属性数据按 c_long 传递
+            let pid_value = pid as c_long;
+            (x.XChangeProperty)(
+                display, window, net_wm_pid, xlib::XA_CARDINAL, 32, xlib::PropModeReplace,
+                &pid_value as *const c_long as *const u8, 1,
+            );
+            (x.XMapWindow)(display, window);
+            let window_value = window as c_long;
+            (x.XChangeProperty)(
+                display, root, net_active_window, xlib::XA_WINDOW, 32, xlib::PropModeReplace,
+                &window_value as *const c_long as *const u8, 1,
+            );
+            (x.XSync)(display, xlib::False);
+
+            let foreground = provider.foreground_window();
+
+            (x.XDeleteProperty)(display, root, net_active_window);
+            (x.XDestroyWindow)(display, window);
+            (x.XCloseDisplay)(display);
+
+            let foreground = foreground.expect("读取前台窗口失败");
+            assert_eq!(foreground.window_title.as_deref(), Some(title));
+            assert_eq!(foreground.process_id, Some(pid));
+            let comm = std::fs::read_to_string("/proc/self/comm").unwrap();
+            assert_eq!(foreground.app_name.as_deref(), Some(comm.trim()));
+            let bounds = foreground.bounds.expect("没有窗口位置");
+            assert_eq!((bounds.x, bounds.y, bounds.width, bounds.height), (10, 20, 200, 100));
+        }
+    }
+}
@@ -11,6 +11,8 @@ let pauseTimer = null;
 let pauseCountdown = 0;
 let currentFocusState = 'idle'; // idle, focused, distracted, severely_distracted
 let currentSelectedTask = null;
+let savedAIConfig = {}; // 最近加载的AI配置，保留界面上没有的字段
+let availableModels = []; // 最近获取的模型列表（含上下文长度、图像输入等元数据）
 
 // 模态框事件处理器
 let modalKeyPressHandler = null;
@@ -55,6 +57,15 @@ const statusConfig = {
         textColor: 'text-red-400',
         statusTextColor: 'text-white',
         animation: 'animate-pulse'
+    },
+    ai_unavailable: {
+        icon: 'fas fa-plug',
+        text: 'AI服务不可用',
+        color: 'orange',
+        bgColor: 'bg-orange-500/10',
+        borderColor: 'border-orange-500',
+        textColor: 'text-orange-400',
+        statusTextColor: 'text-white'
     }
 };
 
@@ -89,6 +100,9 @@ async function initApp() {
         // 加载今日统计数据
         updateDashboardWithRealData();
         
+        // 加载OCR设置并检测已安装的语言
+        loadOcrSettings();
+        
     } catch (error) {
         console.error('应用初始化失败:', error);
     }
@@ -117,6 +131,11 @@ async function initTauriEvents() {
             showDistractionIntervention(event.payload);
         });
         
+        // 监听报告生成过程中的AI洞察片段
+        await TauriEvents.onReportChunk((event) => {
+            appendReportChunk(event.payload);
+        });
+        
     } catch (error) {
         console.error('事件监听初始化失败:', error);
     }
@@ -617,7 +636,7 @@ function initSettings() {
         
         // 自动保存AI配置到后端
         const aiConfig = getCurrentAIConfig();
-        await TauriAPI.saveAIConfig(aiConfig);
+        warnIfSecretsNotPersisted(await TauriAPI.saveAIConfig(aiConfig));
         
         // 显示保存成功提示
         showNotification('设置已保存', '用户设置和AI配置均已自动保存', 'success', false);
@@ -1326,10 +1345,19 @@ function getDefaultAPIUrls() {
     return {
         'OpenAI Compatible': 'https://api.openai.com/v1',
         'Ollama (本地)': 'http://localhost:11434/v1',
-        'Claude API': 'https://api.anthropic.com/v1'
+        'Claude API': 'https://api.anthropic.com/v1',
+        'Google Gemini': 'https://generativelanguage.googleapis.com/v1beta',
+        'Azure OpenAI': 'https://YOUR-RESOURCE.openai.azure.com'
     };
 }
 
+/**
+ * 该API类型是否必须填写API Key（本地Ollama不需要）
+ */
+function apiTypeRequiresKey(apiType) {
+    return apiType !== 'Ollama (本地)';
+}
+
 /**
  * 清除API测试结果和模型列表
  */
@@ -1380,7 +1408,7 @@ async function testAPIConnection() {
     // 获取当前配置
     const config = getCurrentAIConfig();
     
-    if (!config.api_key.trim()) {
+    if (apiTypeRequiresKey(config.api_type) && !config.api_key.trim()) {
         showTestResult({
             success: false,
             message: 'API Key不能为空',
@@ -1438,6 +1466,7 @@ function showTestResult(result) {
             <div class="flex-grow">
                 <p class="${statusClass} font-medium">${result.message}</p>
                 ${result.response_time_ms > 0 ? `<p class="text-xs text-gray-400 mt-1">响应时间: ${result.response_time_ms}ms</p>` : ''}
+                ${result.answered_by ? `<p class="text-xs text-yellow-400 mt-1">已切换到备用服务: ${result.answered_by.api_type} / ${result.answered_by.model}</p>` : ''}
             </div>
         </div>
     `;
@@ -1497,10 +1526,31 @@ async function loadAvailableModels() {
     }
 }
 
+/**
+ * 模型下拉框中显示的名称，附带参数规模、上下文长度和图像输入能力
+ */
+function formatModelLabel(model) {
+    const details = [];
+    if (model.parameter_size) details.push(model.parameter_size);
+    if (model.context_length) {
+        details.push(model.context_length >= 1000
+            ? `${Math.round(model.context_length / 1000)}K上下文`
+            : `${model.context_length}上下文`);
+    }
+    if (model.supports_vision) details.push('视觉');
+    
+    const name = model.display_name && model.display_name !== model.id
+        ? `${model.id} - ${model.display_name}`
+        : model.id;
+    return details.length > 0 ? `${name} (${details.join(', ')})` : name;
+}
+
 /**
  * 更新模型选择器
  */
 function updateModelSelectors(models) {
+    availableModels = models;
+    
     const detectionSelect = document.getElementById('detection-model');
     const reportSelect = document.getElementById('report-model');
     
@@ -1517,7 +1567,7 @@ function updateModelSelectors(models) {
         models.forEach(model => {
             const option = document.createElement('option');
             option.value = model.id;
-            option.textContent = model.id;
+            option.textContent = formatModelLabel(model);
             select.appendChild(option);
         });
     });
@@ -1530,6 +1580,8 @@ function updateModelSelectors(models) {
     if (models.some(m => m.id === currentReport)) {
         reportSelect.value = currentReport;
     }
+    
+    updateVisionCheckbox();
 }
 
 /**
@@ -1539,7 +1591,7 @@ async function refreshModels(type) {
     console.log(`刷新${type}模型列表`);
     
     const config = getCurrentAIConfig();
-    if (!config.api_key.trim()) {
+    if (apiTypeRequiresKey(config.api_type) && !config.api_key.trim()) {
         showNotification('配置错误', '请先配置并测试API连接');
         return;
     }
@@ -1552,15 +1604,153 @@ async function refreshModels(type) {
  * 获取当前AI配置
  */
 function getCurrentAIConfig() {
+    const detectionModel = document.getElementById('detection-model')?.value || '';
+    
+    // 当前检测模型的图像输入能力由复选框决定，其他模型保持原有标记
+    const visionModels = (savedAIConfig.vision_models || []).filter(model => model !== detectionModel);
+    if (detectionModel && document.getElementById('detection-model-vision')?.checked) {
+        visionModels.push(detectionModel);
+    }
+    
     return {
+        ...savedAIConfig,
         api_type: document.getElementById('api-type')?.value || 'OpenAI Compatible',
         api_url: document.getElementById('api-url')?.value || 'https://api.openai.com/v1',
         api_key: document.getElementById('api-key')?.value || '',
-        detection_model: document.getElementById('detection-model')?.value || '',
-        report_model: document.getElementById('report-model')?.value || ''
+        detection_model: detectionModel,
+        report_model: document.getElementById('report-model')?.value || '',
+        vision_models: visionModels,
+        budget: {
+            daily_limit: parseBudgetInput('budget-daily'),
+            monthly_limit: parseBudgetInput('budget-monthly')
+        }
     };
 }
 
+/**
+ * 读取预算输入框，空值或无效值表示不限制
+ */
+function parseBudgetInput(inputId) {
+    const value = parseFloat(document.getElementById(inputId)?.value);
+    return Number.isFinite(value) && value >= 0 ? value : null;
+}
+
+/**
+ * 更新AI花费摘要
+ */
+async function updateAISpendSummary() {
+    const summary = document.getElementById('ai-spend-summary');
+    if (!summary) return;
+    
+    try {
+        const report = await TauriAPI.getAISpendReport();
+        let text = `今日花费 $${report.today.cost.toFixed(4)}（${report.today.calls} 次调用）· 本月花费 $${report.this_month.cost.toFixed(4)}（${report.this_month.calls} 次调用）`;
+        if (report.this_month.unpriced_calls > 0) {
+            text += ` · ${report.this_month.unpriced_calls} 次调用的模型不在价格表中`;
+        }
+        if (report.budget_exceeded) {
+            text += ` · ${report.budget_exceeded}`;
+        }
+        summary.textContent = text;
+        summary.className = report.budget_exceeded ? 'text-xs text-orange-400 mb-4' : 'text-xs text-gray-400 mb-4';
+    } catch (error) {
+        console.error('获取AI花费失败:', error);
+    }
+}
+
+/**
+ * 日志中隐藏API密钥（包括备用服务和监控配置中的AI配置）
+ */
+function redactSecretsForLog(value) {
+    return JSON.parse(JSON.stringify(value ?? null, (key, item) =>
+        key === 'api_key' && item ? '***' : item
+    ));
+}
+
+// 本次运行中是否已经提示过API密钥未持久保存，避免自动保存时反复弹出
+let secretsNotPersistedWarned = false;
+
+/**
+ * 保存结果表明API密钥只保存在内存中时，提示用户解锁或创建密钥库
+ */
+function warnIfSecretsNotPersisted(result) {
+    if (result?.secrets_persisted !== false) {
+        secretsNotPersistedWarned = false;
+        return;
+    }
+    updateSecretStorageStatus();
+    if (secretsNotPersistedWarned) return;
+    secretsNotPersistedWarned = true;
+    showNotification('API Key 未持久保存', '系统密钥服务不可用且本地密钥库未解锁，API Key 仅在本次运行中有效，请解锁或创建密钥库', 'warning');
+}
+
+/**
+ * 显示API密钥的保存位置；系统密钥服务不可用时提供密钥库解锁
+ */
+async function updateSecretStorageStatus() {
+    const statusText = document.getElementById('secret-storage-status');
+    const unlockRow = document.getElementById('secret-vault-unlock');
+    if (!statusText) return;
+    
+    const status = await TauriAPI.getSecretStorageStatus();
+    if (!status || status.success === false) return;
+    
+    let text;
+    let warning = false;
+    if (status.keyring_available) {
+        text = 'API Key 保存在系统密钥服务中，不写入配置文件';
+    } else if (status.vault_unlocked) {
+        text = 'API Key 保存在本地加密密钥库中，不写入配置文件';
+    } else if (status.vault_exists) {
+        text = '系统密钥服务不可用，请输入口令解锁本地密钥库以读取已保存的 API Key';
+        warning = true;
+    } else {
+        text = '系统密钥服务不可用，API Key 仅在本次运行中有效；设置口令创建本地加密密钥库以保存';
+        warning = true;
+    }
+    if (status.plaintext_pending?.length) {
+        // 旧版本的明文密钥无法安全迁移，仍保留在配置文件中
+        text += '。配置文件中仍有旧版本明文保存的 API Key，解锁或创建密钥库后将自动迁移';
+        warning = true;
+    }
+    statusText.textContent = text;
+    statusText.className = warning ? 'text-xs text-orange-400 mt-1' : 'text-xs text-gray-400 mt-1';
+    unlockRow?.classList.toggle('hidden', status.keyring_available || status.vault_unlocked);
+}
+
+/**
+ * 解锁（首次使用时创建）本地密钥库，然后重新读取AI配置
+ */
+async function unlockSecretVault() {
+    const input = document.getElementById('secret-vault-passphrase');
+    const passphrase = input?.value || '';
+    if (!passphrase) {
+        showNotification('解锁失败', '请输入密钥库口令');
+        return;
+    }
+    
+    const status = await TauriAPI.unlockSecretVault(passphrase);
+    if (!status || status.success === false) return;
+    
+    input.value = '';
+    showNotification('密钥库已解锁', 'API Key 将保存在本地加密密钥库中');
+    await loadAIConfig();
+}
+
+/**
+ * 根据已保存的能力标记更新“支持图像输入”复选框
+ */
+function updateVisionCheckbox() {
+    const checkbox = document.getElementById('detection-model-vision');
+    const detectionModel = document.getElementById('detection-model')?.value || '';
+    if (checkbox) {
+        // 已保存的标记优先，其次使用模型列表中的能力信息
+        const modelInfo = availableModels.find(model => model.id === detectionModel);
+        checkbox.checked = (savedAIConfig.vision_models || []).includes(detectionModel)
+            || modelInfo?.supports_vision === true;
+    }
+}
+
 /**
  * 初始化AI设置的自动保存
  */
@@ -1570,32 +1760,46 @@ function initAISettingsAutoSave() {
         'api-url', 
         'api-key',
         'detection-model',
-        'report-model'
+        'detection-model-vision',
+        'report-model',
+        'budget-daily',
+        'budget-monthly'
     ];
     
     aiInputs.forEach(inputId => {
         const element = document.getElementById(inputId);
         if (element) {
-            if (element.type === 'password' || element.type === 'text' || element.type === 'url') {
+            if (element.type === 'password' || element.type === 'text' || element.type === 'url' || element.type === 'number') {
                 // 对于输入框，使用 blur 事件避免过于频繁的保存
                 element.addEventListener('blur', autoSaveAIConfig);
-            } else if (element.tagName === 'SELECT') {
-                // 对于选择框，使用 change 事件立即保存
+            } else if (element.tagName === 'SELECT' || element.type === 'checkbox') {
+                // 对于选择框和复选框，使用 change 事件立即保存
                 element.addEventListener('change', autoSaveAIConfig);
             }
         }
     });
+    
+    // 切换检测模型时显示该模型的图像输入能力
+    document.getElementById('detection-model')?.addEventListener('change', updateVisionCheckbox);
 }
 
 /**
  * 自动保存AI配置（无需通知）
  */
-async function autoSaveAIConfig() {
+async function autoSaveAIConfig(event) {
+    // 切换检测模型时先同步复选框，避免把上一个模型的标记带到新模型
+    if (event?.target?.id === 'detection-model') {
+        updateVisionCheckbox();
+    }
     const config = getCurrentAIConfig();
     
     try {
-        await TauriAPI.saveAIConfig(config);
-        console.log('AI配置已自动保存:', config);
+        warnIfSecretsNotPersisted(await TauriAPI.saveAIConfig(config));
+        savedAIConfig = config;
+        console.log('AI配置已自动保存:', redactSecretsForLog(config));
+        if (event?.target?.id?.startsWith('budget-')) {
+            updateAISpendSummary();
+        }
     } catch (error) {
         console.error('自动保存AI配置失败:', error);
     }
@@ -1608,8 +1812,9 @@ async function saveAIConfig() {
     const config = getCurrentAIConfig();
     
     try {
-        await TauriAPI.saveAIConfig(config);
-        console.log('AI配置已保存:', config);
+        warnIfSecretsNotPersisted(await TauriAPI.saveAIConfig(config));
+        savedAIConfig = config;
+        console.log('AI配置已保存:', redactSecretsForLog(config));
     } catch (error) {
         console.error('保存AI配置失败:', error);
         showNotification('保存失败', 'AI配置保存失败');
@@ -1622,7 +1827,8 @@ async function saveAIConfig() {
 async function loadAIConfig() {
     try {
         const config = await TauriAPI.loadAIConfig();
-        console.log('加载AI配置:', config);
+        console.log('加载AI配置:', redactSecretsForLog(config));
+        savedAIConfig = config || {};
         
         // 恢复配置到界面
         if (document.getElementById('api-type')) {
@@ -1640,6 +1846,15 @@ async function loadAIConfig() {
         if (document.getElementById('report-model')) {
             document.getElementById('report-model').value = config.report_model || '';
         }
+        if (document.getElementById('budget-daily')) {
+            document.getElementById('budget-daily').value = config.budget?.daily_limit ?? '';
+        }
+        if (document.getElementById('budget-monthly')) {
+            document.getElementById('budget-monthly').value = config.budget?.monthly_limit ?? '';
+        }
+        updateVisionCheckbox();
+        updateAISpendSummary();
+        updateSecretStorageStatus();
         
         // 如果URL为空，根据API类型设置默认URL
         const apiUrl = config.api_url;
@@ -2087,12 +2302,17 @@ async function viewReport(reportId, type) {
         const date = dateMatch[0];
         
         let reportData;
-        if (type === 'daily') {
-            reportData = await TauriAPI.generateDailyReport(date);
-        } else if (type === 'weekly') {
-            reportData = await TauriAPI.generateWeeklyReport(date);
-        } else {
-            throw new Error('不支持的报告类型');
+        openReportStreamModal(type, date);
+        try {
+            if (type === 'daily') {
+                reportData = await TauriAPI.generateDailyReport(date);
+            } else if (type === 'weekly') {
+                reportData = await TauriAPI.generateWeeklyReport(date);
+            } else {
+                throw new Error('不支持的报告类型');
+            }
+        } finally {
+            closeReportStreamModal();
         }
         
         // 显示报告详情模态框
@@ -2104,6 +2324,60 @@ async function viewReport(reportId, type) {
     }
 }
 
+// 正在生成的报告，用于实时显示AI洞察
+let reportStream = null;
+
+/**
+ * 显示报告生成进度，AI洞察随 report_chunk 事件逐段显示
+ */
+function openReportStreamModal(type, date) {
+    closeReportStreamModal();
+    
+    const modal = document.createElement('div');
+    modal.className = 'fixed inset-0 bg-black bg-opacity-50 flex items-center justify-center z-50 p-4';
+    modal.innerHTML = `
+        <div class="bg-gray-800 rounded-xl p-6 w-full max-w-2xl">
+            <h3 class="text-lg font-semibold text-white mb-4 flex items-center">
+                <i class="fas fa-spinner fa-spin mr-2 text-blue-400"></i>
+                正在生成${type === 'weekly' ? '周' : '日'}报告 (${date})
+            </h3>
+            <pre class="report-stream-text text-gray-200 text-sm whitespace-pre-wrap bg-gray-700 p-3 rounded max-h-96 overflow-y-auto">正在统计数据...</pre>
+        </div>
+    `;
+    document.body.appendChild(modal);
+    
+    reportStream = { type, date, modal, text: '' };
+}
+
+/**
+ * 追加一段AI洞察文本
+ */
+function appendReportChunk(payload) {
+    if (!reportStream || !payload || payload.report_type !== reportStream.type || payload.date !== reportStream.date) {
+        return;
+    }
+    if (payload.done || !payload.chunk) {
+        return;
+    }
+    
+    reportStream.text += payload.chunk;
+    const textEl = reportStream.modal.querySelector('.report-stream-text');
+    if (textEl) {
+        textEl.textContent = reportStream.text;
+        textEl.scrollTop = textEl.scrollHeight;
+    }
+}
+
+/**
+ * 关闭报告生成进度
+ */
+function closeReportStreamModal() {
+    if (reportStream && reportStream.modal.parentNode) {
+        reportStream.modal.remove();
+    }
+    reportStream = null;
+}
+
 /**
  * 下载报告
  */
@@ -2526,6 +2800,9 @@ function initDashboardControls() {
     // 加载保存的当前任务
     loadCurrentTask();
     
+    // 提示词模板编辑器
+    initPromptTemplateEditor();
+    
     // 确保任务选择按钮事件绑定
     const selectTaskBtn = document.getElementById('select-task-btn');
     if (selectTaskBtn) {
@@ -2944,7 +3221,7 @@ async function checkAIConfiguration() {
     try {
         const aiConfig = await TauriAPI.loadAIConfig();
         
-        if (!aiConfig.api_key || aiConfig.api_key.trim() === '') {
+        if (apiTypeRequiresKey(aiConfig.api_type) && (!aiConfig.api_key || aiConfig.api_key.trim() === '')) {
             showNotification('配置错误', '⚠️ AI API密钥未配置，请前往设置页面配置API密钥', 'error');
             // 自动切换到设置页面
             setTimeout(() => {
@@ -3546,29 +3823,123 @@ async function saveMonitoringConfigForStart() {
         
         // 获取AI配置
         const aiConfig = await TauriAPI.loadAIConfig();
-        console.log('加载AI配置:', aiConfig);
+        console.log('加载AI配置:', redactSecretsForLog(aiConfig));
         
         // 获取用户设置（白名单黑名单）
         const userSettings = await TauriAPI.loadUserSettings();
         console.log('加载用户设置:', userSettings);
         
+        // 获取已保存的监控配置（保留界面上没有的高级设置）
+        const savedConfig = await TauriAPI.loadMonitoringConfig() || {};
+        
         // 获取监控间隔设置
         const intervalSelect = document.getElementById('monitoring-interval');
         const intervalMinutes = intervalSelect ? parseInt(intervalSelect.value) : 3;
         
+        // 获取截图范围设置
+        const captureModeSelect = document.getElementById('capture-mode');
+        const captureMode = captureModeSelect ? captureModeSelect.value : (savedConfig.capture_mode || 'PrimaryOnly');
+        
+        // 获取OCR设置
+        const ocrLanguagesSelect = document.getElementById('ocr-languages');
+        const ocrLanguages = ocrLanguagesSelect ? ocrLanguagesSelect.value : (savedConfig.ocr_languages || 'chi_sim+eng');
+        const ocrEngineSelect = document.getElementById('ocr-engine');
+        const ocrEngine = ocrEngineSelect ? ocrEngineSelect.value : (savedConfig.ocr_engine || 'TesseractCli');
+        const tesseractPathInput = document.getElementById('tesseract-path');
+        const tesseractPath = tesseractPathInput ? (tesseractPathInput.value.trim() || null) : (savedConfig.tesseract_path || null);
+        const tessdataDirInput = document.getElementById('tessdata-dir');
+        const tessdataDir = tessdataDirInput ? (tessdataDirInput.value.trim() || null) : (savedConfig.tessdata_dir || null);
+        const visionModeCheckbox = document.getElementById('vision-mode');
+        const visionMode = visionModeCheckbox ? visionModeCheckbox.checked : !!savedConfig.vision_mode;
+        const promptLanguageSelect = document.getElementById('prompt-language');
+        const promptLanguage = promptLanguageSelect ? promptLanguageSelect.value : (savedConfig.prompt_language || 'zh');
+        const cacheCheckbox = document.getElementById('classification-cache-enabled');
+        const classificationCache = {
+            ...(savedConfig.classification_cache || {}),
+            enabled: cacheCheckbox ? cacheCheckbox.checked : savedConfig.classification_cache?.enabled !== false
+        };
+        const fewShotInput = document.getElementById('few-shot-examples');
+        const fewShotExamples = fewShotInput ? parseInt(fewShotInput.value, 10) : NaN;
+        const localModeSelect = document.getElementById('local-classifier-mode');
+        const localThresholdInput = document.getElementById('local-classifier-threshold');
+        const localThreshold = localThresholdInput ? parseFloat(localThresholdInput.value) : NaN;
+        const localClassifier = {
+            ...(savedConfig.local_classifier || {}),
+            mode: localModeSelect ? localModeSelect.value : (savedConfig.local_classifier?.mode || 'fallback'),
+            confidence_threshold: localThreshold >= 0.5 && localThreshold <= 1
+                ? localThreshold
+                : (savedConfig.local_classifier?.confidence_threshold ?? 0.9)
+        };
+        const corrections = {
+            ...(savedConfig.corrections || {}),
+            few_shot_examples: Number.isInteger(fewShotExamples) && fewShotExamples >= 0
+                ? fewShotExamples
+                : (savedConfig.corrections?.few_shot_examples ?? 3)
+        };
+        
+        // 获取专注规则
+        const rulesList = document.getElementById('focus-rules-list');
+        const rules = rulesList ? readFocusRules() : (savedConfig.rules || []);
+        
+        // 获取隐私排除设置
+        const neverCaptureAppsInput = document.getElementById('never-capture-apps');
+        const neverCaptureTitlesInput = document.getElementById('never-capture-titles');
+        const savedNeverCapture = savedConfig.never_capture || {};
+        const neverCapture = {
+            app_names: neverCaptureAppsInput
+                ? splitLines(neverCaptureAppsInput.value)
+                : (savedNeverCapture.app_names || []),
+            title_patterns: neverCaptureTitlesInput
+                ? splitLines(neverCaptureTitlesInput.value)
+                : (savedNeverCapture.title_patterns || [])
+        };
+        
+        // 获取脱敏设置
+        const redactionCheckbox = document.getElementById('redaction-enabled');
+        const redactionPatternsInput = document.getElementById('redaction-patterns');
+        const redactionAllowImagesCheckbox = document.getElementById('redaction-allow-images');
+        const savedRedaction = savedConfig.redaction || {};
+        const redaction = {
+            enabled: redactionCheckbox ? redactionCheckbox.checked : savedRedaction.enabled !== false,
+            custom_patterns: redactionPatternsInput
+                ? splitLines(redactionPatternsInput.value)
+                : (savedRedaction.custom_patterns || []),
+            allow_unredacted_images: redactionAllowImagesCheckbox
+                ? redactionAllowImagesCheckbox.checked
+                : !!savedRedaction.allow_unredacted_images
+        };
+        
         // 创建监控配置
         const monitoringConfig = {
+            ...savedConfig,
             enabled: true, // 重要：启用监控
             interval_minutes: intervalMinutes,
             whitelist: userSettings.whitelist || [],
             blacklist: userSettings.blacklist || [],
-            ai_config: aiConfig
+            ai_config: aiConfig,
+            capture_mode: captureMode,
+            ocr_languages: ocrLanguages,
+            ocr_engine: ocrEngine,
+            tesseract_path: tesseractPath,
+            tessdata_dir: tessdataDir,
+            vision_mode: visionMode,
+            redaction: redaction,
+            never_capture: neverCapture,
+            classification_cache: classificationCache,
+            prompt_language: promptLanguage,
+            corrections: corrections,
+            local_classifier: localClassifier,
+            rules: rules
         };
         
-        console.log('准备保存的监控配置:', monitoringConfig);
+        console.log('准备保存的监控配置:', redactSecretsForLog(monitoringConfig));
         
         // 保存监控配置到后端
-        await TauriAPI.saveMonitoringConfig(monitoringConfig);
+        const saveResult = await TauriAPI.saveMonitoringConfig(monitoringConfig);
+        if (saveResult && saveResult.success === false) {
+            throw new Error(saveResult.message);
+        }
+        warnIfSecretsNotPersisted(saveResult);
         console.log('监控配置已保存');
         
     } catch (error) {
@@ -3580,6 +3951,370 @@ async function saveMonitoringConfigForStart() {
 // 监控配置相关函数
 window.applyMonitoringInterval = applyMonitoringInterval;
 window.triggerManualCheck = triggerManualCheck;
+window.refreshTesseractInfo = refreshTesseractInfo;
+window.clearClassificationCache = clearClassificationCache;
+window.correctMonitoringResult = correctMonitoringResult;
+window.showCorrectionStats = showCorrectionStats;
+window.trainLocalClassifier = trainLocalClassifier;
+window.unlockSecretVault = unlockSecretVault;
+window.savePromptTemplate = savePromptTemplate;
+window.activatePromptTemplate = activatePromptTemplate;
+window.addFocusRuleRow = addFocusRuleRow;
+
+/**
+ * 把多行文本拆分为非空行
+ */
+function splitLines(text) {
+    return text.split('\n').map(line => line.trim()).filter(Boolean);
+}
+
+/**
+ * 在规则列表中添加一行，不传参数时为空白规则
+ */
+function addFocusRuleRow(rule = {}) {
+    const list = document.getElementById('focus-rules-list');
+    if (!list) return;
+    
+    const selectClass = 'bg-gray-800 border border-gray-600 rounded-lg p-1 text-xs text-gray-200';
+    const row = document.createElement('div');
+    row.className = 'focus-rule flex items-center space-x-2';
+    row.dataset.ruleId = rule.id || '';
+    row.dataset.enabled = rule.enabled === false ? 'false' : 'true';
+    row.innerHTML = `
+        <select class="rule-field ${selectClass}">
+            <option value="app">应用</option>
+            <option value="title">标题</option>
+            <option value="domain">域名</option>
+        </select>
+        <select class="rule-match ${selectClass}">
+            <option value="exact">完全相同</option>
+            <option value="glob">通配符</option>
+            <option value="regex">正则</option>
+        </select>
+        <input type="text" class="rule-pattern flex-1 ${selectClass}" placeholder="如 youtube.com">
+        <select class="rule-verdict ${selectClass}">
+            <option value="focused">专注</option>
+            <option value="distracted">分心</option>
+            <option value="ignore">忽略</option>
+        </select>
+        <input type="number" class="rule-priority w-16 ${selectClass}" title="优先级">
+        <button class="text-gray-400 hover:text-red-400 text-xs px-1" title="删除规则"><i class="fas fa-times"></i></button>
+    `;
+    // 匹配内容由用户输入，通过 value 设置而不是拼接到HTML中
+    row.querySelector('.rule-field').value = rule.field || 'app';
+    row.querySelector('.rule-match').value = rule.match || 'exact';
+    row.querySelector('.rule-pattern').value = rule.pattern || '';
+    row.querySelector('.rule-verdict').value = rule.verdict || 'distracted';
+    row.querySelector('.rule-priority').value = rule.priority ?? 0;
+    row.querySelector('button').addEventListener('click', () => row.remove());
+    list.appendChild(row);
+}
+
+/**
+ * 读取规则列表，忽略匹配内容为空的行
+ */
+function readFocusRules() {
+    return Array.from(document.querySelectorAll('#focus-rules-list .focus-rule'))
+        .map(row => ({
+            id: row.dataset.ruleId,
+            enabled: row.dataset.enabled !== 'false',
+            field: row.querySelector('.rule-field').value,
+            match: row.querySelector('.rule-match').value,
+            pattern: row.querySelector('.rule-pattern').value.trim(),
+            verdict: row.querySelector('.rule-verdict').value,
+            priority: parseInt(row.querySelector('.rule-priority').value, 10) || 0
+        }))
+        .filter(rule => rule.pattern);
+}
+
+/**
+ * 加载已保存的OCR设置
+ */
+async function loadOcrSettings() {
+    try {
+        const config = await TauriAPI.loadMonitoringConfig();
+        if (!config) return;
+        
+        const fields = {
+            'ocr-languages': config.ocr_languages,
+            'ocr-engine': config.ocr_engine,
+            'tesseract-path': config.tesseract_path,
+            'tessdata-dir': config.tessdata_dir
+        };
+        Object.entries(fields).forEach(([id, value]) => {
+            const element = document.getElementById(id);
+            if (element && value) element.value = value;
+        });
+        
+        const visionModeCheckbox = document.getElementById('vision-mode');
+        if (visionModeCheckbox) visionModeCheckbox.checked = !!config.vision_mode;
+        
+        const cacheCheckbox = document.getElementById('classification-cache-enabled');
+        if (cacheCheckbox) cacheCheckbox.checked = config.classification_cache?.enabled !== false;
+        
+        const fewShotInput = document.getElementById('few-shot-examples');
+        if (fewShotInput) fewShotInput.value = config.corrections?.few_shot_examples ?? 3;
+        
+        const localModeSelect = document.getElementById('local-classifier-mode');
+        if (localModeSelect) localModeSelect.value = config.local_classifier?.mode || 'fallback';
+        const localThresholdInput = document.getElementById('local-classifier-threshold');
+        if (localThresholdInput) localThresholdInput.value = config.local_classifier?.confidence_threshold ?? 0.9;
+        
+        const promptLanguageSelect = document.getElementById('prompt-language');
+        if (promptLanguageSelect) promptLanguageSelect.value = config.prompt_language || 'zh';
+        
+        const redaction = config.redaction || {};
+        const redactionCheckbox = document.getElementById('redaction-enabled');
+        if (redactionCheckbox) redactionCheckbox.checked = redaction.enabled !== false;
+        const redactionPatternsInput = document.getElementById('redaction-patterns');
+        if (redactionPatternsInput) redactionPatternsInput.value = (redaction.custom_patterns || []).join('\n');
+        const redactionAllowImagesCheckbox = document.getElementById('redaction-allow-images');
+        if (redactionAllowImagesCheckbox) redactionAllowImagesCheckbox.checked = !!redaction.allow_unredacted_images;
+        
+        const neverCapture = config.never_capture || {};
+        const neverCaptureAppsInput = document.getElementById('never-capture-apps');
+        if (neverCaptureAppsInput) neverCaptureAppsInput.value = (neverCapture.app_names || []).join('\n');
+        const neverCaptureTitlesInput = document.getElementById('never-capture-titles');
+        if (neverCaptureTitlesInput) neverCaptureTitlesInput.value = (neverCapture.title_patterns || []).join('\n');
+        
+        const rulesList = document.getElementById('focus-rules-list');
+        if (rulesList) {
+            rulesList.innerHTML = '';
+            (config.rules || []).forEach(rule => addFocusRuleRow(rule));
+        }
+    } catch (error) {
+        console.error('加载OCR设置失败:', error);
+    }
+    
+    await refreshTesseractInfo();
+    await loadPromptTemplates();
+    await updateLocalClassifierMetrics();
+}
+
+// 当前语言的提示词模板（内置模板和用户保存的版本）
+let promptTemplates = [];
+
+/**
+ * 提示词模板的版本标识，与后端 PromptTemplate::label 一致
+ */
+function promptTemplateLabel(template) {
+    return template.builtin
+        ? `${template.language}-builtin-${template.version}`
+        : `${template.language}-v${template.version}`;
+}
+
+/**
+ * 加载所选语言的提示词模板，编辑框显示当前使用的版本
+ */
+async function loadPromptTemplates() {
+    const languageSelect = document.getElementById('prompt-language');
+    const versionSelect = document.getElementById('prompt-template-version');
+    const bodyInput = document.getElementById('prompt-template-body');
+    if (!languageSelect || !versionSelect || !bodyInput) return;
+    
+    try {
+        const language = languageSelect.value;
+        const [templates, active] = await Promise.all([
+            TauriAPI.listPromptTemplates(),
+            TauriAPI.getActivePromptTemplate(language)
+        ]);
+        promptTemplates = templates.filter(template => template.language === language);
+        const activeLabel = promptTemplateLabel(active);
+        
+        versionSelect.innerHTML = '';
+        promptTemplates.forEach(template => {
+            const label = promptTemplateLabel(template);
+            const option = document.createElement('option');
+            option.value = label;
+            option.textContent = label === activeLabel ? `${label}（当前）` : label;
+            versionSelect.appendChild(option);
+        });
+        versionSelect.value = activeLabel;
+        bodyInput.value = active.body;
+    } catch (error) {
+        console.error('加载提示词模板失败:', error);
+    }
+}
+
+/**
+ * 在编辑框中显示所选版本
+ */
+function showSelectedPromptTemplate() {
+    const label = document.getElementById('prompt-template-version')?.value;
+    const template = promptTemplates.find(template => promptTemplateLabel(template) === label);
+    const bodyInput = document.getElementById('prompt-template-body');
+    if (template && bodyInput) bodyInput.value = template.body;
+}
+
+/**
+ * 把编辑框内容保存为新版本并启用
+ */
+async function savePromptTemplate() {
+    const language = document.getElementById('prompt-language')?.value || 'zh';
+    const body = document.getElementById('prompt-template-body')?.value || '';
+    
+    try {
+        const template = await TauriAPI.savePromptTemplate(language, body);
+        showNotification('模板已保存', `已启用 ${promptTemplateLabel(template)}`);
+        await loadPromptTemplates();
+    } catch (error) {
+        console.error('保存提示词模板失败:', error);
+        showNotification('保存失败', `${error.message || error}`);
+    }
+}
+
+/**
+ * 启用所选的模板版本
+ */
+async function activatePromptTemplate() {
+    const label = document.getElementById('prompt-template-version')?.value;
+    if (!label) return;
+    
+    try {
+        await TauriAPI.activatePromptTemplate(label);
+        showNotification('模板已切换', `已启用 ${label}`);
+        await loadPromptTemplates();
+    } catch (error) {
+        console.error('切换提示词模板失败:', error);
+        showNotification('切换失败', `${error.message || error}`);
+    }
+}
+
+/**
+ * 初始化提示词模板编辑器
+ */
+function initPromptTemplateEditor() {
+    document.getElementById('prompt-language')?.addEventListener('change', loadPromptTemplates);
+    document.getElementById('prompt-template-version')?.addEventListener('change', showSelectedPromptTemplate);
+}
+
+/**
+ * 清除分类缓存
+ */
+async function clearClassificationCache() {
+    try {
+        const removed = await TauriAPI.clearClassificationCache();
+        showNotification('缓存已清除', `已删除 ${removed} 条分类缓存`);
+    } catch (error) {
+        console.error('清除分类缓存失败:', error);
+        showNotification('清除失败', '分类缓存清除失败');
+    }
+}
+
+/**
+ * 标注监控结果的实际状态（与模型判断不同即为纠正）
+ */
+async function correctMonitoringResult(timestamp, focusState, button) {
+    const result = await TauriAPI.correctMonitoringResult(timestamp, focusState);
+    if (!result || result.success === false) return;
+    
+    // 更新弹窗中按钮的选中状态
+    button?.parentElement?.querySelectorAll('button').forEach(btn => {
+        btn.className = btn === button
+            ? 'py-1 px-2 rounded text-xs bg-blue-600 text-white'
+            : 'py-1 px-2 rounded text-xs bg-gray-700 text-gray-200 hover:bg-gray-600';
+    });
+    const message = result.focus_state === focusState
+        ? '已确认该判断'
+        : `已纠正为“${getStateText(focusState)}”，之后相似窗口会参考这次纠正`;
+    showNotification('标注已保存', message);
+}
+
+/**
+ * 显示本地分类器在测试集上的准确率
+ */
+function renderLocalClassifierMetrics(metrics) {
+    const text = document.getElementById('local-classifier-metrics');
+    if (!text) return;
+    if (!metrics || !metrics.trained_at) {
+        text.textContent = '尚未训练：需要足够的历史检查结果，每天自动更新';
+        return;
+    }
+    const percent = value => typeof value === 'number' ? `${Math.round(value * 100)}%` : '无';
+    text.textContent = `${metrics.training_samples} 条样本（${metrics.labeled_samples} 条标注）· 测试准确率 ${percent(metrics.accuracy)}`
+        + ` · 置信度≥${metrics.confidence_threshold} 的占 ${percent(metrics.confident_share)}，其准确率 ${percent(metrics.confident_accuracy)}`
+        + ` · 训练于 ${new Date(metrics.trained_at).toLocaleString()}`;
+}
+
+async function updateLocalClassifierMetrics() {
+    const metrics = await TauriAPI.getLocalClassifierMetrics();
+    if (metrics && metrics.success === false) return;
+    renderLocalClassifierMetrics(metrics);
+}
+
+/**
+ * 立即重新训练本地分类器
+ */
+async function trainLocalClassifier() {
+    const metrics = await TauriAPI.trainLocalClassifier();
+    if (!metrics || metrics.success === false) return;
+    renderLocalClassifierMetrics(metrics);
+    showNotification('训练完成', `测试准确率 ${typeof metrics.accuracy === 'number' ? Math.round(metrics.accuracy * 100) + '%' : '无'}`);
+}
+
+/**
+ * 显示各应用的纠正统计
+ */
+async function showCorrectionStats() {
+    const container = document.getElementById('correction-stats');
+    if (!container) return;
+    
+    const stats = await TauriAPI.getCorrectionStats();
+    if (!Array.isArray(stats)) return;
+    
+    container.classList.remove('hidden');
+    if (stats.length === 0) {
+        container.innerHTML = '<p class="text-xs text-gray-500">还没有标注记录，可在检查结果中标注实际状态</p>';
+        return;
+    }
+    container.innerHTML = stats.map(stat => `
+        <div class="text-xs bg-gray-800 rounded p-2">
+            <div class="flex justify-between">
+                <span class="text-gray-200">${stat.application_name}</span>
+                <span class="${stat.disagreement_rate >= 0.5 ? 'text-orange-400' : 'text-gray-400'}">
+                    不一致 ${stat.disagreements}/${stat.labeled}（${Math.round(stat.disagreement_rate * 100)}%）
+                </span>
+            </div>
+            <div class="text-gray-500">共判断 ${stat.judged} 次 · 纠正为专注 ${stat.corrected_to_focused} 次 · 纠正为分心 ${stat.corrected_to_distracted} 次</div>
+            ${stat.suggestion ? `<div class="text-blue-400">${stat.suggestion}</div>` : ''}
+        </div>
+    `).join('');
+}
+
+/**
+ * 检测Tesseract并只启用已安装的语言
+ */
+async function refreshTesseractInfo() {
+    const statusText = document.getElementById('tesseract-status');
+    const languageSelect = document.getElementById('ocr-languages');
+    const tesseractPath = document.getElementById('tesseract-path')?.value.trim() || null;
+    const tessdataDir = document.getElementById('tessdata-dir')?.value.trim() || null;
+    
+    try {
+        const info = await TauriAPI.getTesseractInfo(tesseractPath, tessdataDir);
+        console.log('Tesseract环境:', info);
+        
+        if (languageSelect) {
+            Array.from(languageSelect.options).forEach(option => {
+                const installed = option.value.split('+').every(lang => info.languages.includes(lang));
+                option.disabled = !installed;
+            });
+            if (languageSelect.selectedOptions[0]?.disabled) {
+                const firstInstalled = Array.from(languageSelect.options).find(option => !option.disabled);
+                if (firstInstalled) languageSelect.value = firstInstalled.value;
+            }
+        }
+        
+        if (statusText) {
+            statusText.textContent = `${info.version || 'Tesseract'} · 已安装: ${info.languages.join(', ')}`;
+        }
+    } catch (error) {
+        console.error('检测Tesseract失败:', error);
+        if (statusText) {
+            statusText.textContent = '未检测到Tesseract，请检查路径设置';
+        }
+    }
+}
 
 /**
  * 应用监控频率设置
@@ -3669,6 +4404,36 @@ function updateDashboardFocusState(result) {
     }
 }
 
+/**
+ * OCR状态说明
+ */
+function getOcrStatusText(status) {
+    switch (status) {
+        case 'NoText': return '未识别到文字';
+        case 'Disabled': return 'OCR已关闭';
+        case 'Unavailable': return 'OCR不可用';
+        default: return '已识别';
+    }
+}
+
+/**
+ * 活动类别说明
+ */
+function getActivityCategoryText(category) {
+    const names = {
+        work: '工作',
+        study: '学习',
+        communication: '沟通',
+        reference: '查阅资料',
+        entertainment: '娱乐',
+        social_media: '社交媒体',
+        shopping: '购物',
+        news: '新闻资讯',
+        other: '其他'
+    };
+    return names[category] || category;
+}
+
 /**
  * 显示监控结果详情
  */
@@ -3685,7 +4450,11 @@ function showMonitoringResult(result) {
             <div class="space-y-3">
                 <div class="flex items-center justify-between">
                     <span class="text-gray-300">状态:</span>
+                    ${result.ai_unavailable ? `
+                    <span class="font-bold text-orange-400">AI服务不可用</span>
+                    ` : `
                     <span class="font-bold ${getStateColor(result.focus_state)}">${getStateText(result.focus_state)}</span>
+                    `}
                 </div>
                 <div class="flex items-center justify-between">
                     <span class="text-gray-300">置信度:</span>
@@ -3697,12 +4466,82 @@ function showMonitoringResult(result) {
                     <span class="text-white text-sm">${result.application_name}</span>
                 </div>
                 ` : ''}
+                ${result.activity_category ? `
+                <div class="flex items-center justify-between">
+                    <span class="text-gray-300">活动类别:</span>
+                    <span class="text-white text-sm">${getActivityCategoryText(result.activity_category)}</span>
+                </div>
+                ` : ''}
+                ${typeof result.task_relevance === 'number' ? `
+                <div class="flex items-center justify-between">
+                    <span class="text-gray-300">任务相关度:</span>
+                    <span class="text-white text-sm">${Math.round(result.task_relevance * 100)}%</span>
+                </div>
+                ` : ''}
+                ${result.answered_by && result.answered_by.fallback ? `
+                <div class="flex items-center justify-between">
+                    <span class="text-gray-300">备用服务:</span>
+                    <span class="text-yellow-400 text-sm">${result.answered_by.api_type} / ${result.answered_by.model}</span>
+                </div>
+                ` : ''}
+                ${result.prompt_version ? `
+                <div class="flex items-center justify-between">
+                    <span class="text-gray-300">提示词模板:</span>
+                    <span class="text-white text-sm">${result.prompt_version}</span>
+                </div>
+                ` : ''}
+                ${result.matched_rule ? `
+                <div class="flex items-center justify-between">
+                    <span class="text-gray-300">命中规则:</span>
+                    <span class="text-blue-400 text-sm">${result.matched_rule}，未调用AI</span>
+                </div>
+                ` : ''}
+                ${result.local_classifier ? `
+                <div class="flex items-center justify-between">
+                    <span class="text-gray-300">判断来源:</span>
+                    <span class="text-blue-400 text-sm">本地分类器，未调用AI</span>
+                </div>
+                ` : ''}
+                ${result.cache_hit ? `
+                <div class="flex items-center justify-between">
+                    <span class="text-gray-300">分类缓存:</span>
+                    <span class="text-blue-400 text-sm">复用相同窗口的判断，未调用AI</span>
+                </div>
+                ` : ''}
+                ${result.budget_mode ? `
+                <div class="flex items-center justify-between">
+                    <span class="text-gray-300">AI预算:</span>
+                    <span class="text-orange-400 text-sm">已超出预算，${result.budget_mode !== 'AppRules' ? '改用本地模型' : result.local_classifier ? '应用规则无法判断，改用本地分类器' : '仅按应用规则判断'}</span>
+                </div>
+                ` : ''}
+                ${result.private ? `
+                <div class="flex items-center justify-between">
+                    <span class="text-gray-300">隐私窗口:</span>
+                    <span class="text-yellow-400 text-sm">未截图，未调用AI</span>
+                </div>
+                ` : result.ocr_status && result.ocr_status !== 'Recognized' ? `
+                <div class="flex items-center justify-between">
+                    <span class="text-gray-300">屏幕文字:</span>
+                    <span class="text-yellow-400 text-sm">${getOcrStatusText(result.ocr_status)}，仅依据窗口标题判断</span>
+                </div>
+                ` : ''}
                 ${result.ai_analysis ? `
                 <div class="mt-4">
-                    <span class="text-gray-300 text-sm">AI分析:</span>
+                    <span class="text-gray-300 text-sm">判断理由:</span>
                     <p class="text-gray-200 text-sm mt-1 bg-gray-700 p-2 rounded">${result.ai_analysis}</p>
                 </div>
                 ` : ''}
+                ${!result.private && !result.ai_unavailable ? `
+                <div class="flex items-center justify-between">
+                    <span class="text-gray-300 text-sm">实际状态:</span>
+                    <div class="flex space-x-1">
+                        ${['Focused', 'Distracted', 'SeverelyDistracted'].map(state => `
+                        <button class="py-1 px-2 rounded text-xs ${result.user_label === state ? 'bg-blue-600 text-white' : 'bg-gray-700 text-gray-200 hover:bg-gray-600'}"
+                            onclick="correctMonitoringResult('${result.timestamp}', '${state}', this)">${getStateText(state)}</button>
+                        `).join('')}
+                    </div>
+                </div>
+                ` : ''}
             </div>
             <div class="mt-6 flex justify-end">
                 <button onclick="this.closest('.fixed').remove()" class="bg-blue-600 hover:bg-blue-700 text-white font-bold py-2 px-4 rounded-lg">
@@ -164,15 +164,75 @@ export const TauriAPI = {
         return await safeInvoke('refresh_models', { config }, '刷新模型列表');
     },
     
+    async getAIServiceStatus(config) {
+        return await safeInvoke('get_ai_service_status', { config }, '获取AI服务状态');
+    },
+    
+    async getAISpendReport() {
+        return await safeInvoke('get_ai_spend_report', {}, '获取AI用量和花费');
+    },
+    
+    async getSecretStorageStatus() {
+        return await safeInvoke('get_secret_storage_status', {}, '获取密钥存储状态');
+    },
+    
+    async unlockSecretVault(passphrase) {
+        return await safeInvoke('unlock_secret_vault', { passphrase }, '解锁密钥库');
+    },
+    
     // 监控配置管理
     async saveMonitoringConfig(config) {
         return await safeInvoke('save_monitoring_config', { config }, '保存监控配置');
     },
     
+    async clearClassificationCache() {
+        return await safeInvoke('clear_classification_cache', {}, '清除分类缓存');
+    },
+    
+    async correctMonitoringResult(timestamp, focusState, note = null) {
+        return await safeInvoke('correct_monitoring_result', { timestamp, focus_state: focusState, note }, '标注监控结果');
+    },
+    
+    async getCorrectionStats() {
+        return await safeInvoke('get_correction_stats', {}, '获取纠正统计');
+    },
+    
+    async trainLocalClassifier() {
+        return await safeInvoke('train_local_classifier', {}, '训练本地分类器');
+    },
+    
+    async getLocalClassifierMetrics() {
+        return await safeInvoke('get_local_classifier_metrics', {}, '获取本地分类器准确率');
+    },
+    
+    async listPromptTemplates() {
+        return await safeInvoke('list_prompt_templates', {}, '获取提示词模板');
+    },
+    
+    async getActivePromptTemplate(language) {
+        return await safeInvoke('get_active_prompt_template', { language }, '获取当前提示词模板');
+    },
+    
+    async savePromptTemplate(language, body) {
+        return await safeInvoke('save_prompt_template', { language, body }, '保存提示词模板');
+    },
+    
+    async activatePromptTemplate(label) {
+        return await safeInvoke('activate_prompt_template', { label }, '切换提示词模板');
+    },
+    
     async loadMonitoringConfig() {
         return await safeInvoke('load_monitoring_config', {}, '加载监控配置');
     },
     
+    async getTesseractInfo(tesseractPath = null, tessdataDir = null) {
+        // 未安装Tesseract是常见情况，由调用方显示状态而不是弹出错误
+        return await invokeCommand('get_tesseract_info', {
+            tesseractPath,
+            tessdataDir
+        });
+    },
+    
     async getCurrentFocusState() {
         return await safeInvoke('get_current_focus_state', {}, '获取当前专注状态');
     },
@@ -248,6 +308,11 @@ export const TauriEvents = {
     // 分心干预事件
     async onDistractionIntervention(handler) {
         return await this.listen('distraction_intervention', handler);
+    },
+    
+    // 报告AI洞察流式输出事件
+    async onReportChunk(handler) {
+        return await this.listen('report_chunk', handler);
     }
 };
 